@@ -0,0 +1,536 @@
+use axum::extract::rejection::{JsonRejection, QueryRejection};
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, Method, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use redis::{RedisError, RedisResult};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeSet, HashMap};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::task::{self, JoinHandle};
+use crate::config_service::{Backend, ConfigService};
+use crate::job_model::{FailedJobFilter, JobData, JobSpec, JobState};
+use crate::metrics_model::{MetricKind, MetricsRange};
+use crate::QueueServiceTrait;
+
+/// Default number of jobs or log lines returned by a page.
+pub const DEFAULT_PAGE_LIMIT: usize = 20;
+
+/// Maximum number of jobs or log lines returned by a page.
+pub const MAX_PAGE_LIMIT: usize = 1000;
+
+/// Default number of minutes of metrics returned.
+pub const DEFAULT_METRICS_MINUTES: i64 = 60;
+
+/// Maximum number of minutes of metrics returned, one day.
+pub const MAX_METRICS_MINUTES: i64 = 24 * 60;
+
+/// Header required on the requests changing data without a JSON body, e.g.
+/// `X-Requested-With: XMLHttpRequest`, which cross-site forms cannot send.
+pub const REQUESTED_WITH_HEADER: &str = "x-requested-with";
+
+/// The error of an API request, answered as `{"error": message}` with its status.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiError {
+    /// The HTTP status of the response.
+    pub status: StatusCode,
+    /// A description of the error.
+    pub message: String,
+}
+
+impl ApiError {
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self { status: StatusCode::BAD_REQUEST, message: message.into() }
+    }
+
+    fn not_found(message: impl Into<String>) -> Self {
+        Self { status: StatusCode::NOT_FOUND, message: message.into() }
+    }
+
+    fn forbidden(message: impl Into<String>) -> Self {
+        Self { status: StatusCode::FORBIDDEN, message: message.into() }
+    }
+}
+
+impl From<RedisError> for ApiError {
+    fn from(e: RedisError) -> Self {
+        Self { status: StatusCode::INTERNAL_SERVER_ERROR, message: format!("queue backend error: {}", e) }
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(e: JsonRejection) -> Self {
+        Self { status: e.status(), message: e.body_text() }
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(e: QueryRejection) -> Self {
+        Self::bad_request(e.body_text())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(json!({ "error": self.message }))).into_response()
+    }
+}
+
+type ApiResult<T> = Result<T, ApiError>;
+
+/// The body of an enqueue request: one job or an array of jobs.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EnqueueRequest {
+    One(JobSpec),
+    Many(Vec<JobSpec>),
+}
+
+/// The query of a paginated request.
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(deny_unknown_fields)]
+struct PageQuery {
+    #[serde(default)]
+    offset: usize,
+    #[serde(default = "default_page_limit")]
+    limit: usize,
+}
+
+fn default_page_limit() -> usize {
+    DEFAULT_PAGE_LIMIT
+}
+
+impl PageQuery {
+    fn validate(self) -> ApiResult<Self> {
+        if self.limit == 0 || self.limit > MAX_PAGE_LIMIT {
+            return Err(ApiError::bad_request(format!("limit must be between 1 and {}", MAX_PAGE_LIMIT)));
+        }
+        Ok(self)
+    }
+}
+
+/// The query of a job listing, failed jobs can be filtered by failure reason and age.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+struct ListQuery {
+    #[serde(default = "default_list_state")]
+    state: JobState,
+    #[serde(default)]
+    offset: usize,
+    #[serde(default = "default_page_limit")]
+    limit: usize,
+    #[serde(default)]
+    reason: Option<String>,
+    #[serde(default)]
+    failed_within: Option<u64>,
+}
+
+/// The query selecting the failed jobs of a bulk request.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+struct FailedQuery {
+    #[serde(default)]
+    reason: Option<String>,
+    #[serde(default)]
+    failed_within: Option<u64>,
+}
+
+/// The query of a move request: the target queue, and the failed jobs to move for bulk requests.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+struct MoveQuery {
+    to: String,
+    #[serde(default)]
+    reason: Option<String>,
+    #[serde(default)]
+    failed_within: Option<u64>,
+}
+
+fn default_list_state() -> JobState {
+    JobState::Waiting
+}
+
+impl MoveQuery {
+    fn validate(self) -> ApiResult<Self> {
+        if self.to.is_empty() {
+            return Err(ApiError::bad_request("to must name the target queue"));
+        }
+        Ok(self)
+    }
+}
+
+/// The query of a metrics request.
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(deny_unknown_fields)]
+struct MetricsQuery {
+    #[serde(default = "default_metric_kind")]
+    kind: MetricKind,
+    #[serde(default = "default_metrics_minutes")]
+    minutes: i64,
+}
+
+fn default_metric_kind() -> MetricKind {
+    MetricKind::Completed
+}
+
+fn default_metrics_minutes() -> i64 {
+    DEFAULT_METRICS_MINUTES
+}
+
+/// A page of jobs.
+#[derive(Serialize)]
+struct JobPage {
+    state: JobState,
+    offset: usize,
+    limit: usize,
+    jobs: Vec<JobData>,
+}
+
+/// Service exposing the queues of a backend over an HTTP REST API.
+///
+/// All routes answer JSON, and errors are answered as `{"error": message}` with
+/// status 400 for invalid input, 404 for unknown jobs and 500 for backend errors:
+///
+/// * `GET /queues` - the queues holding any data, with their job counts and pause state.
+/// * `GET /queues/{queue}` - the job counts and pause state of the queue.
+/// * `GET /queues/{queue}/metrics?kind=&minutes=` - the stored per-minute metrics of the queue, completed jobs by default.
+/// * `POST /queues/{queue}/jobs` - enqueues a `{id, message, priority, delay, retries}` job, or an array of them.
+/// * `GET /queues/{queue}/jobs?state=&offset=&limit=` - lists the jobs in a state, waiting by default;
+///   failed jobs can be filtered with `reason=` (a case-insensitive substring) and `failed_within=` (seconds).
+/// * `GET /queues/{queue}/jobs/{id}` - the state and data of a job.
+/// * `DELETE /queues/{queue}/jobs/{id}` - removes a job.
+/// * `GET /queues/{queue}/jobs/{id}/progress` - the progress of a job.
+/// * `GET /queues/{queue}/jobs/{id}/logs?offset=&limit=` - the log lines of a job.
+/// * `POST /queues/{queue}/jobs/{id}/retry` - moves a failed job back to the waiting jobs.
+/// * `POST /queues/{queue}/retry?reason=&failed_within=` - moves the failed jobs, all by default, back to the waiting jobs.
+/// * `POST /queues/{queue}/jobs/{id}/move?to=` - moves a failed job to the waiting jobs of another queue.
+/// * `POST /queues/{queue}/move?to=&reason=&failed_within=` - moves the failed jobs, all by default, to another queue.
+/// * `POST /queues/{queue}/pause` and `POST /queues/{queue}/resume` - pauses or resumes the queue.
+///
+/// Each request works on its own handle on the queue service of its queue, so requests are
+/// served concurrently.
+#[derive(Clone)]
+pub struct ApiService {
+    queue_service: Box<dyn QueueServiceTrait>,
+    queue_services: Arc<HashMap<String, Box<dyn QueueServiceTrait>>>,
+}
+
+impl ApiService {
+    /// Creates a new `ApiService`.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_service` - The queue service backing the API.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of `ApiService`.
+    pub fn new(queue_service: Box<dyn QueueServiceTrait>) -> Self {
+        Self { queue_service, queue_services: Arc::new(HashMap::new()) }
+    }
+
+    /// Creates the `ApiService` of the configured backends.
+    ///
+    /// Queues listed in `queue_backends` are served by the service of their backend, which is
+    /// created once per backend and shared by its queues.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The configuration of the backends.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the `ApiService`.
+    pub async fn from_config(config: &ConfigService) -> RedisResult<Self> {
+        let mut api = Self::new(config.queue_service().await?);
+        let mut backend_services: HashMap<Backend, Box<dyn QueueServiceTrait>> = HashMap::new();
+        for (queue, backend) in &config.queue_backends {
+            if *backend == config.backend {
+                continue;
+            }
+            let queue_service = match backend_services.get(backend) {
+                Some(queue_service) => queue_service.clone(),
+                None => {
+                    let queue_service = config.queue_service_for(queue).await?;
+                    backend_services.insert(*backend, queue_service.clone());
+                    queue_service
+                }
+            };
+            api = api.with_queue_service(queue, queue_service);
+        }
+        Ok(api)
+    }
+
+    /// Serves a queue with another queue service than the one given to `new`.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue.
+    /// * `queue_service` - The queue service storing the queue.
+    ///
+    /// # Returns
+    ///
+    /// The `ApiService` serving the queue with the given service.
+    pub fn with_queue_service(mut self, queue_name: &str, queue_service: Box<dyn QueueServiceTrait>) -> Self {
+        Arc::make_mut(&mut self.queue_services).insert(queue_name.to_string(), queue_service);
+        self
+    }
+
+    /// Returns a handle on the queue service storing a queue.
+    fn queue_service(&self, queue_name: &str) -> Box<dyn QueueServiceTrait> {
+        self.queue_services.get(queue_name).unwrap_or(&self.queue_service).clone()
+    }
+
+    /// Builds the router of the API, which can be served or called in-process.
+    ///
+    /// With the `dashboard` feature, the router also serves the web dashboard under `/dashboard`.
+    pub fn router(self) -> Router {
+        let router = Router::new()
+            .route("/queues", get(list_queues))
+            .route("/queues/{queue}", get(get_queue))
+            .route("/queues/{queue}/metrics", get(get_metrics))
+            .route("/queues/{queue}/jobs", get(list_jobs).post(add_jobs))
+            .route("/queues/{queue}/jobs/{id}", get(get_job).delete(remove_job))
+            .route("/queues/{queue}/jobs/{id}/progress", get(get_progress))
+            .route("/queues/{queue}/jobs/{id}/logs", get(get_logs))
+            .route("/queues/{queue}/jobs/{id}/retry", post(retry_job))
+            .route("/queues/{queue}/jobs/{id}/cancel", post(cancel_job))
+            .route("/queues/{queue}/jobs/{id}/move", post(move_job))
+            .route("/queues/{queue}/retry", post(retry_all))
+            .route("/queues/{queue}/move", post(move_all))
+            .route("/queues/{queue}/pause", post(pause_queue))
+            .route("/queues/{queue}/resume", post(resume_queue))
+            .fallback(|| async { ApiError::not_found("no such route") })
+            .layer(middleware::from_fn(require_script_request))
+            .with_state(self);
+        #[cfg(feature = "dashboard")]
+        let router = router.merge(crate::dashboard_service::router());
+        router
+    }
+
+    /// Serves the API on the given address.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address to listen on, port `0` picks a free port.
+    ///
+    /// # Returns
+    ///
+    /// An `io::Result` containing the bound address and the handle of the server task.
+    pub async fn serve(self, addr: SocketAddr) -> std::io::Result<(SocketAddr, JoinHandle<()>)> {
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        let router = self.router();
+        let handle = task::spawn(async move {
+            if let Err(e) = axum::serve(listener, router).await {
+                diagnostic!(error, "Error serving the API: {}", e);
+            }
+        });
+        Ok((local_addr, handle))
+    }
+}
+
+/// Rejects the requests changing data that a cross-site form could send: a browser only lets scripts
+/// of other origins set a custom header or a JSON content type after a CORS preflight, which the API
+/// does not answer.
+async fn require_script_request(request: Request, next: Next) -> Response {
+    let safe_method = matches!(*request.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+    let headers = request.headers();
+    let is_json = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.trim_start().to_ascii_lowercase().starts_with("application/json"));
+    if safe_method || is_json || headers.contains_key(REQUESTED_WITH_HEADER) {
+        next.run(request).await
+    } else {
+        ApiError::forbidden(format!("requests changing data need a JSON body or an {} header", REQUESTED_WITH_HEADER))
+            .into_response()
+    }
+}
+
+/// Returns the job counts and pause state of a queue.
+async fn queue_summary(queue_service: &mut dyn QueueServiceTrait, queue: &str) -> ApiResult<Value> {
+    let waiting = queue_service.count_jobs_by_state(queue, JobState::Waiting).await?;
+    let failed = queue_service.count_jobs_by_state(queue, JobState::Failed).await?;
+    let paused = queue_service.is_paused(queue).await?;
+    Ok(json!({ "queue": queue, "paused": paused, "counts": { "waiting": waiting, "failed": failed } }))
+}
+
+async fn list_queues(State(mut api): State<ApiService>) -> ApiResult<Json<Value>> {
+    let mut names: BTreeSet<String> = api.queue_service.list_queues().await?.into_iter().collect();
+    for queue_service in api.queue_services.values() {
+        names.extend(queue_service.clone().list_queues().await?);
+    }
+    let mut queues = Vec::new();
+    for queue in names {
+        queues.push(queue_summary(&mut *api.queue_service(&queue), &queue).await?);
+    }
+    Ok(Json(json!({ "queues": queues })))
+}
+
+async fn get_queue(State(api): State<ApiService>, Path(queue): Path<String>) -> ApiResult<Json<Value>> {
+    Ok(Json(queue_summary(&mut *api.queue_service(&queue), &queue).await?))
+}
+
+async fn get_metrics(
+    State(api): State<ApiService>,
+    Path(queue): Path<String>,
+    query: Result<Query<MetricsQuery>, QueryRejection>,
+) -> ApiResult<Json<Value>> {
+    let query = query?.0;
+    if !(1..=MAX_METRICS_MINUTES).contains(&query.minutes) {
+        return Err(ApiError::bad_request(format!("minutes must be between 1 and {}", MAX_METRICS_MINUTES)));
+    }
+    let range = MetricsRange::last_minutes(query.minutes);
+    let points = api.queue_service(&queue).get_metrics(&queue, query.kind, range).await?;
+    Ok(Json(json!({ "queue": queue, "kind": query.kind, "points": points })))
+}
+
+async fn add_jobs(
+    State(api): State<ApiService>,
+    Path(queue): Path<String>,
+    body: Result<Json<EnqueueRequest>, JsonRejection>,
+) -> ApiResult<(StatusCode, Json<Value>)> {
+    match body?.0 {
+        EnqueueRequest::One(spec) => {
+            let job = spec.into_job().map_err(ApiError::bad_request)?;
+            api.queue_service(&queue).add_job(&queue, job.clone()).await?;
+            Ok((StatusCode::CREATED, Json(json!(job))))
+        }
+        EnqueueRequest::Many(specs) => {
+            let jobs = specs
+                .into_iter()
+                .enumerate()
+                .map(|(index, spec)| spec.into_job().map_err(|e| ApiError::bad_request(format!("invalid job #{}: {}", index + 1, e))))
+                .collect::<ApiResult<Vec<JobData>>>()?;
+            let ids: Vec<String> = jobs.iter().map(|job| job.id.clone()).collect();
+            api.queue_service(&queue).add_jobs(&queue, jobs).await?;
+            Ok((StatusCode::CREATED, Json(json!({ "added": ids.len(), "ids": ids }))))
+        }
+    }
+}
+
+async fn list_jobs(
+    State(api): State<ApiService>,
+    Path(queue): Path<String>,
+    query: Result<Query<ListQuery>, QueryRejection>,
+) -> ApiResult<Json<JobPage>> {
+    let query = query?.0;
+    let page = PageQuery { offset: query.offset, limit: query.limit }.validate()?;
+    let filter = FailedJobFilter { reason: query.reason, failed_within_secs: query.failed_within };
+    let jobs = match query.state {
+        JobState::Failed => api.queue_service(&queue).list_failed_jobs(&queue, &filter, page.offset, page.limit).await?,
+        _ if filter != FailedJobFilter::default() => {
+            return Err(ApiError::bad_request("reason and failed_within only apply to failed jobs"));
+        }
+        state => api.queue_service(&queue).list_jobs(&queue, state, page.offset, page.limit).await?,
+    };
+    Ok(Json(JobPage { state: query.state, offset: page.offset, limit: page.limit, jobs }))
+}
+
+async fn get_job(State(api): State<ApiService>, Path((queue, id)): Path<(String, String)>) -> ApiResult<Json<Value>> {
+    let (state, job) = api
+        .queue_service(&queue)
+        .get_job(&queue, &id)
+        .await?
+        .ok_or_else(|| job_not_found(&queue, &id))?;
+    Ok(Json(json!({ "state": state, "job": job })))
+}
+
+async fn remove_job(State(api): State<ApiService>, Path((queue, id)): Path<(String, String)>) -> ApiResult<Json<Value>> {
+    if !api.queue_service(&queue).remove_job(&queue, &id).await? {
+        return Err(job_not_found(&queue, &id));
+    }
+    Ok(Json(json!({ "removed": id })))
+}
+
+async fn get_progress(State(api): State<ApiService>, Path((queue, id)): Path<(String, String)>) -> ApiResult<Json<Value>> {
+    let progress = api
+        .queue_service(&queue)
+        .get_job_progress(&queue, &id)
+        .await?
+        .ok_or_else(|| ApiError::not_found(format!("no progress for job {} in {}", id, queue)))?;
+    Ok(Json(json!({ "id": id, "progress": progress })))
+}
+
+async fn get_logs(
+    State(api): State<ApiService>,
+    Path((queue, id)): Path<(String, String)>,
+    query: Result<Query<PageQuery>, QueryRejection>,
+) -> ApiResult<Json<Value>> {
+    let page = query?.0.validate()?;
+    let start = page.offset.min(isize::MAX as usize) as isize;
+    let end = start.saturating_add(page.limit as isize - 1);
+    let lines = api.queue_service(&queue).get_job_logs(&queue, &id, start, end).await?;
+    Ok(Json(json!({ "id": id, "offset": page.offset, "limit": page.limit, "lines": lines })))
+}
+
+async fn retry_job(State(api): State<ApiService>, Path((queue, id)): Path<(String, String)>) -> ApiResult<Json<Value>> {
+    if !api.queue_service(&queue).retry_job(&queue, &id).await? {
+        return Err(ApiError::not_found(format!("no failed job {} in {}", id, queue)));
+    }
+    Ok(Json(json!({ "retried": [id] })))
+}
+
+async fn cancel_job(State(api): State<ApiService>, Path((queue, id)): Path<(String, String)>) -> ApiResult<(StatusCode, Json<Value>)> {
+    api.queue_service(&queue).cancel_job(&queue, &id).await?;
+    Ok((StatusCode::ACCEPTED, Json(json!({ "cancelled": id }))))
+}
+
+async fn move_job(
+    State(api): State<ApiService>,
+    Path((queue, id)): Path<(String, String)>,
+    query: Result<Query<MoveQuery>, QueryRejection>,
+) -> ApiResult<Json<Value>> {
+    let query = query?.0.validate()?;
+    if query.reason.is_some() || query.failed_within.is_some() {
+        return Err(ApiError::bad_request("reason and failed_within only apply to bulk moves"));
+    }
+    let mut target = api.queue_service(&query.to);
+    if !api.queue_service(&queue).move_failed_job(&queue, &id, &mut *target, &query.to).await? {
+        return Err(ApiError::not_found(format!("no failed job {} in {}", id, queue)));
+    }
+    Ok(Json(json!({ "moved": [id], "to": query.to })))
+}
+
+async fn retry_all(
+    State(api): State<ApiService>,
+    Path(queue): Path<String>,
+    query: Result<Query<FailedQuery>, QueryRejection>,
+) -> ApiResult<Json<Value>> {
+    let query = query?.0;
+    let filter = FailedJobFilter { reason: query.reason, failed_within_secs: query.failed_within };
+    let retried = api.queue_service(&queue).retry_failed_jobs(&queue, &filter).await?;
+    Ok(Json(json!({ "retried": retried })))
+}
+
+async fn move_all(
+    State(api): State<ApiService>,
+    Path(queue): Path<String>,
+    query: Result<Query<MoveQuery>, QueryRejection>,
+) -> ApiResult<Json<Value>> {
+    let query = query?.0.validate()?;
+    let filter = FailedJobFilter { reason: query.reason, failed_within_secs: query.failed_within };
+    let mut target = api.queue_service(&query.to);
+    let moved = api.queue_service(&queue).move_failed_jobs(&queue, &filter, &mut *target, &query.to).await?;
+    Ok(Json(json!({ "moved": moved, "to": query.to })))
+}
+
+async fn pause_queue(State(api): State<ApiService>, Path(queue): Path<String>) -> ApiResult<Json<Value>> {
+    api.queue_service(&queue).pause_queue(&queue).await?;
+    Ok(Json(json!({ "queue": queue, "paused": true })))
+}
+
+async fn resume_queue(State(api): State<ApiService>, Path(queue): Path<String>) -> ApiResult<Json<Value>> {
+    api.queue_service(&queue).resume_queue(&queue).await?;
+    Ok(Json(json!({ "queue": queue, "paused": false })))
+}
+
+fn job_not_found(queue: &str, id: &str) -> ApiError {
+    ApiError::not_found(format!("no job {} in {}", id, queue))
+}
@@ -0,0 +1,49 @@
+use bullmq_rust::cli_service::{Cli, Command, EXIT_FAILURE};
+use bullmq_rust::config_service::ConfigService;
+use clap::Parser;
+use serde_json::json;
+use std::io;
+use std::process;
+
+#[tokio::main]
+async fn main() {
+    // Usage errors are reported by clap with exit code 2.
+    let mut cli = Cli::parse();
+
+    #[cfg(feature = "tracing")]
+    let tracing_guard = bullmq_rust::tracing_service::init_tracing_from_env("bullmq");
+
+    let result = match ConfigService::load() {
+        Ok(config) => {
+            // The timeout configured for the queue applies when `--job-timeout` is not given.
+            if let Command::Work { queue, job_timeout, .. } = &mut cli.command {
+                *job_timeout = job_timeout.or_else(|| config.job_timeout_for(queue).map(|timeout| timeout.as_millis() as u64));
+            }
+            // `move` adds the jobs through the backend serving the target queue.
+            let target_service = match cli.command.target_queue() {
+                Some(target_queue) => config.queue_service_for(target_queue).await.map(Some),
+                None => Ok(None),
+            };
+            match (config.queue_service_for(cli.command.queue()).await, target_service) {
+                (Ok(mut queue_service), Ok(target_service)) => cli
+                    .execute_with_target(&mut *queue_service, target_service, &mut io::stdout())
+                    .await
+                    .map_err(|e| (e.to_string(), e.exit_code())),
+                (Err(e), _) | (_, Err(e)) => Err((format!("cannot connect to the queue backend: {}", e), EXIT_FAILURE)),
+            }
+        }
+        Err(e) => Err((format!("invalid configuration: {}", e), EXIT_FAILURE)),
+    };
+
+    if let Err((message, code)) = result {
+        if cli.json {
+            eprintln!("{}", json!({ "error": message, "code": code }));
+        } else {
+            eprintln!("bullmq: {}", message);
+        }
+        // `process::exit` skips destructors: flush the exported spans first.
+        #[cfg(feature = "tracing")]
+        drop(tracing_guard);
+        process::exit(code);
+    }
+}
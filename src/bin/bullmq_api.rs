@@ -0,0 +1,46 @@
+use bullmq_rust::api_service::ApiService;
+use bullmq_rust::config_service::ConfigService;
+use clap::Parser;
+use std::net::SocketAddr;
+use std::process;
+
+/// HTTP REST API managing the queues and jobs of the configured backends.
+#[derive(Parser, Debug)]
+#[command(name = "bullmq_api", version, about = "Serve BullMQ queues over HTTP")]
+struct Args {
+    /// The address to listen on.
+    #[arg(long, short, default_value = "127.0.0.1:3000")]
+    addr: SocketAddr,
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+
+    #[cfg(feature = "tracing")]
+    let _tracing_guard = bullmq_rust::tracing_service::init_tracing_from_env("bullmq_api");
+
+    let config = ConfigService::load().unwrap_or_else(|e| {
+        eprintln!("bullmq_api: invalid configuration: {}", e);
+        process::exit(1);
+    });
+    let api = ApiService::from_config(&config).await.unwrap_or_else(|e| {
+        eprintln!("bullmq_api: cannot connect to the queue backend: {}", e);
+        process::exit(1);
+    });
+
+    match api.serve(args.addr).await {
+        Ok((local_addr, _)) => {
+            eprintln!("Serving the API on http://{}", local_addr);
+            #[cfg(feature = "dashboard")]
+            eprintln!("Serving the dashboard on http://{}/dashboard/", local_addr);
+        }
+        Err(e) => {
+            eprintln!("bullmq_api: cannot listen on {}: {}", args.addr, e);
+            process::exit(1);
+        }
+    }
+
+    // Keep the main function alive until interrupted
+    let _ = tokio::signal::ctrl_c().await;
+}
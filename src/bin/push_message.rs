@@ -1,9 +1,10 @@
-use std::sync::{Arc, Mutex};
-use std::env;
-use bullmq_rust::job_model::JobData;
-use bullmq_rust::queue_service::QueueService;
-use bullmq_rust::QueueServiceTrait;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::process;
+use bullmq_rust::config_service::ConfigService;
+use bullmq_rust::job_model::{generate_job_id, parse_job_lines, JobData};
 use chrono::Utc;
+use clap::Parser;
 use redis::RedisResult;
 use serde::{Deserialize, Serialize};
 
@@ -14,74 +15,89 @@ struct DataModel {
     message_enum: i64
 }
 
+/// Adds a test job, or the jobs of a JSON Lines file, to a queue.
+#[derive(Parser, Debug)]
+#[command(name = "push_message", about = "Add jobs to a queue")]
+struct Args {
+    /// The name of the queue.
+    #[arg(short = 'q', long = "queue_name", default_value = "my_queue")]
+    queue_name: String,
+    /// The type of the message.
+    #[arg(short = 't', long = "message_type", default_value = "TEST")]
+    message_type: String,
+    /// The content of the message.
+    #[arg(short = 'c', long = "message_content", default_value = "ContentTEST")]
+    message_content: String,
+    /// The enum value of the message.
+    #[arg(short = 'e', long = "message_enum", default_value_t = 3)]
+    message_enum: i64,
+    /// Add one job per line of a JSON Lines file, `-` for stdin.
+    #[arg(short = 'f', long = "file", value_name = "PATH")]
+    file: Option<PathBuf>,
+    /// Add one job per line read from stdin.
+    #[arg(long, conflicts_with = "file")]
+    stdin: bool,
+}
+
 #[tokio::main]
 async fn main() -> RedisResult<()> {
-    let args: Vec<String> = env::args().collect();
-    let mut queue_name = "my_queue".to_string();
-    let mut message_type = "TEST".to_string();
-    let mut message_content = "ContentTEST".to_string();
-    let mut message_enum = 3;
+    // Usage errors are reported by clap with exit code 2.
+    let args = Args::parse();
 
-    let mut i = 1;
-    while i < args.len() {
-        match args[i].as_str() {
-            "--queue_name" | "-q" => {
-                if i + 1 < args.len() {
-                    queue_name = args[i + 1].clone();
-                    i += 1;
-                } else {
-                    print_usage();
-                    return Ok(());
-                }
-            }
-            "--message_type" | "-t" => {
-                if i + 1 < args.len() {
-                    message_type = args[i + 1].clone();
-                    i += 1;
-                } else {
-                    print_usage();
-                    return Ok(());
-                }
-            }
-            "--message_content" | "-c" => {
-                if i + 1 < args.len() {
-                    message_content = args[i + 1].clone();
-                    i += 1;
-                } else {
-                    print_usage();
-                    return Ok(());
-                }
-            }
-            "--message_enum" | "-e" => {
-                if i + 1 < args.len() {
-                    message_enum = args[i + 1].parse().unwrap_or(3);
-                    i += 1;
-                } else {
-                    print_usage();
-                    return Ok(());
-                }
-            }
-            "--help" | "-h" => {
-                print_usage();
-                return Ok(());
+    #[cfg(feature = "tracing")]
+    let tracing_guard = bullmq_rust::tracing_service::init_tracing_from_env("push_message");
+
+    let queue_name = args.queue_name;
+    let input = if args.stdin { Some(PathBuf::from("-")) } else { args.file };
+
+    // Read and validate the jobs before connecting, so malformed input adds nothing
+    let bulk_jobs = input.map(|input| {
+        let content = if input.as_os_str() == "-" {
+            let mut content = String::new();
+            io::stdin().read_to_string(&mut content).map(|_| content)
+        } else {
+            std::fs::read_to_string(&input)
+        };
+        let content = content.unwrap_or_else(|e| {
+            eprintln!("Failed to read {}: {}", input.display(), e);
+            process::exit(1);
+        });
+        parse_job_lines(&content).unwrap_or_else(|errors| {
+            for error in &errors {
+                eprintln!("{}: {}", input.display(), error);
             }
-            _ => {}
-        }
-        i += 1;
-    }
+            eprintln!("No job added to {}: {} malformed lines", queue_name, errors.len());
+            process::exit(2);
+        })
+    });
 
     // Create a new queue service instance
-    let conn = QueueService::connect().await;
-    let redis_service = Arc::new(Mutex::new(QueueService::new(conn)));
+    let config = ConfigService::load().unwrap_or_else(|e| {
+        eprintln!("Invalid configuration: {}", e);
+        process::exit(1);
+    });
+    let mut redis_service = config.queue_service_for(&queue_name).await?;
+
+    if let Some(jobs) = bulk_jobs {
+        let count = jobs.len();
+        if let Err(e) = redis_service.add_jobs(&queue_name, jobs).await {
+            eprintln!("Failed to add {} jobs to {}: {}", count, queue_name, e);
+            #[cfg(feature = "tracing")]
+            drop(tracing_guard);
+            process::exit(1);
+        }
+        eprintln!("Succeeded to add {} jobs to {}", count, queue_name);
+        return Ok(());
+    }
 
     let data_model = DataModel {
-        message_type,
-        message_content,
-        message_enum,
+        message_type: args.message_type,
+        message_content: args.message_content,
+        message_enum: args.message_enum,
     };
 
     // Create a job and add it to the queue
-    let job_id = "job_1".to_string();
+    let job_id = generate_job_id();
     let job = JobData {
         id: job_id.clone(),
         message: serde_json::to_string(&data_model).unwrap(),
@@ -91,23 +107,16 @@ async fn main() -> RedisResult<()> {
         retries: Some(3),
         expires_in: Some(Utc::now().timestamp() + 60),
         progress: Some(0),
+        ..Default::default()
     };
 
-    if let Err(e) = redis_service.lock().unwrap().add_job(&queue_name, job).await {
+    if let Err(e) = redis_service.add_job(&queue_name, job).await {
         eprintln!("Failed to add {} to {}: {}", job_id, queue_name, e);
-    } else {
-        eprintln!("Succeeded to add {} to {}", job_id, queue_name);
+        #[cfg(feature = "tracing")]
+        drop(tracing_guard);
+        process::exit(1);
     }
+    eprintln!("Succeeded to add {} to {}", job_id, queue_name);
 
     Ok(())
 }
-
-fn print_usage() {
-    println!("Usage: push_message [options]");
-    println!("Options:");
-    println!("  --queue_name, -q <queue_name>         The name of the queue (default: my_queue)");
-    println!("  --message_type, -t <message_type>     The type of the message (default: TEST)");
-    println!("  --message_content, -c <message_content> The content of the message (default: ContentTEST)");
-    println!("  --message_enum, -e <message_enum>     The enum value of the message (default: 3)");
-    println!("  --help, -h                            Print this help message");
-}
@@ -1,69 +1,188 @@
-use std::sync::Arc;
-use bullmq_rust::queue_trigger_service::QueueTriggerService;
+use bullmq_rust::queue_trigger_service::{OutputFormat, QueueTriggerService};
+use bullmq_rust::sandbox_service::SandboxOptions;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
 use redis::RedisResult;
 use std::env;
+use std::net::SocketAddr;
+#[cfg(feature = "prometheus")]
+use bullmq_rust::prometheus_service::PrometheusService;
+#[cfg(feature = "prometheus")]
+use std::sync::Arc;
+#[cfg(feature = "webhook")]
+use bullmq_rust::webhook_service::WebhookService;
+#[cfg(feature = "webhook")]
+use std::time::Duration;
 
-#[tokio::main]
-async fn main() -> RedisResult<()> {
-    let args: Vec<String> = env::args().collect();
-    let mut queue_name = None;
-    let mut refresh_time_milli = 1000;
+/// Environment variable holding the webhook signing secret, so it does not appear in the process list.
+const WEBHOOK_SECRET_ENV: &str = "BULLMQ_WEBHOOK_SECRET";
+
+/// Processes the jobs of a queue.
+#[derive(Parser, Debug)]
+#[command(name = "queue_trigger", about = "Process the jobs of a queue")]
+struct Args {
+    /// The name of the queue to monitor.
+    #[arg(short = 'q', long = "queue_name")]
+    queue_name: String,
+    /// The maximum time in milliseconds to block waiting for a job.
+    #[arg(short = 'r', long = "refresh", value_name = "TIME", default_value_t = 1000)]
+    refresh_time_milli: u64,
+    /// Serve Prometheus metrics on <ADDR>/metrics (requires the `prometheus` feature).
+    #[arg(short = 'm', long = "metrics-addr", value_name = "ADDR")]
+    metrics_addr: Option<SocketAddr>,
+    /// Run <CMD> with `sh -c` for each job, with the message on stdin and JOB_ID, QUEUE and ATTEMPT set;
+    /// a non-zero exit fails or retries the job.
+    #[arg(short = 'x', long, value_name = "CMD", conflicts_with_all = ["command", "url"])]
+    exec: Option<String>,
+    #[command(flatten)]
+    sandbox: SandboxArgs,
+    #[command(flatten)]
+    forward: ForwardOptions,
+    /// Fail attempts taking longer than <TIME> milliseconds, for the jobs without their own timeout.
+    #[arg(long = "job-timeout", value_name = "TIME", value_parser = clap::value_parser!(u64).range(1..))]
+    job_timeout_milli: Option<u64>,
+    /// Report each job on stdout as text, json or jsonl; diagnostics always go to stderr.
+    #[arg(short = 'o', long, value_name = "FORMAT", default_value = "text")]
+    output: OutputFormat,
+    /// Do not report processed jobs on stdout.
+    #[arg(long)]
+    quiet: bool,
+}
+
+/// The webhook forwarding options.
+#[derive(clap::Args, Debug)]
+struct ForwardOptions {
+    /// POST each job as JSON to <URL> (requires the `webhook` feature); a 2xx completes the job,
+    /// 429 or 503 backs off, other statuses fail the job.
+    #[arg(short = 'f', long = "forward-url", value_name = "URL", conflicts_with = "command")]
+    url: Option<String>,
+    /// Send a header with each forwarded job, can be repeated.
+    #[arg(short = 'H', long = "header", value_name = "NAME: VALUE", requires = "url")]
+    headers: Vec<String>,
+    /// The time in milliseconds to wait for the endpoint [default: 10000].
+    #[arg(short = 't', long = "timeout", value_name = "TIME", requires = "url")]
+    timeout_milli: Option<u64>,
+    /// Sign each body with HMAC-SHA256 in X-BullMQ-Signature [default: $BULLMQ_WEBHOOK_SECRET].
+    #[arg(short = 's', long = "hmac-secret", value_name = "SECRET", requires = "url")]
+    secret: Option<String>,
+}
 
-    let mut i = 1;
-    while i < args.len() {
-        match args[i].as_str() {
-            "--queue_name" | "-q" => {
-                if i + 1 < args.len() {
-                    queue_name = Some(args[i + 1].clone());
-                    i += 1;
-                } else {
-                    print_usage();
-                    return Ok(());
-                }
-            }
-            "--refresh" | "-r" => {
-                if i + 1 < args.len() {
-                    refresh_time_milli = args[i + 1].parse().unwrap_or(1000);
-                    i += 1;
-                } else {
-                    print_usage();
-                    return Ok(());
-                }
-            }
-            "--help" | "-h" => {
-                print_usage();
-                return Ok(());
-            }
-            _ => {}
+/// The sandbox process pool options.
+#[derive(clap::Args, Debug)]
+struct SandboxArgs {
+    /// Start <CMD> with `sh -c` as long-lived processes reading one JSON job per line on stdin
+    /// and answering with JSON lines on stdout.
+    #[arg(short = 'S', long = "sandbox", value_name = "CMD")]
+    command: Option<String>,
+    /// The number of sandbox processes, and of concurrent jobs [default: 1].
+    #[arg(short = 'P', long, value_name = "COUNT", requires = "command")]
+    processes: Option<usize>,
+    /// Replace each sandbox process after <COUNT> jobs.
+    #[arg(long = "max-jobs-per-process", value_name = "COUNT", requires = "command")]
+    max_jobs_per_process: Option<u64>,
+    /// Replace each sandbox process using more than <SIZE> MiB (Linux only).
+    #[arg(long = "max-memory-mb", value_name = "SIZE", requires = "command")]
+    max_memory_mb: Option<u64>,
+}
+
+impl SandboxArgs {
+    /// Builds the pool options, when a sandbox command was given.
+    fn options(&self) -> Option<SandboxOptions> {
+        let mut options = SandboxOptions::new(self.command.as_deref()?);
+        if let Some(processes) = self.processes {
+            options = options.with_processes(processes);
+        }
+        if let Some(max_jobs) = self.max_jobs_per_process {
+            options = options.with_max_jobs_per_process(max_jobs);
+        }
+        if let Some(max_memory_mb) = self.max_memory_mb {
+            options = options.with_max_memory_bytes(max_memory_mb.saturating_mul(1024 * 1024));
         }
-        i += 1;
+        Some(options)
     }
+}
 
-    if let Some(queue_name) = queue_name {
-        start_queue_trigger(&queue_name, refresh_time_milli).await
-    } else {
-        print_usage();
-        Ok(())
-    }
+/// Reports an invalid combination of arguments the way clap reports usage errors, and exits with code 2.
+fn usage_error(message: impl std::fmt::Display) -> ! {
+    Args::command().error(ErrorKind::ArgumentConflict, message).exit()
 }
 
-fn print_usage() {
-    println!("Usage: queue_trigger --queue_name <queue_name> [--refresh <time>] [--help]");
-    println!("Options:");
-    println!("  --queue_name, -q <queue_name>  The name of the queue to monitor (mandatory)");
-    println!("  --refresh, -r <time>           The refresh time in milliseconds (default: 1000)");
-    println!("  --help, -h                     Print this help message");
+#[tokio::main]
+async fn main() -> RedisResult<()> {
+    // Usage errors are reported by clap with exit code 2.
+    let mut args = Args::parse();
+
+    #[cfg(feature = "tracing")]
+    let _tracing_guard = bullmq_rust::tracing_service::init_tracing_from_env("queue_trigger");
+
+    if args.forward.secret.is_none() {
+        args.forward.secret = env::var(WEBHOOK_SECRET_ENV).ok();
+    }
+    let mut queue_trigger = QueueTriggerService::new(args.queue_name).with_output(args.output).with_quiet(args.quiet);
+    if let Some(timeout_milli) = args.job_timeout_milli {
+        queue_trigger = queue_trigger.with_job_timeout(std::time::Duration::from_millis(timeout_milli));
+    }
+    start_queue_trigger(queue_trigger, args.refresh_time_milli, args.metrics_addr, args.exec, args.sandbox, args.forward).await
 }
 
-async fn start_queue_trigger(queue_name: &str, refresh_time_milli: u64) -> RedisResult<()> {
-    // Create queue trigger service
-    let queue_trigger = Arc::new(QueueTriggerService::new(queue_name.to_string()));
-    
-        // Start queue trigger to monitor and process jobs
-    tokio::spawn(async move {
-        queue_trigger.start(refresh_time_milli).await;
-    });
+async fn start_queue_trigger(
+    mut queue_trigger: QueueTriggerService,
+    refresh_time_milli: u64,
+    metrics_addr: Option<SocketAddr>,
+    exec: Option<String>,
+    sandbox: SandboxArgs,
+    forward: ForwardOptions,
+) -> RedisResult<()> {
+    let sandbox = sandbox.options();
+    if let Some(command) = &exec {
+        queue_trigger = queue_trigger.with_exec(command);
+    }
+    if let Some(options) = sandbox {
+        queue_trigger = queue_trigger.with_sandbox(options);
+    }
+
+    // Forward the jobs to the webhook when requested
+    #[cfg(feature = "webhook")]
+    if let Some(url) = &forward.url {
+        let mut webhook = WebhookService::new(url);
+        for header in &forward.headers {
+            webhook = webhook.with_header(header).unwrap_or_else(|e| usage_error(e));
+        }
+        if let Some(timeout_milli) = forward.timeout_milli {
+            webhook = webhook.with_timeout(Duration::from_millis(timeout_milli));
+        }
+        if let Some(secret) = &forward.secret {
+            webhook = webhook.with_signing_secret(secret);
+        }
+        queue_trigger = queue_trigger.with_webhook(webhook);
+    }
+    #[cfg(not(feature = "webhook"))]
+    if forward.url.is_some() {
+        usage_error("--forward-url requires queue_trigger to be built with the `webhook` feature");
+    }
+
+    // Serve the Prometheus metrics endpoint when requested
+    #[cfg(feature = "prometheus")]
+    let queue_trigger = match metrics_addr {
+        Some(addr) => {
+            let prometheus = Arc::new(PrometheusService::new().map_err(|e| {
+                redis::RedisError::from((redis::ErrorKind::ClientError, "Failed to create metrics registry", e.to_string()))
+            })?);
+            let (local_addr, _) = prometheus.clone().serve(addr).await?;
+            eprintln!("Serving metrics on http://{}/metrics", local_addr);
+            queue_trigger.with_prometheus(prometheus)
+        }
+        None => queue_trigger,
+    };
+    #[cfg(not(feature = "prometheus"))]
+    if metrics_addr.is_some() {
+        usage_error("--metrics-addr requires queue_trigger to be built with the `prometheus` feature");
+    }
+
+    // Start queue trigger to monitor and process jobs
+    queue_trigger.start(refresh_time_milli).await?;
 
-    // Keep the main function alive
-    loop {}
+    // Keep the main function alive until interrupted
+    tokio::signal::ctrl_c().await?;
+    Ok(())
 }
@@ -0,0 +1,583 @@
+use clap::{Args, Parser, Subcommand};
+use redis::RedisError;
+use serde_json::{json, Value};
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+use crate::job_model::{FailedJobFilter, JobData, JobSpec, JobState};
+use crate::log_model::LogRange;
+use crate::queue_trigger_service::{OutputFormat, QueueTriggerService};
+use crate::QueueServiceTrait;
+
+/// Exit code of a command that failed because of the queue backend or an I/O error.
+pub const EXIT_FAILURE: i32 = 1;
+
+/// Exit code of a command given invalid arguments or input, the same as for `clap` usage errors.
+pub const EXIT_USAGE: i32 = 2;
+
+/// Exit code of a command referring to a job or value that does not exist.
+pub const EXIT_NOT_FOUND: i32 = 3;
+
+/// Command line interface managing the queues and jobs of any backend.
+#[derive(Parser, Debug)]
+#[command(name = "bullmq", version, about = "Manage BullMQ queues and jobs")]
+pub struct Cli {
+    /// Print the result as JSON.
+    #[arg(long, global = true)]
+    pub json: bool,
+    /// The command to run.
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+/// The options of a job added from the command line.
+#[derive(Args, Debug, Clone, Default, PartialEq)]
+pub struct JobOptions {
+    /// The ID of the job, generated if unset.
+    #[arg(long)]
+    pub id: Option<String>,
+    /// The priority of the job.
+    #[arg(long)]
+    pub priority: Option<i32>,
+    /// The delay, in seconds, before the job can be processed.
+    #[arg(long, value_parser = clap::value_parser!(i64).range(0..))]
+    pub delay: Option<i64>,
+    /// The number of retries allowed for the job.
+    #[arg(long)]
+    pub retries: Option<u32>,
+    /// The maximum time, in milliseconds, an attempt to process the job may take.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub timeout: Option<u64>,
+}
+
+/// A page of jobs or log entries.
+#[derive(Args, Debug, Clone, PartialEq)]
+pub struct PageOptions {
+    /// The number of items to skip.
+    #[arg(long, default_value_t = 0)]
+    pub offset: u64,
+    /// The maximum number of items to return.
+    #[arg(long, default_value_t = 20, value_parser = clap::value_parser!(u64).range(1..))]
+    pub limit: u64,
+}
+
+/// The filter selecting failed jobs.
+#[derive(Args, Debug, Clone, Default, PartialEq)]
+pub struct FailedFilterOptions {
+    /// Only select the jobs whose failure reason contains this text, ignoring case.
+    #[arg(long)]
+    pub reason: Option<String>,
+    /// Only select the jobs whose last attempt failed within this number of seconds.
+    #[arg(long)]
+    pub failed_within: Option<u64>,
+}
+
+impl FailedFilterOptions {
+    /// Returns the filter selecting the failed jobs.
+    pub fn filter(&self) -> FailedJobFilter {
+        FailedJobFilter { reason: self.reason.clone(), failed_within_secs: self.failed_within }
+    }
+}
+
+/// The subcommands of the CLI.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Add a job to a queue.
+    Add {
+        /// The name of the queue.
+        queue: String,
+        /// The message of the job.
+        message: String,
+        #[command(flatten)]
+        options: JobOptions,
+    },
+    /// Add the jobs of a JSON array of `{id, message, priority, delay, retries, timeout}` objects.
+    AddBulk {
+        /// The name of the queue.
+        queue: String,
+        /// The file to read the jobs from, standard input if unset or `-`.
+        file: Option<PathBuf>,
+    },
+    /// List the jobs of a queue.
+    List {
+        /// The name of the queue.
+        queue: String,
+        /// The state of the jobs to list, `waiting` or `failed`.
+        #[arg(long, default_value = "waiting")]
+        state: JobState,
+        #[command(flatten)]
+        page: PageOptions,
+    },
+    /// Show a job and its state.
+    Get {
+        /// The name of the queue.
+        queue: String,
+        /// The ID of the job.
+        job_id: String,
+    },
+    /// Count the jobs of a queue by state.
+    Count {
+        /// The name of the queue.
+        queue: String,
+        /// Only count the jobs in this state, `waiting` or `failed`.
+        #[arg(long)]
+        state: Option<JobState>,
+    },
+    /// List the failed jobs of a queue with their attempts and failure reasons, most recent first.
+    Failed {
+        /// The name of the queue.
+        queue: String,
+        #[command(flatten)]
+        filter: FailedFilterOptions,
+        #[command(flatten)]
+        page: PageOptions,
+    },
+    /// Move failed jobs back to the waiting jobs.
+    Retry {
+        /// The name of the queue.
+        queue: String,
+        /// The ID of the failed job to retry.
+        #[arg(
+            required_unless_present_any = ["all", "reason", "failed_within"],
+            conflicts_with_all = ["all", "reason", "failed_within"]
+        )]
+        job_id: Option<String>,
+        /// Retry every failed job.
+        #[arg(long)]
+        all: bool,
+        #[command(flatten)]
+        filter: FailedFilterOptions,
+    },
+    /// Move failed jobs to the waiting jobs of another queue.
+    Move {
+        /// The name of the queue.
+        queue: String,
+        /// The ID of the failed job to move.
+        #[arg(
+            required_unless_present_any = ["all", "reason", "failed_within"],
+            conflicts_with_all = ["all", "reason", "failed_within"]
+        )]
+        job_id: Option<String>,
+        /// The name of the queue to move the jobs to.
+        #[arg(long, value_parser = clap::builder::NonEmptyStringValueParser::new())]
+        to: String,
+        /// Move every failed job.
+        #[arg(long)]
+        all: bool,
+        #[command(flatten)]
+        filter: FailedFilterOptions,
+    },
+    /// Remove a job together with its progress and log lines.
+    Remove {
+        /// The name of the queue.
+        queue: String,
+        /// The ID of the job.
+        job_id: String,
+    },
+    /// Cancel a job: the worker processing it stops its handler, a waiting job is cancelled once fetched.
+    Cancel {
+        /// The name of the queue.
+        queue: String,
+        /// The ID of the job.
+        job_id: String,
+    },
+    /// Pause a queue, workers stop fetching its jobs.
+    Pause {
+        /// The name of the queue.
+        queue: String,
+    },
+    /// Resume a paused queue.
+    Resume {
+        /// The name of the queue.
+        queue: String,
+    },
+    /// Remove old jobs in a given state.
+    Clean {
+        /// The name of the queue.
+        queue: String,
+        /// The state of the jobs to remove, `waiting` or `failed`.
+        #[arg(long, default_value = "failed")]
+        state: JobState,
+        /// The minimum age, in seconds, of the removed jobs.
+        #[arg(long, default_value_t = 0)]
+        grace: u64,
+    },
+    /// Read the queue log, or the log lines of a job.
+    Logs {
+        /// The name of the queue.
+        queue: String,
+        /// Read the log lines of this job instead of the queue log.
+        #[arg(long)]
+        job: Option<String>,
+        #[command(flatten)]
+        page: PageOptions,
+    },
+    /// Show the progress of a job, or set it when a value is given.
+    Progress {
+        /// The name of the queue.
+        queue: String,
+        /// The ID of the job.
+        job_id: String,
+        /// The progress to set, between 0 and 100.
+        #[arg(value_parser = clap::value_parser!(u32).range(0..=100))]
+        value: Option<u32>,
+    },
+    /// Process the jobs of a queue.
+    Work {
+        /// The name of the queue.
+        queue: String,
+        /// The maximum time in milliseconds to block waiting for a job.
+        #[arg(long, default_value_t = 1000, value_parser = clap::value_parser!(u64).range(1..))]
+        refresh: u64,
+        /// Stop after processing this number of jobs, run forever if unset.
+        #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+        max_jobs: Option<u64>,
+        /// Run this shell command for each job, with the message on stdin and
+        /// `JOB_ID`, `QUEUE` and `ATTEMPT` set; a non-zero exit fails or retries the job.
+        #[arg(long)]
+        exec: Option<String>,
+        /// The maximum time, in milliseconds, an attempt may take for jobs without their own timeout.
+        #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+        job_timeout: Option<u64>,
+    },
+    /// Monitor queue depths, active jobs, recent failures and the queue log in a terminal UI.
+    #[cfg(feature = "tui")]
+    Top {
+        /// The names of the queues, all the queues found in the backend if unset.
+        queues: Vec<String>,
+        /// The interval in milliseconds between two refreshes.
+        #[arg(long, default_value_t = 1000, value_parser = clap::value_parser!(u64).range(100..))]
+        refresh: u64,
+    },
+}
+
+impl Command {
+    /// Returns the name of the queue the command applies to, empty for `top` without queues.
+    pub fn queue(&self) -> &str {
+        match self {
+            Command::Add { queue, .. }
+            | Command::AddBulk { queue, .. }
+            | Command::List { queue, .. }
+            | Command::Get { queue, .. }
+            | Command::Count { queue, .. }
+            | Command::Failed { queue, .. }
+            | Command::Retry { queue, .. }
+            | Command::Move { queue, .. }
+            | Command::Remove { queue, .. }
+            | Command::Cancel { queue, .. }
+            | Command::Pause { queue }
+            | Command::Resume { queue }
+            | Command::Clean { queue, .. }
+            | Command::Logs { queue, .. }
+            | Command::Progress { queue, .. }
+            | Command::Work { queue, .. } => queue,
+            #[cfg(feature = "tui")]
+            Command::Top { queues, .. } => queues.first().map_or("", String::as_str),
+        }
+    }
+
+    /// Returns the name of the queue the command moves jobs to, if any.
+    pub fn target_queue(&self) -> Option<&str> {
+        match self {
+            Command::Move { to, .. } => Some(to),
+            _ => None,
+        }
+    }
+}
+
+/// The error of a CLI command.
+#[derive(Debug)]
+pub enum CliError {
+    /// The input of the command is invalid.
+    InvalidInput(String),
+    /// The job or value the command refers to does not exist.
+    NotFound(String),
+    /// The queue backend failed.
+    Backend(RedisError),
+    /// An input could not be read or the output could not be written.
+    Io(io::Error),
+}
+
+impl CliError {
+    /// Returns the process exit code reporting the error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CliError::InvalidInput(_) => EXIT_USAGE,
+            CliError::NotFound(_) => EXIT_NOT_FOUND,
+            CliError::Backend(_) | CliError::Io(_) => EXIT_FAILURE,
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::InvalidInput(message) | CliError::NotFound(message) => f.write_str(message),
+            CliError::Backend(e) => write!(f, "queue backend error: {}", e),
+            CliError::Io(e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+impl From<RedisError> for CliError {
+    fn from(e: RedisError) -> Self {
+        CliError::Backend(e)
+    }
+}
+
+impl From<io::Error> for CliError {
+    fn from(e: io::Error) -> Self {
+        CliError::Io(e)
+    }
+}
+
+impl Cli {
+    /// Runs the command with the given queue service.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_service` - The queue service of the queue the command applies to.
+    /// * `out` - Where the result of the command is written.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating the success of the command, or the error to report with its exit code.
+    pub async fn execute(&self, queue_service: &mut dyn QueueServiceTrait, out: &mut dyn Write) -> Result<(), CliError> {
+        self.execute_with_target(queue_service, None, out).await
+    }
+
+    /// Runs the command with the given queue service and the queue service of its target queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_service` - The queue service of the queue the command applies to.
+    /// * `target_service` - The queue service of the queue `move` moves jobs to, `queue_service` when `None`.
+    /// * `out` - Where the result of the command is written.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating the success of the command, or the error to report with its exit code.
+    pub async fn execute_with_target(
+        &self,
+        queue_service: &mut dyn QueueServiceTrait,
+        target_service: Option<Box<dyn QueueServiceTrait>>,
+        out: &mut dyn Write,
+    ) -> Result<(), CliError> {
+        match &self.command {
+            Command::Add { queue, message, options } => {
+                let job = JobSpec {
+                    id: options.id.clone(),
+                    message: Value::String(message.clone()),
+                    priority: options.priority,
+                    delay: options.delay,
+                    retries: options.retries,
+                    timeout: options.timeout,
+                }
+                .into_job()
+                .map_err(CliError::InvalidInput)?;
+                queue_service.add_job(queue, job.clone()).await?;
+                self.print(out, json!(job), format!("Added job {} to {}", job.id, queue))
+            }
+            Command::AddBulk { queue, file } => {
+                let input = match file {
+                    Some(path) if path.as_os_str() != "-" => std::fs::read_to_string(path)?,
+                    _ => {
+                        let mut input = String::new();
+                        io::stdin().read_to_string(&mut input)?;
+                        input
+                    }
+                };
+                let specs: Vec<JobSpec> = serde_json::from_str(&input)
+                    .map_err(|e| CliError::InvalidInput(format!("invalid jobs: {}", e)))?;
+                let jobs = specs
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, spec)| {
+                        spec.into_job().map_err(|e| CliError::InvalidInput(format!("invalid job #{}: {}", index + 1, e)))
+                    })
+                    .collect::<Result<Vec<JobData>, CliError>>()?;
+                let ids: Vec<String> = jobs.iter().map(|job| job.id.clone()).collect();
+                queue_service.add_jobs(queue, jobs).await?;
+                let text = format!("Added {} jobs to {}", ids.len(), queue);
+                self.print(out, json!({ "added": ids.len(), "ids": ids }), text)
+            }
+            Command::List { queue, state, page } => {
+                let jobs = queue_service.list_jobs(queue, *state, page.offset as usize, page.limit as usize).await?;
+                let text = jobs.iter().map(job_line).collect::<Vec<_>>().join("\n");
+                self.print(out, json!(jobs), text)
+            }
+            Command::Get { queue, job_id } => {
+                let (state, job) = queue_service
+                    .get_job(queue, job_id)
+                    .await?
+                    .ok_or_else(|| job_not_found(queue, job_id))?;
+                let mut text = format!(
+                    "id:\t\t{}\nstate:\t\t{}\ntimestamp:\t{}\nmessage:\t{}",
+                    job.id, state.as_str(), job.timestamp, job.message
+                );
+                for failure in &job.failures {
+                    text.push_str(&format!(
+                        "\nattempt {}:\t{}\t{}ms\t{}",
+                        failure.attempt, failure.failed_at, failure.duration_ms, failure.reason
+                    ));
+                    if let Some(stack) = &failure.stack {
+                        text.push_str(&format!("\n{}", stack));
+                    }
+                }
+                self.print(out, json!({ "state": state, "job": job }), text)
+            }
+            Command::Count { queue, state } => {
+                let states = match state {
+                    Some(state) => vec![*state],
+                    None => vec![JobState::Waiting, JobState::Failed],
+                };
+                let mut counts = serde_json::Map::new();
+                let mut lines = Vec::new();
+                for state in states {
+                    let count = queue_service.count_jobs_by_state(queue, state).await?;
+                    counts.insert(state.as_str().to_string(), json!(count));
+                    lines.push(format!("{}\t{}", state.as_str(), count));
+                }
+                self.print(out, Value::Object(counts), lines.join("\n"))
+            }
+            Command::Failed { queue, filter, page } => {
+                let jobs = queue_service
+                    .list_failed_jobs(queue, &filter.filter(), page.offset as usize, page.limit as usize)
+                    .await?;
+                let text = jobs.iter().map(failed_job_line).collect::<Vec<_>>().join("\n");
+                self.print(out, json!(jobs), text)
+            }
+            Command::Retry { queue, job_id, filter, .. } => {
+                let retried = match job_id {
+                    Some(job_id) if queue_service.retry_job(queue, job_id).await? => vec![job_id.clone()],
+                    Some(job_id) => return Err(failed_job_not_found(queue, job_id)),
+                    None => queue_service.retry_failed_jobs(queue, &filter.filter()).await?,
+                };
+                let text = format!("Retried {} jobs in {}", retried.len(), queue);
+                self.print(out, json!({ "retried": retried }), text)
+            }
+            Command::Move { queue, job_id, to, filter, .. } => {
+                let mut target = target_service.unwrap_or_else(|| queue_service.clone_box());
+                let moved = match job_id {
+                    Some(job_id) if queue_service.move_failed_job(queue, job_id, &mut *target, to).await? => vec![job_id.clone()],
+                    Some(job_id) => return Err(failed_job_not_found(queue, job_id)),
+                    None => queue_service.move_failed_jobs(queue, &filter.filter(), &mut *target, to).await?,
+                };
+                let text = format!("Moved {} jobs from {} to {}", moved.len(), queue, to);
+                self.print(out, json!({ "moved": moved, "to": to }), text)
+            }
+            Command::Remove { queue, job_id } => {
+                if !queue_service.remove_job(queue, job_id).await? {
+                    return Err(job_not_found(queue, job_id));
+                }
+                self.print(out, json!({ "removed": job_id }), format!("Removed job {} from {}", job_id, queue))
+            }
+            Command::Cancel { queue, job_id } => {
+                queue_service.cancel_job(queue, job_id).await?;
+                let text = format!("Requested cancellation of job {} in {}", job_id, queue);
+                self.print(out, json!({ "cancelled": job_id }), text)
+            }
+            Command::Pause { queue } => {
+                queue_service.pause_queue(queue).await?;
+                self.print(out, json!({ "queue": queue, "paused": true }), format!("Paused {}", queue))
+            }
+            Command::Resume { queue } => {
+                queue_service.resume_queue(queue).await?;
+                self.print(out, json!({ "queue": queue, "paused": false }), format!("Resumed {}", queue))
+            }
+            Command::Clean { queue, state, grace } => {
+                let removed = queue_service.clean_jobs(queue, *state, *grace).await?;
+                let text = format!("Removed {} {} jobs from {}", removed, state.as_str(), queue);
+                self.print(out, json!({ "removed": removed }), text)
+            }
+            Command::Logs { queue, job: Some(job_id), page } => {
+                let start = page.offset.min(isize::MAX as u64) as isize;
+                let end = start.saturating_add(page.limit.min(isize::MAX as u64) as isize - 1);
+                let lines = queue_service.get_job_logs(queue, job_id, start, end).await?;
+                let text = lines.join("\n");
+                self.print(out, json!(lines), text)
+            }
+            Command::Logs { queue, job: None, page } => {
+                let range = LogRange { offset: page.offset as usize, limit: page.limit as usize };
+                let entries = queue_service.read_logs(queue, None, range).await?;
+                let text = entries
+                    .iter()
+                    .map(|entry| {
+                        format!(
+                            "{}\t{}\t{}\t{}",
+                            entry.timestamp,
+                            format!("{:?}", entry.level).to_uppercase(),
+                            entry.job_id.as_deref().unwrap_or("-"),
+                            entry.event
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                self.print(out, json!(entries), text)
+            }
+            Command::Progress { queue, job_id, value: Some(value) } => {
+                queue_service.update_job_progress(queue, job_id, *value).await?;
+                let text = format!("Set progress of {} to {}", job_id, value);
+                self.print(out, json!({ "id": job_id, "progress": value }), text)
+            }
+            Command::Progress { queue, job_id, value: None } => {
+                let progress = queue_service
+                    .get_job_progress(queue, job_id)
+                    .await?
+                    .ok_or_else(|| CliError::NotFound(format!("no progress for job {} in {}", job_id, queue)))?;
+                self.print(out, json!({ "id": job_id, "progress": progress }), progress.to_string())
+            }
+            Command::Work { queue, refresh, max_jobs, exec, job_timeout } => {
+                let output = if self.json { OutputFormat::Jsonl } else { OutputFormat::Text };
+                let mut trigger = QueueTriggerService::new(queue.clone()).with_output(output);
+                if let Some(command) = exec {
+                    trigger = trigger.with_exec(command);
+                }
+                if let Some(timeout) = job_timeout {
+                    trigger = trigger.with_job_timeout(Duration::from_millis(*timeout));
+                }
+                let processed = trigger.run(queue_service, *refresh, *max_jobs).await;
+                let text = format!("Processed {} jobs from {}", processed, queue);
+                self.print(out, json!({ "processed": processed }), text)
+            }
+            #[cfg(feature = "tui")]
+            Command::Top { queues, refresh } => {
+                crate::top_service::run(queue_service, queues.clone(), std::time::Duration::from_millis(*refresh)).await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Writes the result of a command, as JSON when requested.
+    fn print(&self, out: &mut dyn Write, value: Value, text: String) -> Result<(), CliError> {
+        if self.json {
+            writeln!(out, "{}", value)?;
+        } else if !text.is_empty() {
+            writeln!(out, "{}", text)?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats a job on one line for listings.
+fn job_line(job: &JobData) -> String {
+    format!("{}\t{}\t{}", job.id, job.timestamp, job.message)
+}
+
+/// Formats a failed job on one line for listings, with its attempts and last failure.
+fn failed_job_line(job: &JobData) -> String {
+    let failed_at = job.failures.last().map_or("-", |failure| failure.failed_at.as_str());
+    let reason = job.failed_reason.as_deref().unwrap_or("-");
+    format!("{}\t{}\t{}\t{}", job.id, job.attempts_made.unwrap_or(0), failed_at, reason)
+}
+
+fn job_not_found(queue: &str, job_id: &str) -> CliError {
+    CliError::NotFound(format!("no job {} in {}", job_id, queue))
+}
+
+fn failed_job_not_found(queue: &str, job_id: &str) -> CliError {
+    CliError::NotFound(format!("no failed job {} in {}", job_id, queue))
+}
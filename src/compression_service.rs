@@ -0,0 +1,638 @@
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use redis::{ErrorKind, RedisError, RedisResult};
+use serde::Deserialize;
+use serde_json::Value;
+use std::io;
+#[cfg(feature = "gzip")]
+use std::io::{Read, Write};
+use crate::config_service::CompressionConfig;
+use crate::job_model::{Compression, FailedJobFilter, JobData, JobState};
+use crate::log_model::{LogEntry, LogRange};
+use crate::metrics_model::{MetricKind, MetricPoint, MetricsRange};
+use crate::QueueServiceTrait;
+
+/// Compresses the message of a job, then encodes it in base64 and marks the job with the algorithm.
+///
+/// Messages up to the threshold, messages that would not get smaller and jobs already
+/// compressed are left unchanged.
+///
+/// # Arguments
+///
+/// * `job` - The job to compress.
+/// * `config` - The compression algorithm and threshold.
+///
+/// # Returns
+///
+/// A `RedisResult` containing `true` if the message was compressed.
+pub fn compress_job(job: &mut JobData, config: &CompressionConfig) -> RedisResult<bool> {
+    if job.compression.is_some() || job.message.len() <= config.threshold_bytes {
+        return Ok(false);
+    }
+    let compressed = compress(config.algorithm, job.message.as_bytes())
+        .map_err(|e| codec_error("Failed to compress job message", e))?;
+    let encoded = BASE64.encode(compressed);
+    if encoded.len() >= job.message.len() {
+        return Ok(false);
+    }
+    job.message = encoded;
+    job.compression = Some(config.algorithm);
+    Ok(true)
+}
+
+/// Restores the plain message of a compressed job, jobs without compression are left unchanged.
+///
+/// # Arguments
+///
+/// * `job` - The job to decompress.
+///
+/// # Returns
+///
+/// A `RedisResult` indicating whether the message could be decoded.
+pub fn decompress_job(job: &mut JobData) -> RedisResult<()> {
+    let Some(algorithm) = job.compression else { return Ok(()) };
+    job.message = decompress_message(algorithm, &job.message)?;
+    job.compression = None;
+    Ok(())
+}
+
+/// Decodes and decompresses a base64-encoded compressed message.
+fn decompress_message(algorithm: Compression, encoded: &str) -> RedisResult<String> {
+    let compressed = BASE64
+        .decode(encoded)
+        .map_err(|e| codec_error("Invalid compressed job message", e))?;
+    let message = decompress(algorithm, &compressed).map_err(|e| codec_error("Failed to decompress job message", e))?;
+    String::from_utf8(message).map_err(|e| codec_error("Invalid compressed job message", e))
+}
+
+fn compress(algorithm: Compression, data: &[u8]) -> io::Result<Vec<u8>> {
+    match algorithm {
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => zstd::encode_all(data, zstd::DEFAULT_COMPRESSION_LEVEL),
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => {
+            let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(data)?;
+            encoder.finish()
+        }
+        #[cfg(not(all(feature = "zstd", feature = "gzip")))]
+        algorithm => Err(unsupported(algorithm)),
+    }
+}
+
+fn decompress(algorithm: Compression, data: &[u8]) -> io::Result<Vec<u8>> {
+    match algorithm {
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => zstd::decode_all(data),
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => {
+            let mut message = Vec::new();
+            flate2::read::GzDecoder::new(data).read_to_end(&mut message)?;
+            Ok(message)
+        }
+        #[cfg(not(all(feature = "zstd", feature = "gzip")))]
+        algorithm => Err(unsupported(algorithm)),
+    }
+}
+
+#[cfg(not(all(feature = "zstd", feature = "gzip")))]
+fn unsupported(algorithm: Compression) -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, format!("{} compression requires the `{}` feature", algorithm.as_str(), algorithm.as_str()))
+}
+
+fn codec_error(description: &'static str, e: impl ToString) -> RedisError {
+    RedisError::from((ErrorKind::TypeError, description, e.to_string()))
+}
+
+/// Decompresses a job read from a backend.
+///
+/// A job that cannot be decompressed is returned as stored, still marked as compressed, so
+/// that it is not lost once fetched: workers fail such jobs instead of processing them.
+fn decode_job(mut job: JobData) -> JobData {
+    let stored = job.clone();
+    match decompress_job(&mut job) {
+        Ok(()) => job,
+        Err(e) => {
+            diagnostic!(warn, "Error decompressing job {}: {}", job.id, e);
+            stored
+        }
+    }
+}
+
+/// Decompresses the message of a queue log entry recording its compression, keeping it compressed if it cannot be.
+fn decode_entry(mut entry: LogEntry) -> LogEntry {
+    let Some(algorithm) = entry.fields.get("compression").and_then(|value| Compression::deserialize(value).ok()) else {
+        return entry;
+    };
+    let Some(encoded) = entry.fields.get("message").and_then(Value::as_str) else {
+        return entry;
+    };
+    match decompress_message(algorithm, encoded) {
+        Ok(message) => {
+            entry.fields.insert("message".to_string(), Value::String(message));
+            entry.fields.remove("compression");
+        }
+        Err(e) => diagnostic!(warn, "Error decompressing the logged message of job {:?}: {}", entry.job_id, e),
+    }
+    entry
+}
+
+/// Decompresses a serialized job fetched from a backend, other values are returned unchanged.
+fn decode_member(member: String) -> String {
+    match serde_json::from_str::<JobData>(&member) {
+        Ok(job) if job.compression.is_some() => {
+            let job = decode_job(job);
+            serde_json::to_string(&job).unwrap_or(member)
+        }
+        _ => member,
+    }
+}
+
+/// Queue service compressing the messages of the jobs written to another queue service, and
+/// decompressing the jobs read from it.
+///
+/// Compressed jobs carry the algorithm in their `compression` field, so plain and compressed
+/// jobs can share a queue, and workers reading through this service handle both.
+#[derive(Clone)]
+pub struct CompressedQueueService {
+    inner: Box<dyn QueueServiceTrait>,
+    config: Option<CompressionConfig>,
+}
+
+impl CompressedQueueService {
+    /// Creates a new `CompressedQueueService`.
+    ///
+    /// # Arguments
+    ///
+    /// * `inner` - The queue service storing the jobs.
+    /// * `config` - The compression of the jobs written, jobs are only decompressed when read if unset.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of `CompressedQueueService`.
+    pub fn new(inner: Box<dyn QueueServiceTrait>, config: Option<CompressionConfig>) -> Self {
+        Self { inner, config }
+    }
+
+    /// Compresses a job about to be written, according to the configuration.
+    fn encode(&self, mut job: JobData) -> RedisResult<JobData> {
+        if let Some(config) = &self.config {
+            compress_job(&mut job, config)?;
+        }
+        Ok(job)
+    }
+}
+
+#[async_trait]
+impl QueueServiceTrait for CompressedQueueService {
+    /// Compresses the message of a job and adds the job to the specified queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to add the job to.
+    /// * `job` - The job data to add to the queue.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn add_job(&mut self, queue_name: &str, job: JobData) -> RedisResult<()> {
+        let job = self.encode(job)?;
+        self.inner.add_job(queue_name, job).await
+    }
+
+    /// Compresses the message of a job and adds the job to the specified queue, available after the given delay.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to add the job to.
+    /// * `job` - The job data to add to the queue.
+    /// * `delay_secs` - The delay in seconds before the job can be fetched.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn add_job_after(&mut self, queue_name: &str, job: JobData, delay_secs: i64) -> RedisResult<()> {
+        let job = self.encode(job)?;
+        self.inner.add_job_after(queue_name, job, delay_secs).await
+    }
+
+    /// Retrieves the next job from the specified queue, decompressed.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to retrieve the job from.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the serialized job, or `None` if the queue is empty.
+    async fn get_next_job(&mut self, queue_name: &str) -> RedisResult<Option<Vec<String>>> {
+        let next_job = self.inner.get_next_job(queue_name).await?;
+        Ok(next_job.map(|members| members.into_iter().map(decode_member).collect()))
+    }
+
+    /// Blocks until a job is available in the specified queue or the timeout elapses, and returns it decompressed.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to retrieve the job from.
+    /// * `timeout_secs` - The maximum time to wait, in seconds.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the serialized job, or `None` if the timeout elapsed.
+    async fn wait_for_next_job(&mut self, queue_name: &str, timeout_secs: f64) -> RedisResult<Option<Vec<String>>> {
+        let next_job = self.inner.wait_for_next_job(queue_name, timeout_secs).await?;
+        Ok(next_job.map(|members| members.into_iter().map(decode_member).collect()))
+    }
+
+    /// Counts the number of jobs in the specified queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to count the jobs of.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the number of jobs.
+    async fn count_jobs(&mut self, queue_name: &str) -> RedisResult<u64> {
+        self.inner.count_jobs(queue_name).await
+    }
+
+    /// Counts the jobs of the specified queue in a given state.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to count the jobs of.
+    /// * `state` - The state of the jobs to count.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the number of jobs in the state.
+    async fn count_jobs_by_state(&mut self, queue_name: &str, state: JobState) -> RedisResult<u64> {
+        self.inner.count_jobs_by_state(queue_name, state).await
+    }
+
+    /// Updates the progress of a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    /// * `progress` - The progress value to update.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn update_job_progress(&mut self, queue_name: &str, job_id: &str, progress: u32) -> RedisResult<()> {
+        self.inner.update_job_progress(queue_name, job_id, progress).await
+    }
+
+    /// Retrieves the progress of a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the progress value of the job, or `None` if none was set.
+    async fn get_job_progress(&mut self, queue_name: &str, job_id: &str) -> RedisResult<Option<u32>> {
+        self.inner.get_job_progress(queue_name, job_id).await
+    }
+
+    /// Compresses the message of a job and moves the job to the failed queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job` - The job data to move to the failed queue.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn move_to_failed(&mut self, queue_name: &str, job: JobData) -> RedisResult<()> {
+        let job = self.encode(job)?;
+        self.inner.move_to_failed(queue_name, job).await
+    }
+
+    /// Logs the status of a job, with its message compressed as it is stored.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job` - The job data.
+    /// * `status` - The status to log.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn log_job_status(&mut self, queue_name: &str, job: &JobData, status: &str) -> RedisResult<()> {
+        let job = self.encode(job.clone())?;
+        self.inner.log_job_status(queue_name, &job, status).await
+    }
+
+    /// Marks a job as completed and records its metrics.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job` - The completed job.
+    /// * `processing_ms` - The time spent processing the job, in milliseconds.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn mark_completed(&mut self, queue_name: &str, job: &JobData, processing_ms: u64) -> RedisResult<()> {
+        self.inner.mark_completed(queue_name, job, processing_ms).await
+    }
+
+    /// Retrieves the per-minute metrics of a queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue.
+    /// * `kind` - The kind of metric to retrieve.
+    /// * `range` - The range of minutes to retrieve.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the data points, oldest first.
+    async fn get_metrics(&mut self, queue_name: &str, kind: MetricKind, range: MetricsRange) -> RedisResult<Vec<MetricPoint>> {
+        self.inner.get_metrics(queue_name, kind, range).await
+    }
+
+    /// Reads a page of the queue log, with the logged messages decompressed.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue.
+    /// * `job_id` - Only return the entries of this job when set.
+    /// * `range` - The page of entries to read, most recent first.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the log entries.
+    async fn read_logs(&mut self, queue_name: &str, job_id: Option<&str>, range: LogRange) -> RedisResult<Vec<LogEntry>> {
+        let entries = self.inner.read_logs(queue_name, job_id, range).await?;
+        Ok(entries.into_iter().map(decode_entry).collect())
+    }
+
+    /// Appends a line to the log of a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    /// * `line` - The line to append.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the number of lines in the log.
+    async fn job_log(&mut self, queue_name: &str, job_id: &str, line: &str) -> RedisResult<u64> {
+        self.inner.job_log(queue_name, job_id, line).await
+    }
+
+    /// Reads lines from the log of a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    /// * `start` - The index of the first line, negative values count from the end.
+    /// * `end` - The index of the last line, negative values count from the end.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the log lines.
+    async fn get_job_logs(&mut self, queue_name: &str, job_id: &str, start: isize, end: isize) -> RedisResult<Vec<String>> {
+        self.inner.get_job_logs(queue_name, job_id, start, end).await
+    }
+
+    /// Removes a job together with its progress and log lines.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the job was found.
+    async fn remove_job(&mut self, queue_name: &str, job_id: &str) -> RedisResult<bool> {
+        self.inner.remove_job(queue_name, job_id).await
+    }
+
+    /// Compresses the messages of several jobs and adds them to the specified queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to add the jobs to.
+    /// * `jobs` - The jobs to add.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn add_jobs(&mut self, queue_name: &str, jobs: Vec<JobData>) -> RedisResult<()> {
+        let jobs = jobs.into_iter().map(|job| self.encode(job)).collect::<RedisResult<Vec<JobData>>>()?;
+        self.inner.add_jobs(queue_name, jobs).await
+    }
+
+    /// Lists a page of the jobs of a queue in a given state, decompressed.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to list the jobs of.
+    /// * `state` - The state of the jobs to list.
+    /// * `offset` - The number of jobs to skip.
+    /// * `limit` - The maximum number of jobs to return.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the jobs.
+    async fn list_jobs(&mut self, queue_name: &str, state: JobState, offset: usize, limit: usize) -> RedisResult<Vec<JobData>> {
+        let jobs = self.inner.list_jobs(queue_name, state, offset, limit).await?;
+        Ok(jobs.into_iter().map(decode_job).collect())
+    }
+
+    /// Finds a waiting or failed job by its ID, decompressed.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the state and data of the job, or `None` if it is not found.
+    async fn get_job(&mut self, queue_name: &str, job_id: &str) -> RedisResult<Option<(JobState, JobData)>> {
+        let found = self.inner.get_job(queue_name, job_id).await?;
+        Ok(found.map(|(state, job)| (state, decode_job(job))))
+    }
+
+    /// Moves a failed job back to the waiting jobs, ready to be fetched immediately.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the failed job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the job was found in the failed queue.
+    async fn retry_job(&mut self, queue_name: &str, job_id: &str) -> RedisResult<bool> {
+        self.inner.retry_job(queue_name, job_id).await
+    }
+
+    /// Removes a failed job from the failed jobs of a queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job failed in.
+    /// * `job_id` - The ID of the failed job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the job was found in the failed queue.
+    async fn remove_failed_job(&mut self, queue_name: &str, job_id: &str) -> RedisResult<bool> {
+        self.inner.remove_failed_job(queue_name, job_id).await
+    }
+
+    /// Lists the failed jobs of a queue selected by a filter, decompressed.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to list the failed jobs of.
+    /// * `filter` - The filter selecting the jobs.
+    /// * `offset` - The number of selected jobs to skip.
+    /// * `limit` - The maximum number of jobs to return.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the selected jobs, most recently failed first.
+    async fn list_failed_jobs(
+        &mut self,
+        queue_name: &str,
+        filter: &FailedJobFilter,
+        offset: usize,
+        limit: usize,
+    ) -> RedisResult<Vec<JobData>> {
+        let jobs = self.inner.list_failed_jobs(queue_name, filter, offset, limit).await?;
+        Ok(jobs.into_iter().map(decode_job).collect())
+    }
+
+    /// Moves the failed jobs selected by a filter back to the waiting jobs.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the jobs failed in.
+    /// * `filter` - The filter selecting the jobs.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the IDs of the retried jobs.
+    async fn retry_failed_jobs(&mut self, queue_name: &str, filter: &FailedJobFilter) -> RedisResult<Vec<String>> {
+        self.inner.retry_failed_jobs(queue_name, filter).await
+    }
+
+    /// Pauses the specified queue, workers stop fetching its jobs until it is resumed.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to pause.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn pause_queue(&mut self, queue_name: &str) -> RedisResult<()> {
+        self.inner.pause_queue(queue_name).await
+    }
+
+    /// Resumes a paused queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to resume.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn resume_queue(&mut self, queue_name: &str) -> RedisResult<()> {
+        self.inner.resume_queue(queue_name).await
+    }
+
+    /// Tells whether a queue is paused.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the queue is paused.
+    async fn is_paused(&mut self, queue_name: &str) -> RedisResult<bool> {
+        self.inner.is_paused(queue_name).await
+    }
+
+    /// Removes the jobs in a given state older than the grace period.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to clean.
+    /// * `state` - The state of the jobs to remove.
+    /// * `grace_secs` - The minimum age, in seconds, of the removed jobs.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the number of removed jobs.
+    async fn clean_jobs(&mut self, queue_name: &str, state: JobState, grace_secs: u64) -> RedisResult<u64> {
+        self.inner.clean_jobs(queue_name, state, grace_secs).await
+    }
+
+    /// Lists the queues holding any data.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the names of the queues, sorted.
+    async fn list_queues(&mut self) -> RedisResult<Vec<String>> {
+        self.inner.list_queues().await
+    }
+
+    /// Requests the cancellation of a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn cancel_job(&mut self, queue_name: &str, job_id: &str) -> RedisResult<()> {
+        self.inner.cancel_job(queue_name, job_id).await
+    }
+
+    /// Consumes the cancellation request of a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the cancellation of the job was requested.
+    async fn take_job_cancellation(&mut self, queue_name: &str, job_id: &str) -> RedisResult<bool> {
+        self.inner.take_job_cancellation(queue_name, job_id).await
+    }
+
+    /// Returns a new handle on the same queues.
+    ///
+    /// # Returns
+    ///
+    /// The boxed copy of the queue service.
+    fn clone_box(&self) -> Box<dyn QueueServiceTrait> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,310 @@
+use redis::aio::{ConnectionLike, ConnectionManager};
+use redis::{Cmd, ErrorKind, Pipeline, RedisError, RedisFuture, Value};
+#[cfg(feature = "sentinel")]
+use redis::RedisResult;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+#[cfg(feature = "cluster")]
+use redis::cluster_async::ClusterConnection;
+#[cfg(feature = "sentinel")]
+use redis::aio::ConnectionManagerConfig;
+#[cfg(feature = "sentinel")]
+use redis::sentinel::{Sentinel, SentinelNodeConnectionInfo};
+#[cfg(feature = "sentinel")]
+use std::sync::Arc;
+#[cfg(feature = "sentinel")]
+use tokio::sync::Mutex;
+
+/// The way a failed command is retried.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RetryMethod {
+    /// The connection was lost. The command may have run before the reply was lost, so it is returned, never replayed.
+    Reconnect,
+    /// The slot is being migrated (`ASK`), retry so the command is sent to the importing node.
+    AskRedirect,
+    /// The slot has moved (`MOVED`), retry once the slot map has been refreshed.
+    MovedRedirect,
+    /// The cluster is temporarily unavailable (`TRYAGAIN`, `CLUSTERDOWN`, ...), wait then retry.
+    WaitAndRetry,
+    /// The command timed out. It may still run on the server, so it is returned, never replayed.
+    RetryImmediately,
+}
+
+impl RetryMethod {
+    /// Returns how a command failing with the given error should be retried.
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - The error returned by Redis.
+    ///
+    /// # Returns
+    ///
+    /// The `RetryMethod` to apply, or `None` if the error is not transient.
+    pub fn for_error(error: &RedisError) -> Option<RetryMethod> {
+        match error.kind() {
+            ErrorKind::Moved => Some(RetryMethod::MovedRedirect),
+            ErrorKind::Ask => Some(RetryMethod::AskRedirect),
+            ErrorKind::TryAgain | ErrorKind::ClusterDown | ErrorKind::MasterDown | ErrorKind::BusyLoadingError => {
+                Some(RetryMethod::WaitAndRetry)
+            }
+            _ if error.is_connection_dropped() || error.is_connection_refusal() || error.is_io_error() => {
+                Some(RetryMethod::Reconnect)
+            }
+            _ if error.is_timeout() => Some(RetryMethod::RetryImmediately),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the server rejected the command without running it, so sending it again cannot run it twice.
+    ///
+    /// Pops, stream reads and writes are not idempotent: replaying one after a lost reply would drop
+    /// the popped job or add a duplicate, so only redirections and `WaitAndRetry` errors are replayed.
+    pub fn is_replay_safe(self) -> bool {
+        matches!(self, RetryMethod::AskRedirect | RetryMethod::MovedRedirect | RetryMethod::WaitAndRetry)
+    }
+}
+
+/// Policy deciding how many times a command rejected by the server is retried and how long to wait in between.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryPolicy {
+    /// The maximum number of retries of a command.
+    pub max_retries: u32,
+    /// The delay, in milliseconds, before retrying with `WaitAndRetry`, doubled on each retry.
+    pub wait_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { max_retries: 3, wait_ms: 100 }
+    }
+}
+
+impl RetryPolicy {
+    /// Returns the delay to wait before retrying a command, or `None` if it must not be retried.
+    ///
+    /// Errors that leave it unknown whether the command ran, such as a lost connection or a
+    /// timeout, are never retried.
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - The error returned by the last attempt.
+    /// * `retries` - The number of retries already made.
+    ///
+    /// # Returns
+    ///
+    /// The delay before the next attempt, if any.
+    pub fn retry_delay(&self, error: &RedisError, retries: u32) -> Option<Duration> {
+        let method = RetryMethod::for_error(error)?;
+        if retries >= self.max_retries || !method.is_replay_safe() {
+            return None;
+        }
+        match method {
+            RetryMethod::WaitAndRetry => Some(Duration::from_millis(self.wait_ms.saturating_mul(1 << retries.min(16)))),
+            _ => Some(Duration::ZERO),
+        }
+    }
+}
+
+/// A connection to the current master of a Redis Sentinel deployment.
+///
+/// The master is resolved through the sentinels when connecting. When a command
+/// fails because the master is unreachable or has been demoted to a replica
+/// (`READONLY`), the master is resolved again. A command rejected by a demoted
+/// master is retried on the new master, while a command whose connection was
+/// lost is returned as an error, since it may already have run. Clones share
+/// the resolved master, so a failover is only resolved once.
+#[cfg(feature = "sentinel")]
+#[derive(Clone)]
+pub struct SentinelConnection {
+    shared: Arc<Mutex<SentinelMaster>>,
+    con: ConnectionManager,
+    generation: u64,
+    policy: RetryPolicy,
+}
+
+#[cfg(feature = "sentinel")]
+struct SentinelMaster {
+    sentinel: Sentinel,
+    master_name: String,
+    node_info: SentinelNodeConnectionInfo,
+    config: ConnectionManagerConfig,
+    con: ConnectionManager,
+    generation: u64,
+}
+
+#[cfg(feature = "sentinel")]
+impl SentinelConnection {
+    /// Resolves the current master through the sentinels and connects to it.
+    ///
+    /// # Arguments
+    ///
+    /// * `sentinel` - The sentinels to query.
+    /// * `master_name` - The name of the monitored master.
+    /// * `node_info` - The authentication, database and TLS settings of the master.
+    /// * `config` - The configuration of the connection to the master.
+    /// * `policy` - The policy used to retry commands while a failover is in progress.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the connection to the current master.
+    pub async fn connect(
+        mut sentinel: Sentinel,
+        master_name: &str,
+        node_info: SentinelNodeConnectionInfo,
+        config: ConnectionManagerConfig,
+        policy: RetryPolicy,
+    ) -> RedisResult<Self> {
+        let client = sentinel.async_master_for(master_name, Some(&node_info)).await?;
+        let con = ConnectionManager::new_with_config(client, config.clone()).await?;
+        let shared = SentinelMaster {
+            sentinel,
+            master_name: master_name.to_string(),
+            node_info,
+            config,
+            con: con.clone(),
+            generation: 0,
+        };
+        Ok(Self { shared: Arc::new(Mutex::new(shared)), con, generation: 0, policy })
+    }
+
+    /// Returns `true` if the error means the connected node is no longer the master.
+    pub fn is_failover_error(error: &RedisError) -> bool {
+        error.kind() == ErrorKind::ReadOnly
+            || error.kind() == ErrorKind::MasterDown
+            || error.is_connection_dropped()
+            || error.is_connection_refusal()
+            || error.is_io_error()
+    }
+
+    /// Switches to the current master, resolving it again unless a clone already did.
+    async fn refresh_master(&mut self) -> RedisResult<()> {
+        let mut shared = self.shared.lock().await;
+        if shared.generation == self.generation {
+            let master_name = shared.master_name.clone();
+            let node_info = shared.node_info.clone();
+            let client = shared.sentinel.async_master_for(&master_name, Some(&node_info)).await?;
+            shared.con = ConnectionManager::new_with_config(client, shared.config.clone()).await?;
+            shared.generation += 1;
+        }
+        self.con = shared.con.clone();
+        self.generation = shared.generation;
+        Ok(())
+    }
+
+    /// Returns the delay before sending a command rejected by a demoted or unavailable master again,
+    /// or `None` if the error must be returned.
+    fn replay_delay(&self, error: &RedisError, retries: u32) -> Option<Duration> {
+        let rejected = error.kind() == ErrorKind::ReadOnly || error.kind() == ErrorKind::MasterDown;
+        if retries >= self.policy.max_retries || !rejected {
+            return None;
+        }
+        Some(Duration::from_millis(self.policy.wait_ms.saturating_mul(1 << retries.min(16))))
+    }
+
+    /// Handles a failed command, returning `true` if it must be sent again to the current master.
+    ///
+    /// The master is resolved again after any failover error, so the next command reaches the new master.
+    async fn recover(&mut self, error: &RedisError, retries: u32) -> bool {
+        let delay = self.replay_delay(error, retries);
+        if let Some(delay) = delay {
+            tokio::time::sleep(delay).await;
+        }
+        if Self::is_failover_error(error) {
+            if let Err(refresh_error) = self.refresh_master().await {
+                diagnostic!(warn, "Failed to resolve the Sentinel master: {}", refresh_error);
+            }
+        }
+        delay.is_some()
+    }
+}
+
+#[cfg(feature = "sentinel")]
+impl ConnectionLike for SentinelConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        Box::pin(async move {
+            let mut retries = 0;
+            loop {
+                match self.con.req_packed_command(cmd).await {
+                    Err(e) if self.recover(&e, retries).await => retries += 1,
+                    result => return result,
+                }
+            }
+        })
+    }
+
+    fn req_packed_commands<'a>(&'a mut self, cmd: &'a Pipeline, offset: usize, count: usize) -> RedisFuture<'a, Vec<Value>> {
+        Box::pin(async move {
+            let mut retries = 0;
+            loop {
+                match self.con.req_packed_commands(cmd, offset, count).await {
+                    Err(e) if self.recover(&e, retries).await => retries += 1,
+                    result => return result,
+                }
+            }
+        })
+    }
+
+    fn get_db(&self) -> i64 {
+        self.con.get_db()
+    }
+}
+
+/// A connection to a single Redis node, a Redis Cluster or a Sentinel-managed master.
+#[derive(Clone)]
+pub enum RedisConnection {
+    /// A connection to a single node, re-established automatically when lost.
+    Single(ConnectionManager),
+    /// A connection to a Redis Cluster, which follows redirections and retries `TRYAGAIN`/`CLUSTERDOWN` itself.
+    #[cfg(feature = "cluster")]
+    Cluster(ClusterConnection),
+    /// A connection to the current master of a Redis Sentinel deployment.
+    #[cfg(feature = "sentinel")]
+    Sentinel(SentinelConnection),
+}
+
+impl From<ConnectionManager> for RedisConnection {
+    fn from(con: ConnectionManager) -> Self {
+        RedisConnection::Single(con)
+    }
+}
+
+#[cfg(feature = "cluster")]
+impl From<ClusterConnection> for RedisConnection {
+    fn from(con: ClusterConnection) -> Self {
+        RedisConnection::Cluster(con)
+    }
+}
+
+impl ConnectionLike for RedisConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        match self {
+            RedisConnection::Single(con) => con.req_packed_command(cmd),
+            #[cfg(feature = "sentinel")]
+            RedisConnection::Sentinel(con) => con.req_packed_command(cmd),
+            #[cfg(feature = "cluster")]
+            RedisConnection::Cluster(con) => con.req_packed_command(cmd),
+        }
+    }
+
+    fn req_packed_commands<'a>(&'a mut self, cmd: &'a Pipeline, offset: usize, count: usize) -> RedisFuture<'a, Vec<Value>> {
+        match self {
+            RedisConnection::Single(con) => con.req_packed_commands(cmd, offset, count),
+            #[cfg(feature = "sentinel")]
+            RedisConnection::Sentinel(con) => con.req_packed_commands(cmd, offset, count),
+            #[cfg(feature = "cluster")]
+            RedisConnection::Cluster(con) => con.req_packed_commands(cmd, offset, count),
+        }
+    }
+
+    fn get_db(&self) -> i64 {
+        match self {
+            RedisConnection::Single(con) => con.get_db(),
+            #[cfg(feature = "sentinel")]
+            RedisConnection::Sentinel(con) => con.get_db(),
+            #[cfg(feature = "cluster")]
+            RedisConnection::Cluster(con) => con.get_db(),
+        }
+    }
+}
@@ -0,0 +1,261 @@
+"use strict";
+
+const REFRESH_MS = 5000;
+const PAGE_SIZE = 20;
+const CHART_MINUTES = 60;
+
+const view = { queue: null, state: "waiting", offset: 0, job: null };
+
+const $ = (id) => document.getElementById(id);
+
+async function api(method, path) {
+  // The API refuses requests changing data without this header, which cross-site forms cannot send.
+  const headers = method === "GET" ? {} : { "X-Requested-With": "XMLHttpRequest" };
+  const response = await fetch(path, { method, headers });
+  const body = await response.json();
+  if (!response.ok) {
+    throw new Error(body.error || response.statusText);
+  }
+  return body;
+}
+
+function setStatus(message, isError) {
+  $("status").textContent = message;
+  $("status").className = isError ? "error" : "muted";
+}
+
+function cell(row, text, className) {
+  const td = row.insertCell();
+  td.textContent = text;
+  if (className) {
+    td.className = className;
+  }
+  return td;
+}
+
+function button(label, className, onClick) {
+  const element = document.createElement("button");
+  element.textContent = label;
+  element.className = className;
+  element.addEventListener("click", (event) => {
+    event.stopPropagation();
+    onClick();
+  });
+  return element;
+}
+
+async function act(method, path, message) {
+  try {
+    await api(method, path);
+    setStatus(message, false);
+  } catch (error) {
+    setStatus(error.message, true);
+  }
+  await refresh(false);
+}
+
+function queuePath(queue) {
+  return "/queues/" + encodeURIComponent(queue);
+}
+
+function jobPath(queue, id) {
+  return queuePath(queue) + "/jobs/" + encodeURIComponent(id);
+}
+
+async function renderQueues() {
+  const { queues } = await api("GET", "/queues");
+  const rows = $("queue-rows");
+  rows.replaceChildren();
+  $("no-queues").hidden = queues.length > 0;
+  for (const queue of queues) {
+    const row = rows.insertRow();
+    row.classList.toggle("selected", queue.queue === view.queue);
+    const name = cell(row, queue.queue);
+    if (queue.paused) {
+      const badge = document.createElement("span");
+      badge.className = "badge";
+      badge.textContent = "paused";
+      name.append(" ", badge);
+    }
+    cell(row, queue.counts.waiting);
+    cell(row, queue.counts.failed);
+    const toggle = queue.paused
+      ? button("Resume", "action", () => act("POST", queuePath(queue.queue) + "/resume", "Resumed " + queue.queue))
+      : button("Pause", "", () => act("POST", queuePath(queue.queue) + "/pause", "Paused " + queue.queue));
+    row.insertCell().append(toggle);
+    row.addEventListener("click", () => selectQueue(queue.queue));
+  }
+}
+
+function renderChart(svg, points, className) {
+  svg.replaceChildren();
+  const max = Math.max(1, ...points.map((point) => point.count));
+  const width = 600 / Math.max(1, points.length);
+  points.forEach((point, index) => {
+    const height = (point.count / max) * 110;
+    const rect = document.createElementNS("http://www.w3.org/2000/svg", "rect");
+    rect.setAttribute("x", index * width);
+    rect.setAttribute("y", 120 - height);
+    rect.setAttribute("width", Math.max(1, width - 1));
+    rect.setAttribute("height", height);
+    rect.setAttribute("class", className);
+    const title = document.createElementNS("http://www.w3.org/2000/svg", "title");
+    title.textContent = new Date(point.timestamp * 1000).toLocaleTimeString() + ": " + point.count;
+    rect.append(title);
+    svg.append(rect);
+  });
+}
+
+async function renderQueue() {
+  if (!view.queue) {
+    return;
+  }
+  $("queue").hidden = false;
+  $("queue-name").textContent = view.queue;
+  for (const kind of ["completed", "failed"]) {
+    const { points } = await api("GET", queuePath(view.queue) + "/metrics?kind=" + kind + "&minutes=" + CHART_MINUTES);
+    renderChart($("chart-" + kind), points, kind);
+  }
+  for (const tab of document.querySelectorAll(".tabs [data-state]")) {
+    tab.classList.toggle("active", tab.dataset.state === view.state);
+  }
+  $("retry-all").hidden = view.state !== "failed";
+
+  const query = "?state=" + view.state + "&offset=" + view.offset + "&limit=" + PAGE_SIZE;
+  const { jobs } = await api("GET", queuePath(view.queue) + "/jobs" + query);
+  const rows = $("job-rows");
+  rows.replaceChildren();
+  for (const job of jobs) {
+    const row = rows.insertRow();
+    row.classList.toggle("selected", job.id === view.job);
+    cell(row, job.id);
+    cell(row, new Date(job.timestamp).toLocaleString());
+    cell(row, job.attempts_made || 0);
+    cell(row, job.failed_reason || job.message, "message");
+    row.addEventListener("click", () => selectJob(job.id));
+  }
+  $("page").textContent = jobs.length ? view.offset + 1 + "-" + (view.offset + jobs.length) : "No job";
+  $("previous").disabled = view.offset === 0;
+  $("next").disabled = jobs.length < PAGE_SIZE;
+}
+
+function formatPayload(message) {
+  try {
+    return JSON.stringify(JSON.parse(message), null, 2);
+  } catch (error) {
+    return message;
+  }
+}
+
+async function renderJob() {
+  if (!view.queue || !view.job) {
+    $("job").hidden = true;
+    return;
+  }
+  let details;
+  try {
+    details = await api("GET", jobPath(view.queue, view.job));
+  } catch (error) {
+    view.job = null;
+    $("job").hidden = true;
+    return;
+  }
+  const { state, job } = details;
+  $("job").hidden = false;
+  $("job-id").textContent = job.id;
+  $("retry-job").hidden = state !== "failed";
+
+  const fields = $("job-fields");
+  fields.replaceChildren();
+  const values = [
+    ["State", state],
+    ["Added", new Date(job.timestamp).toLocaleString()],
+    ["Priority", job.priority],
+    ["Delay", job.delay],
+    ["Retries", job.retries],
+    ["Attempts", job.attempts_made],
+  ];
+  for (const [name, value] of values) {
+    if (value === undefined || value === null) {
+      continue;
+    }
+    const dt = document.createElement("dt");
+    dt.textContent = name;
+    const dd = document.createElement("dd");
+    dd.textContent = value;
+    fields.append(dt, dd);
+  }
+
+  let progress = null;
+  try {
+    progress = (await api("GET", jobPath(view.queue, job.id) + "/progress")).progress;
+  } catch (error) {
+    // Jobs without progress answer 404.
+  }
+  $("job-progress").value = progress || 0;
+  $("job-progress-value").textContent = progress === null ? "none" : progress + "%";
+  $("job-reason").textContent = job.failed_reason || "none";
+  const failures = (job.failures || []).map((failure) => {
+    const line = "#" + failure.attempt + " " + new Date(failure.failed_at).toLocaleString()
+      + " (" + failure.duration_ms + "ms): " + failure.reason;
+    return failure.stack ? line + "\n" + failure.stack : line;
+  });
+  $("job-failures").textContent = failures.length ? failures.join("\n") : "none";
+  $("job-payload").textContent = formatPayload(job.message);
+  const { lines } = await api("GET", jobPath(view.queue, job.id) + "/logs?limit=1000");
+  $("job-logs").textContent = lines.length ? lines.join("\n") : "none";
+}
+
+async function refresh(updateStatus = true) {
+  try {
+    await renderQueues();
+    await renderQueue();
+    await renderJob();
+    if (updateStatus) {
+      setStatus("Updated " + new Date().toLocaleTimeString(), false);
+    }
+  } catch (error) {
+    setStatus(error.message, true);
+  }
+}
+
+function selectQueue(queue) {
+  view.queue = queue;
+  view.state = "waiting";
+  view.offset = 0;
+  view.job = null;
+  refresh();
+}
+
+function selectJob(id) {
+  view.job = id;
+  refresh();
+}
+
+for (const tab of document.querySelectorAll(".tabs [data-state]")) {
+  tab.addEventListener("click", () => {
+    view.state = tab.dataset.state;
+    view.offset = 0;
+    refresh();
+  });
+}
+$("previous").addEventListener("click", () => {
+  view.offset = Math.max(0, view.offset - PAGE_SIZE);
+  refresh();
+});
+$("next").addEventListener("click", () => {
+  view.offset += PAGE_SIZE;
+  refresh();
+});
+$("retry-all").addEventListener("click", () => act("POST", queuePath(view.queue) + "/retry", "Retried all failed jobs"));
+$("retry-job").addEventListener("click", () => act("POST", jobPath(view.queue, view.job) + "/retry", "Retried " + view.job));
+$("remove-job").addEventListener("click", () => {
+  if (confirm("Remove job " + view.job + "?")) {
+    const id = view.job;
+    view.job = null;
+    act("DELETE", jobPath(view.queue, id), "Removed " + id);
+  }
+});
+
+refresh();
+setInterval(refresh, REFRESH_MS);
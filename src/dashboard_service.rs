@@ -0,0 +1,32 @@
+use axum::http::header::{CACHE_CONTROL, CONTENT_TYPE};
+use axum::response::{IntoResponse, Redirect};
+use axum::routing::get;
+use axum::Router;
+
+/// The page of the dashboard.
+const INDEX_HTML: &str = include_str!("dashboard/index.html");
+
+/// The script of the dashboard, calling the REST API of [`crate::api_service`].
+const APP_JS: &str = include_str!("dashboard/app.js");
+
+/// The stylesheet of the dashboard.
+const STYLE_CSS: &str = include_str!("dashboard/style.css");
+
+/// Builds the router serving the web dashboard under `/dashboard`.
+///
+/// The assets are bundled into the binary, so the dashboard only needs the
+/// REST API routes served on the same origin. The dashboard lists the queues
+/// with their counts, charts the completed and failed jobs of the last hour,
+/// and lets users browse jobs and retry, remove or pause them.
+pub fn router() -> Router {
+    Router::new()
+        .route("/", get(|| async { Redirect::permanent("/dashboard/") }))
+        .route("/dashboard", get(|| async { Redirect::permanent("/dashboard/") }))
+        .route("/dashboard/", get(|| async { asset("text/html; charset=utf-8", INDEX_HTML) }))
+        .route("/dashboard/app.js", get(|| async { asset("text/javascript; charset=utf-8", APP_JS) }))
+        .route("/dashboard/style.css", get(|| async { asset("text/css; charset=utf-8", STYLE_CSS) }))
+}
+
+fn asset(content_type: &'static str, body: &'static str) -> impl IntoResponse {
+    ([(CONTENT_TYPE, content_type), (CACHE_CONTROL, "no-cache")], body)
+}
@@ -0,0 +1,798 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use redis::{RedisError, RedisResult};
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::Notify;
+use crate::job_model::{JobData, JobState};
+use crate::log_model::{list_range, LogEntry, LogRange};
+use crate::log_service::{DEFAULT_MAX_JOB_LOG_LINES, DEFAULT_MAX_LOG_ENTRIES};
+use crate::metrics_model::{histogram_bucket, minute_bucket, MetricKind, MetricPoint, MetricsRange};
+use crate::metrics_service::DEFAULT_METRICS_RETENTION_MINUTES;
+use crate::queue_service::CANCELLATION_TTL_SECS;
+use crate::QueueServiceTrait;
+
+/// A sorted set of serialized jobs, ordered by score then by member like a Redis sorted set.
+type JobSet = BTreeSet<(i64, String)>;
+
+/// The data points recorded for one metric during one minute.
+#[derive(Default)]
+struct MetricCell {
+    count: u64,
+    buckets: HashMap<u64, u64>,
+}
+
+/// Everything stored for one queue.
+#[derive(Default)]
+struct QueueState {
+    /// The jobs ready to be fetched, scored with the time they became ready in milliseconds.
+    waiting: JobSet,
+    /// The jobs waiting for their delay, scored with their due time in seconds.
+    delayed: JobSet,
+    /// The score of the last job added to `waiting`, so jobs added within the same millisecond keep their order.
+    last_ready_ms: i64,
+    failed: JobSet,
+    progress: HashMap<String, u32>,
+    log: VecDeque<LogEntry>,
+    job_logs: HashMap<String, Vec<String>>,
+    metrics: HashMap<(MetricKind, i64), MetricCell>,
+    paused: bool,
+    /// The jobs whose cancellation is requested, with the time of the request in seconds.
+    cancelled: HashMap<String, i64>,
+}
+
+impl QueueState {
+    /// Returns `true` if nothing is stored for the queue.
+    fn is_empty(&self) -> bool {
+        self.waiting.is_empty()
+            && self.delayed.is_empty()
+            && self.failed.is_empty()
+            && self.progress.is_empty()
+            && self.log.is_empty()
+            && self.job_logs.is_empty()
+            && self.metrics.is_empty()
+            && !self.paused
+            && self.cancelled.is_empty()
+    }
+
+    /// Adds a job to the waiting jobs, or to the delayed jobs until it is due,
+    /// replacing an identical job.
+    fn enqueue(&mut self, job: &JobData, delay_secs: i64, now_ms: i64) -> RedisResult<()> {
+        let member = serde_json::to_string(job).map_err(|e| {
+            RedisError::from((redis::ErrorKind::TypeError, "Failed to serialize job", e.to_string()))
+        })?;
+        self.waiting.retain(|(_, existing)| *existing != member);
+        self.delayed.retain(|(_, existing)| *existing != member);
+        match Some(delay_secs).filter(|delay| *delay > 0) {
+            Some(delay) => self.delayed.insert((now_ms + delay.saturating_mul(1000), member)),
+            None => {
+                let score = self.ready_score(now_ms);
+                self.waiting.insert((score, member))
+            }
+        };
+        Ok(())
+    }
+
+    /// Returns the score of a job becoming ready now, after every job already made ready.
+    fn ready_score(&mut self, now_ms: i64) -> i64 {
+        self.last_ready_ms = now_ms.max(self.last_ready_ms + 1);
+        self.last_ready_ms
+    }
+
+    /// Moves the due delayed jobs to the waiting jobs, scored with their due time.
+    fn promote_delayed(&mut self) {
+        let now_ms = Utc::now().timestamp_millis();
+        while let Some((due, member)) = self.delayed.first().cloned() {
+            if due > now_ms {
+                break;
+            }
+            self.delayed.remove(&(due, member.clone()));
+            self.waiting.insert((due, member));
+        }
+    }
+
+    /// Returns the time until the next delayed job is due, if any.
+    fn next_due_in(&self) -> Option<Duration> {
+        let (due, _) = self.delayed.first()?;
+        Some(Duration::from_millis((due - Utc::now().timestamp_millis()).max(0) as u64))
+    }
+
+    /// Pops the next waiting job, unless the queue is paused.
+    fn next_job(&mut self) -> Option<Vec<String>> {
+        if self.paused {
+            return None;
+        }
+        self.promote_delayed();
+        InMemoryQueueService::pop_min(&mut self.waiting)
+    }
+}
+
+/// State shared by all clones of an `InMemoryQueueService`.
+#[derive(Default)]
+struct Shared {
+    queues: Mutex<HashMap<String, QueueState>>,
+    job_added: Notify,
+}
+
+/// In-memory implementation of `QueueServiceTrait` for tests and local development.
+///
+/// Jobs, progress, logs and metrics are kept in process memory with the same
+/// semantics as `QueueService`: waiting jobs are fetched in the order they
+/// became ready, delayed jobs are held until they are due, a job serialized
+/// identically is only stored once, and logs and metrics are capped like in Redis.
+/// Clones share the same storage, so a producer and a worker can each hold one.
+#[derive(Clone)]
+pub struct InMemoryQueueService {
+    shared: Arc<Shared>,
+    max_entries: usize,
+    max_job_lines: usize,
+    retention_minutes: u64,
+}
+
+impl Default for InMemoryQueueService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InMemoryQueueService {
+    /// Creates a new, empty `InMemoryQueueService`.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of `InMemoryQueueService`.
+    pub fn new() -> Self {
+        Self {
+            shared: Arc::new(Shared::default()),
+            max_entries: DEFAULT_MAX_LOG_ENTRIES,
+            max_job_lines: DEFAULT_MAX_JOB_LOG_LINES,
+            retention_minutes: DEFAULT_METRICS_RETENTION_MINUTES,
+        }
+    }
+
+    /// Sets the maximum number of lines kept in each job log.
+    pub fn with_max_job_log_lines(mut self, max_lines: usize) -> Self {
+        self.max_job_lines = max_lines.max(1);
+        self
+    }
+
+    /// Sets the maximum number of entries kept in each queue log.
+    pub fn with_max_log_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries.max(1);
+        self
+    }
+
+    /// Sets the number of minutes metrics data points are kept for.
+    pub fn with_metrics_retention(mut self, retention_minutes: u64) -> Self {
+        self.retention_minutes = retention_minutes.max(1);
+        self
+    }
+
+    /// Returns the jobs of a queue in the given state, in the order they would be fetched.
+    ///
+    /// Waiting jobs are followed by the delayed jobs by due time, failed jobs are most recent first.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue.
+    /// * `state` - The state of the jobs to return.
+    ///
+    /// # Returns
+    ///
+    /// The stored jobs, next job first.
+    pub fn jobs(&self, queue_name: &str, state: JobState) -> Vec<JobData> {
+        let queues = self.lock();
+        let Some(queue) = queues.get(queue_name) else {
+            return Vec::new();
+        };
+        let members: Vec<&String> = match state {
+            JobState::Waiting => queue.waiting.iter().chain(&queue.delayed).map(|(_, member)| member).collect(),
+            JobState::Failed => queue.failed.iter().rev().map(|(_, member)| member).collect(),
+        };
+        members.into_iter().filter_map(|member| serde_json::from_str(member).ok()).collect()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, QueueState>> {
+        self.shared.queues.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Runs a closure on the state of a queue, creating it if needed.
+    fn with_queue<T>(&self, queue_name: &str, f: impl FnOnce(&mut QueueState) -> T) -> T {
+        f(self.lock().entry(queue_name.to_string()).or_default())
+    }
+
+    /// Adds a job to a sorted set scored with the current time, replacing the score of an identical member.
+    fn push_job(set: &mut JobSet, job: &JobData) -> RedisResult<()> {
+        let member = serde_json::to_string(job).map_err(|e| {
+            RedisError::from((redis::ErrorKind::TypeError, "Failed to serialize job", e.to_string()))
+        })?;
+        set.retain(|(_, existing)| *existing != member);
+        set.insert((Utc::now().timestamp(), member));
+        Ok(())
+    }
+
+    /// Pops the member with the lowest score, like `ZPOPMIN`.
+    fn pop_min(set: &mut JobSet) -> Option<Vec<String>> {
+        let (score, member) = set.pop_first()?;
+        Some(vec![member, score.to_string()])
+    }
+
+    fn record_metric(&self, queue_name: &str, kind: MetricKind, duration_ms: Option<u64>) {
+        let minute = minute_bucket(Utc::now().timestamp());
+        self.with_queue(queue_name, |queue| {
+            let cell = queue.metrics.entry((kind, minute)).or_default();
+            cell.count += 1;
+            if let Some(duration_ms) = duration_ms {
+                *cell.buckets.entry(histogram_bucket(duration_ms)).or_insert(0) += 1;
+            }
+        });
+    }
+}
+
+#[async_trait]
+impl QueueServiceTrait for InMemoryQueueService {
+    /// Adds a job to the specified queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to add the job to.
+    /// * `job` - The job data to add to the queue.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn add_job(&mut self, queue_name: &str, job: JobData) -> RedisResult<()> {
+        let delay_secs = job.delay.unwrap_or(0);
+        self.add_job_after(queue_name, job, delay_secs).await
+    }
+
+    /// Adds a job to the specified queue, available after the given delay rather than its own.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to add the job to.
+    /// * `job` - The job data to add to the queue.
+    /// * `delay_secs` - The delay in seconds before the job can be fetched.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn add_job_after(&mut self, queue_name: &str, job: JobData, delay_secs: i64) -> RedisResult<()> {
+        let now_ms = Utc::now().timestamp_millis();
+        self.with_queue(queue_name, |queue| queue.enqueue(&job, delay_secs, now_ms))?;
+        self.shared.job_added.notify_waiters();
+        Ok(())
+    }
+
+    /// Retrieves the next job from the specified queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to retrieve the job from.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the job JSON string and its score, `None` while the queue is paused,
+    /// or an error if the queue is empty.
+    async fn get_next_job(&mut self, queue_name: &str) -> RedisResult<Option<Vec<String>>> {
+        if self.with_queue(queue_name, |queue| queue.paused) {
+            return Ok(None);
+        }
+        match self.with_queue(queue_name, QueueState::next_job) {
+            Some(next_job) => Ok(Some(next_job)),
+            None => Err(RedisError::from((redis::ErrorKind::TypeError, "No job found"))),
+        }
+    }
+
+    /// Waits for the next job from the specified queue, waking up when the next delayed job is due.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to retrieve the job from.
+    /// * `timeout_secs` - The maximum time in seconds to block, `0` blocks forever.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the job JSON string and its score, or `None` on timeout.
+    /// A paused queue yields no job until it is resumed.
+    async fn wait_for_next_job(&mut self, queue_name: &str, timeout_secs: f64) -> RedisResult<Option<Vec<String>>> {
+        let deadline = (timeout_secs > 0.0)
+            .then(|| tokio::time::Instant::now() + Duration::from_secs_f64(timeout_secs));
+        loop {
+            // Registered before checking the queue so a job added or a resume in between is not missed.
+            let job_added = self.shared.job_added.notified();
+            let (next_job, next_due_in) = self.with_queue(queue_name, |queue| (queue.next_job(), queue.next_due_in()));
+            if let Some(next_job) = next_job {
+                return Ok(Some(next_job));
+            }
+            let wake_up = next_due_in.map(|next_due_in| tokio::time::Instant::now() + next_due_in);
+            match (deadline, wake_up) {
+                (Some(deadline), _) if deadline <= tokio::time::Instant::now() => return Ok(None),
+                (Some(deadline), Some(wake_up)) => {
+                    let _ = tokio::time::timeout_at(deadline.min(wake_up), job_added).await;
+                }
+                (Some(deadline), None) => {
+                    let _ = tokio::time::timeout_at(deadline, job_added).await;
+                }
+                (None, Some(wake_up)) => {
+                    let _ = tokio::time::timeout_at(wake_up, job_added).await;
+                }
+                (None, None) => job_added.await,
+            }
+        }
+    }
+
+    /// Counts the number of jobs in the specified queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to count the jobs in.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the number of jobs in the queue.
+    async fn count_jobs(&mut self, queue_name: &str) -> RedisResult<u64> {
+        self.count_jobs_by_state(queue_name, JobState::Waiting).await
+    }
+
+    /// Counts the number of jobs of the specified queue in the given state.
+    ///
+    /// Waiting jobs include the delayed jobs.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to count the jobs in.
+    /// * `state` - The state of the jobs to count.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the number of jobs in the given state.
+    async fn count_jobs_by_state(&mut self, queue_name: &str, state: JobState) -> RedisResult<u64> {
+        Ok(self.with_queue(queue_name, |queue| match state {
+            JobState::Waiting => queue.waiting.len() + queue.delayed.len(),
+            JobState::Failed => queue.failed.len(),
+        }) as u64)
+    }
+
+    /// Updates the progress of a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    /// * `progress` - The progress value to update.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn update_job_progress(&mut self, queue_name: &str, job_id: &str, progress: u32) -> RedisResult<()> {
+        self.with_queue(queue_name, |queue| queue.progress.insert(job_id.to_string(), progress));
+        Ok(())
+    }
+
+    /// Retrieves the progress of a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the progress value of the job, or `None` if none was set.
+    async fn get_job_progress(&mut self, queue_name: &str, job_id: &str) -> RedisResult<Option<u32>> {
+        Ok(self.with_queue(queue_name, |queue| queue.progress.get(job_id).copied()))
+    }
+
+    /// Moves a job to the failed queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job` - The job data to move to the failed queue.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn move_to_failed(&mut self, queue_name: &str, job: JobData) -> RedisResult<()> {
+        self.with_queue(queue_name, |queue| Self::push_job(&mut queue.failed, &job))?;
+        self.record_metric(queue_name, MetricKind::Failed, None);
+        Ok(())
+    }
+
+    /// Logs the status of a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job` - A reference to the job data.
+    /// * `status` - The status message to log.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn log_job_status(&mut self, queue_name: &str, job: &JobData, status: &str) -> RedisResult<()> {
+        let entry = LogEntry::job_status(job, status);
+        let max_entries = self.max_entries;
+        self.with_queue(queue_name, |queue| {
+            queue.log.push_front(entry);
+            queue.log.truncate(max_entries);
+        });
+        Ok(())
+    }
+
+    /// Marks a job as completed and records its throughput and latency metrics.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job` - A reference to the job data.
+    /// * `processing_ms` - The time spent processing the job, in milliseconds.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn mark_completed(&mut self, queue_name: &str, job: &JobData, processing_ms: u64) -> RedisResult<()> {
+        self.record_metric(queue_name, MetricKind::Completed, None);
+        self.record_metric(queue_name, MetricKind::ProcessingTime, Some(processing_ms));
+        if let Some(wait_ms) = job.wait_time_ms(processing_ms) {
+            self.record_metric(queue_name, MetricKind::WaitTime, Some(wait_ms));
+        }
+        Ok(())
+    }
+
+    /// Retrieves the per-minute data points of a queue metric.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to read the metrics of.
+    /// * `kind` - The metric to read.
+    /// * `range` - The time range to read, clamped to the retention period and to the current minute.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing one data point per minute, oldest first.
+    async fn get_metrics(&mut self, queue_name: &str, kind: MetricKind, range: MetricsRange) -> RedisResult<Vec<MetricPoint>> {
+        let oldest = Utc::now().timestamp() - (self.retention_minutes * 60) as i64;
+        let range = range.clamp_to_retention((self.retention_minutes * 60) as i64);
+        Ok(self.with_queue(queue_name, |queue| {
+            queue.metrics.retain(|(_, minute), _| *minute >= minute_bucket(oldest));
+            range
+                .minutes()
+                .into_iter()
+                .map(|timestamp| {
+                    let mut fields = HashMap::new();
+                    if let Some(cell) = queue.metrics.get(&(kind, timestamp)) {
+                        fields.insert("count".to_string(), cell.count);
+                        for (le_ms, count) in &cell.buckets {
+                            fields.insert(le_ms.to_string(), *count);
+                        }
+                    }
+                    MetricPoint::from_fields(timestamp, kind, &fields)
+                })
+                .collect()
+        }))
+    }
+
+    /// Reads entries from the specified queue's log, most recent first.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to read the log of.
+    /// * `job_id` - Only return the entries of this job, if set.
+    /// * `range` - The page of matching entries to return.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the matching entries.
+    async fn read_logs(&mut self, queue_name: &str, job_id: Option<&str>, range: LogRange) -> RedisResult<Vec<LogEntry>> {
+        Ok(self.with_queue(queue_name, |queue| {
+            queue
+                .log
+                .iter()
+                .filter(|entry| job_id.is_none() || entry.job_id.as_deref() == job_id)
+                .skip(range.offset)
+                .take(range.limit)
+                .cloned()
+                .collect()
+        }))
+    }
+
+    /// Appends a line to the log of a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    /// * `line` - The line to append.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the number of lines in the job log.
+    async fn job_log(&mut self, queue_name: &str, job_id: &str, line: &str) -> RedisResult<u64> {
+        let max_job_lines = self.max_job_lines;
+        Ok(self.with_queue(queue_name, |queue| {
+            let lines = queue.job_logs.entry(job_id.to_string()).or_default();
+            lines.push(line.to_string());
+            if lines.len() > max_job_lines {
+                lines.drain(..lines.len() - max_job_lines);
+            }
+            lines.len() as u64
+        }))
+    }
+
+    /// Reads lines from the log of a job, oldest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    /// * `start` - The index of the first line, negative values count from the end.
+    /// * `end` - The index of the last line, inclusive, negative values count from the end.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the requested lines.
+    async fn get_job_logs(&mut self, queue_name: &str, job_id: &str, start: isize, end: isize) -> RedisResult<Vec<String>> {
+        Ok(self.with_queue(queue_name, |queue| {
+            queue
+                .job_logs
+                .get(job_id)
+                .map(|lines| list_range(lines, start, end))
+                .unwrap_or_default()
+        }))
+    }
+
+    /// Removes a job from the queue, its delayed jobs and its failed queue, together with its progress and log lines.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the job was found in a queue.
+    async fn remove_job(&mut self, queue_name: &str, job_id: &str) -> RedisResult<bool> {
+        Ok(self.with_queue(queue_name, |queue| {
+            let is_job = |member: &str| {
+                serde_json::from_str::<JobData>(member)
+                    .map(|job| job.id == job_id)
+                    .unwrap_or(false)
+            };
+            let before = queue.waiting.len() + queue.delayed.len() + queue.failed.len();
+            queue.waiting.retain(|(_, member)| !is_job(member));
+            queue.delayed.retain(|(_, member)| !is_job(member));
+            queue.failed.retain(|(_, member)| !is_job(member));
+            queue.progress.remove(job_id);
+            queue.job_logs.remove(job_id);
+            queue.cancelled.remove(job_id);
+            queue.waiting.len() + queue.delayed.len() + queue.failed.len() < before
+        }))
+    }
+
+    /// Lists the jobs of the specified queue in the given state.
+    ///
+    /// Waiting jobs are listed in the order they will be fetched, followed by
+    /// the delayed jobs by due time. Failed jobs are listed most recent first.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to list the jobs of.
+    /// * `state` - The state of the jobs to list.
+    /// * `offset` - The number of jobs to skip.
+    /// * `limit` - The maximum number of jobs to return.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the jobs.
+    async fn list_jobs(&mut self, queue_name: &str, state: JobState, offset: usize, limit: usize) -> RedisResult<Vec<JobData>> {
+        Ok(self.jobs(queue_name, state).into_iter().skip(offset).take(limit).collect())
+    }
+
+    /// Moves a failed job back to the waiting jobs, ready to be fetched immediately.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the failed job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the job was found in the failed queue.
+    async fn retry_job(&mut self, queue_name: &str, job_id: &str) -> RedisResult<bool> {
+        let now_ms = Utc::now().timestamp_millis();
+        let retried = self.with_queue(queue_name, |queue| {
+            let failed: Vec<(i64, String)> = queue
+                .failed
+                .iter()
+                .filter(|(_, member)| {
+                    serde_json::from_str::<JobData>(member)
+                        .map(|job| job.id == job_id)
+                        .unwrap_or(false)
+                })
+                .cloned()
+                .collect();
+            for entry in &failed {
+                queue.failed.remove(entry);
+                queue.waiting.retain(|(_, existing)| *existing != entry.1);
+                let score = queue.ready_score(now_ms);
+                queue.waiting.insert((score, entry.1.clone()));
+            }
+            !failed.is_empty()
+        });
+        if retried {
+            self.shared.job_added.notify_waiters();
+        }
+        Ok(retried)
+    }
+
+    /// Removes a failed job from the failed jobs of a queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job failed in.
+    /// * `job_id` - The ID of the failed job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the job was found in the failed queue.
+    async fn remove_failed_job(&mut self, queue_name: &str, job_id: &str) -> RedisResult<bool> {
+        Ok(self.with_queue(queue_name, |queue| {
+            let before = queue.failed.len();
+            queue.failed.retain(|(_, member)| {
+                serde_json::from_str::<JobData>(member)
+                    .map(|job| job.id != job_id)
+                    .unwrap_or(true)
+            });
+            queue.failed.len() < before
+        }))
+    }
+
+    /// Pauses the specified queue, workers stop fetching its jobs until it is resumed.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to pause.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn pause_queue(&mut self, queue_name: &str) -> RedisResult<()> {
+        self.with_queue(queue_name, |queue| queue.paused = true);
+        Ok(())
+    }
+
+    /// Resumes a paused queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to resume.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn resume_queue(&mut self, queue_name: &str) -> RedisResult<()> {
+        self.with_queue(queue_name, |queue| queue.paused = false);
+        self.shared.job_added.notify_waiters();
+        Ok(())
+    }
+
+    /// Tells whether the specified queue is paused.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the queue is paused.
+    async fn is_paused(&mut self, queue_name: &str) -> RedisResult<bool> {
+        Ok(self.with_queue(queue_name, |queue| queue.paused))
+    }
+
+    /// Removes the jobs in the given state older than a grace period, together with their progress and log lines.
+    ///
+    /// Waiting jobs are aged by the time they became ready, delayed jobs by their due time.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to clean.
+    /// * `state` - The state of the jobs to remove.
+    /// * `grace_secs` - The minimum age, in seconds, of the removed jobs.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the number of removed jobs.
+    async fn clean_jobs(&mut self, queue_name: &str, state: JobState, grace_secs: u64) -> RedisResult<u64> {
+        let cutoff_ms = Utc::now().timestamp_millis() - grace_secs as i64 * 1000;
+        let cutoff = cutoff_ms.div_euclid(1000);
+        // Ready scores may run a few milliseconds ahead of the clock to keep the jobs in order,
+        // so without a grace period every waiting job is removed whatever its score.
+        let ready_cutoff_ms = if grace_secs == 0 { i64::MAX } else { cutoff_ms };
+        Ok(self.with_queue(queue_name, |queue| {
+            let sets = match state {
+                JobState::Waiting => vec![(&mut queue.waiting, ready_cutoff_ms), (&mut queue.delayed, cutoff_ms)],
+                JobState::Failed => vec![(&mut queue.failed, cutoff)],
+            };
+            let mut removed: Vec<(i64, String)> = Vec::new();
+            for (set, max_score) in sets {
+                let old: Vec<(i64, String)> = set.iter().take_while(|(score, _)| *score <= max_score).cloned().collect();
+                for entry in &old {
+                    set.remove(entry);
+                }
+                removed.extend(old);
+            }
+            for job in removed.iter().filter_map(|(_, member)| serde_json::from_str::<JobData>(member).ok()) {
+                queue.progress.remove(&job.id);
+                queue.job_logs.remove(&job.id);
+            }
+            removed.len() as u64
+        }))
+    }
+
+    /// Lists the queues holding any data.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the names of the queues, sorted.
+    async fn list_queues(&mut self) -> RedisResult<Vec<String>> {
+        let mut queues: Vec<String> = self
+            .lock()
+            .iter()
+            .filter(|(_, queue)| !queue.is_empty())
+            .map(|(queue_name, _)| queue_name.clone())
+            .collect();
+        queues.sort();
+        Ok(queues)
+    }
+
+    /// Requests the cancellation of a job, dropping requests older than `CANCELLATION_TTL_SECS`.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn cancel_job(&mut self, queue_name: &str, job_id: &str) -> RedisResult<()> {
+        let now = Utc::now().timestamp();
+        self.with_queue(queue_name, |queue| {
+            queue.cancelled.retain(|_, requested_at| now - *requested_at < CANCELLATION_TTL_SECS as i64);
+            queue.cancelled.insert(job_id.to_string(), now);
+        });
+        Ok(())
+    }
+
+    /// Consumes the cancellation request of a job, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the job's cancellation was requested.
+    async fn take_job_cancellation(&mut self, queue_name: &str, job_id: &str) -> RedisResult<bool> {
+        let now = Utc::now().timestamp();
+        Ok(self.with_queue(queue_name, |queue| {
+            queue
+                .cancelled
+                .remove(job_id)
+                .is_some_and(|requested_at| now - requested_at < CANCELLATION_TTL_SECS as i64)
+        }))
+    }
+
+    /// Returns a new handle on the same queues.
+    ///
+    /// # Returns
+    ///
+    /// The boxed copy of the queue service.
+    fn clone_box(&self) -> Box<dyn QueueServiceTrait> {
+        Box::new(self.clone())
+    }
+}
@@ -1,6 +1,26 @@
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+/// Maximum number of failed attempts kept in the history of a job, the oldest are dropped first.
+pub const MAX_FAILURE_HISTORY: usize = 20;
+
+/// Sequence making the generated job IDs unique within the process.
+static JOB_ID_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// Generates a job ID unique within the process, made of the current time and a sequence number.
+pub fn generate_job_id() -> String {
+    format!(
+        "job_{}_{}",
+        chrono::Utc::now().timestamp_micros(),
+        JOB_ID_SEQUENCE.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
 pub struct JobData {
     /// The unique identifier of the job.
     pub id: String,
@@ -18,4 +38,335 @@ pub struct JobData {
     pub expires_in: Option<i64>,
     /// The progress of the job.
     pub progress: Option<u32>,
+    /// The W3C trace context (`traceparent`, `tracestate`) of the producer that added the job.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_context: Option<HashMap<String, String>>,
+    /// The number of times processing the job was attempted and failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attempts_made: Option<u32>,
+    /// Why the last attempt to process the job failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failed_reason: Option<String>,
+    /// The maximum time, in milliseconds, an attempt to process the job may take before it fails.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    /// The failed attempts to process the job, oldest first, up to `MAX_FAILURE_HISTORY`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failures: Vec<JobFailure>,
+    /// The algorithm `message` is compressed with, then base64-encoded; the message is plain when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<Compression>,
+}
+
+/// A failed attempt to process a job.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct JobFailure {
+    /// The number of the attempt, starting at 1.
+    pub attempt: u32,
+    /// Why the attempt failed.
+    pub reason: String,
+    /// The stack trace or context reported by the handler, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stack: Option<String>,
+    /// When the attempt failed, in RFC 3339 format.
+    pub failed_at: String,
+    /// The time spent on the attempt, in milliseconds.
+    pub duration_ms: u64,
+}
+
+impl JobData {
+    /// Returns the number of the current processing attempt, starting at 1.
+    pub fn attempt(&self) -> u32 {
+        self.attempts_made.unwrap_or(0) + 1
+    }
+
+    /// Records a failed attempt: counts it, sets the failure reason and appends it to the failure history.
+    ///
+    /// # Arguments
+    ///
+    /// * `reason` - Why the attempt failed.
+    /// * `stack` - The stack trace or context reported by the handler, if any.
+    /// * `duration_ms` - The time spent on the attempt, in milliseconds.
+    pub fn record_failure(&mut self, reason: &str, stack: Option<String>, duration_ms: u64) {
+        let attempt = self.attempt();
+        self.attempts_made = Some(attempt);
+        self.failed_reason = Some(reason.to_string());
+        self.failures.push(JobFailure {
+            attempt,
+            reason: reason.to_string(),
+            stack,
+            failed_at: chrono::Utc::now().to_rfc3339(),
+            duration_ms,
+        });
+        let excess = self.failures.len().saturating_sub(MAX_FAILURE_HISTORY);
+        self.failures.drain(..excess);
+    }
+
+    /// Returns when the last recorded attempt failed, if the history has one.
+    pub fn failed_at(&self) -> Option<chrono::DateTime<chrono::FixedOffset>> {
+        chrono::DateTime::parse_from_rfc3339(&self.failures.last()?.failed_at).ok()
+    }
+
+    /// Returns the time the job waited between becoming available, i.e. its
+    /// timestamp plus its delay, and the start of its processing.
+    ///
+    /// # Arguments
+    ///
+    /// * `processing_ms` - The time spent processing the job, in milliseconds.
+    ///
+    /// # Returns
+    ///
+    /// The wait time in milliseconds, or `None` if the timestamp is not RFC 3339.
+    pub fn wait_time_ms(&self, processing_ms: u64) -> Option<u64> {
+        let created_at = chrono::DateTime::parse_from_rfc3339(&self.timestamp).ok()?;
+        let available_at = created_at.timestamp_millis() + self.delay.unwrap_or(0) * 1000;
+        let started_at = chrono::Utc::now().timestamp_millis() - processing_ms as i64;
+        Some((started_at - available_at).max(0) as u64)
+    }
+}
+
+/// The description of a job to add, as given on the command line or in a JSON file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct JobSpec {
+    /// The ID of the job, generated if unset.
+    #[serde(default)]
+    pub id: Option<String>,
+    /// The message of the job, JSON values other than strings are stored serialized.
+    pub message: Value,
+    /// The priority of the job.
+    #[serde(default)]
+    pub priority: Option<i32>,
+    /// The delay, in seconds, before the job can be processed.
+    #[serde(default)]
+    pub delay: Option<i64>,
+    /// The number of retries allowed for the job.
+    #[serde(default)]
+    pub retries: Option<u32>,
+    /// The maximum time, in milliseconds, an attempt to process the job may take.
+    #[serde(default)]
+    pub timeout: Option<u64>,
+}
+
+impl JobSpec {
+    /// Validates the spec and turns it into a job timestamped now.
+    ///
+    /// # Returns
+    ///
+    /// The job, or a description of the invalid field.
+    pub fn into_job(self) -> Result<JobData, String> {
+        let id = match self.id {
+            Some(id) if id.trim().is_empty() => return Err("id must not be empty".to_string()),
+            Some(id) => id,
+            None => generate_job_id(),
+        };
+        if self.delay.is_some_and(|delay| delay < 0) {
+            return Err("delay must not be negative".to_string());
+        }
+        if self.timeout == Some(0) {
+            return Err("timeout must be positive".to_string());
+        }
+        let message = match self.message {
+            Value::String(message) => message,
+            message => message.to_string(),
+        };
+        Ok(JobData {
+            id,
+            message,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            priority: self.priority,
+            delay: self.delay,
+            retries: self.retries,
+            timeout: self.timeout,
+            ..Default::default()
+        })
+    }
+}
+
+/// A line of a JSON Lines input that is not a valid job.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobLineError {
+    /// The number of the line, starting at 1.
+    pub line: usize,
+    /// Why the line is not a valid job.
+    pub reason: String,
+}
+
+impl fmt::Display for JobLineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)
+    }
+}
+
+impl std::error::Error for JobLineError {}
+
+/// Parses one job per line of a JSON Lines input, skipping blank lines.
+///
+/// A line holding an object with `id`, `message` and `timestamp` fields is a
+/// full `JobData`, an object with a `message` field and only `JobSpec` fields is
+/// a `JobSpec` setting its own `id`, `priority`, `delay`, `retries` and `timeout`,
+/// and any other JSON value, such as `{"message": "hi", "user": 1}`, is a bare
+/// payload stored as the message of a new job.
+///
+/// # Arguments
+///
+/// * `input` - The JSON Lines input.
+///
+/// # Returns
+///
+/// The jobs in input order, or an error for every invalid line.
+pub fn parse_job_lines(input: &str) -> Result<Vec<JobData>, Vec<JobLineError>> {
+    let mut jobs = Vec::new();
+    let mut errors = Vec::new();
+    for (index, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_job_line(line) {
+            Ok(job) => jobs.push(job),
+            Err(reason) => errors.push(JobLineError { line: index + 1, reason }),
+        }
+    }
+    if errors.is_empty() {
+        Ok(jobs)
+    } else {
+        Err(errors)
+    }
+}
+
+/// The fields of a `JobSpec`, an object with any other field is a bare payload.
+const JOB_SPEC_FIELDS: [&str; 6] = ["id", "message", "priority", "delay", "retries", "timeout"];
+
+fn parse_job_line(line: &str) -> Result<JobData, String> {
+    let value: Value = serde_json::from_str(line).map_err(|e| format!("invalid JSON: {}", e))?;
+    let fields = match &value {
+        Value::Object(fields) if fields.contains_key("message") => fields,
+        _ => return JobSpec { message: value, ..Default::default() }.into_job(),
+    };
+    let is_full_job = ["id", "timestamp"].iter().all(|field| fields.contains_key(*field));
+    if !is_full_job && !fields.keys().all(|key| JOB_SPEC_FIELDS.contains(&key.as_str())) {
+        return JobSpec { message: value, ..Default::default() }.into_job();
+    }
+    if is_full_job {
+        let job: JobData = serde_json::from_value(value).map_err(|e| format!("invalid job: {}", e))?;
+        if job.id.trim().is_empty() {
+            return Err("id must not be empty".to_string());
+        }
+        return Ok(job);
+    }
+    let spec: JobSpec = serde_json::from_value(value).map_err(|e| format!("invalid job: {}", e))?;
+    spec.into_job()
+}
+
+/// Selects failed jobs by failure reason and age; the default filter selects every job.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct FailedJobFilter {
+    /// Only select the jobs whose failure reason contains this text, ignoring case.
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// Only select the jobs whose last recorded attempt failed within this number of seconds.
+    #[serde(default)]
+    pub failed_within_secs: Option<u64>,
+}
+
+impl FailedJobFilter {
+    /// Tells whether a failed job is selected by the filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `job` - The failed job.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the job matches every set criterion.
+    pub fn matches(&self, job: &JobData) -> bool {
+        if let Some(reason) = &self.reason {
+            let failed_reason = job.failed_reason.as_deref().unwrap_or_default().to_lowercase();
+            if !failed_reason.contains(&reason.to_lowercase()) {
+                return false;
+            }
+        }
+        if let Some(within_secs) = self.failed_within_secs {
+            let Some(failed_at) = job.failed_at() else { return false };
+            let age_secs = chrono::Utc::now().timestamp() - failed_at.timestamp();
+            if age_secs > within_secs as i64 {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// The state of a job in a queue.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    /// The job is waiting to be processed.
+    Waiting,
+    /// The job has failed and was moved to the failed queue.
+    Failed,
+}
+
+impl JobState {
+    /// Returns the name of the state.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobState::Waiting => "waiting",
+            JobState::Failed => "failed",
+        }
+    }
+}
+
+impl FromStr for JobState {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "waiting" => Ok(JobState::Waiting),
+            "failed" => Ok(JobState::Failed),
+            _ => Err("expected waiting or failed".to_string()),
+        }
+    }
+}
+
+/// The algorithm a job message is compressed with.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    /// Zstandard (`zstd` feature).
+    #[default]
+    Zstd,
+    /// Gzip (`gzip` feature).
+    Gzip,
+}
+
+impl Compression {
+    /// Returns the name of the algorithm.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Compression::Zstd => "zstd",
+            Compression::Gzip => "gzip",
+        }
+    }
+
+    /// Tells whether the crate was built with the feature of the algorithm.
+    pub fn is_supported(&self) -> bool {
+        match self {
+            Compression::Zstd => cfg!(feature = "zstd"),
+            Compression::Gzip => cfg!(feature = "gzip"),
+        }
+    }
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "zstd" => Ok(Compression::Zstd),
+            "gzip" => Ok(Compression::Gzip),
+            _ => Err("expected zstd or gzip".to_string()),
+        }
+    }
 }
@@ -1,17 +1,73 @@
 use async_trait::async_trait;
-use job_model::JobData;
+use job_model::{FailedJobFilter, JobData, JobState};
+use log_model::{LogEntry, LogRange};
+use metrics_model::{MetricKind, MetricPoint, MetricsRange};
 use redis::RedisResult;
 
+/// Reports a diagnostic message through `tracing` when the feature is enabled,
+/// or on stderr otherwise.
+macro_rules! diagnostic {
+    ($level:ident, $($arg:tt)*) => {{
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($arg)*);
+        #[cfg(not(feature = "tracing"))]
+        eprintln!($($arg)*);
+    }};
+}
+
 /// Module for managing Redis configuration.
 pub mod config_service;
+/// Module for managing single-node and cluster Redis connections.
+pub mod connection_service;
+/// Module for building the Redis keys of a queue.
+pub mod queue_keys;
 /// Module for managing queues and jobs.
 pub mod queue_service;
+/// Module for managing queues stored in Redis Streams with consumer groups.
+pub mod stream_queue_service;
 /// Module for defining the job data model.
 pub mod job_model;
+/// Module for defining the log entry model.
+pub mod log_model;
 /// Module for logging job events.
 pub mod log_service;
 /// Module for queue trigger service
 pub mod queue_trigger_service;
+/// Module for running job handlers in sandboxed child processes.
+pub mod sandbox_service;
+/// Module for the `bullmq` command line interface.
+pub mod cli_service;
+/// Module for defining the queue metrics model.
+pub mod metrics_model;
+/// Module for storing queue metrics.
+pub mod metrics_service;
+/// Module for exporting queue and worker metrics to Prometheus.
+#[cfg(feature = "prometheus")]
+pub mod prometheus_service;
+/// Module for compressing the messages of large jobs.
+#[cfg(any(feature = "zstd", feature = "gzip"))]
+pub mod compression_service;
+/// Module for the in-memory queue service used in tests and local development.
+#[cfg(feature = "test-util")]
+pub mod in_memory_queue_service;
+/// Module for the queue service storing queues in an embedded SQLite database.
+#[cfg(feature = "sqlite")]
+pub mod sqlite_queue_service;
+/// Module for forwarding jobs to an HTTP endpoint.
+#[cfg(feature = "webhook")]
+pub mod webhook_service;
+/// Module for the HTTP REST API managing queues and jobs.
+#[cfg(feature = "api")]
+pub mod api_service;
+/// Module for the web dashboard served alongside the HTTP REST API.
+#[cfg(feature = "dashboard")]
+pub mod dashboard_service;
+/// Module for the `bullmq top` terminal UI.
+#[cfg(feature = "tui")]
+pub mod top_service;
+/// Module for tracing instrumentation and trace-context propagation.
+#[cfg(feature = "tracing")]
+pub mod tracing_service;
 
 
 #[async_trait]
@@ -28,6 +84,21 @@ pub trait QueueServiceTrait: Send + Sync {
     /// A `RedisResult` indicating the success or failure of the operation.
     async fn add_job(&mut self, queue_name: &str, job: JobData) -> RedisResult<()>;
 
+    /// Adds a job to the specified queue, available after the given delay rather than its own.
+    ///
+    /// The stored job keeps its own `delay`.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to add the job to.
+    /// * `job` - The job data to add to the queue.
+    /// * `delay_secs` - The delay in seconds before the job can be fetched.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn add_job_after(&mut self, queue_name: &str, job: JobData, delay_secs: i64) -> RedisResult<()>;
+
     /// Retrieves the next job from the specified queue.
     ///
     /// # Arguments
@@ -39,6 +110,21 @@ pub trait QueueServiceTrait: Send + Sync {
     /// A `RedisResult` containing an optional job JSON string.
     async fn get_next_job(&mut self, queue_name: &str) -> RedisResult<Option<Vec<String>>>;
 
+    /// Waits for the next job from the specified queue.
+    ///
+    /// Unlike `get_next_job`, this blocks until a job arrives or the timeout
+    /// elapses, so callers don't have to poll an empty queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to retrieve the job from.
+    /// * `timeout_secs` - The maximum time in seconds to block, `0` blocks forever.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the job JSON string and its score, or `None` on timeout.
+    async fn wait_for_next_job(&mut self, queue_name: &str, timeout_secs: f64) -> RedisResult<Option<Vec<String>>>;
+
     /// Counts the number of jobs in the specified queue.
     ///
     /// # Arguments
@@ -50,6 +136,18 @@ pub trait QueueServiceTrait: Send + Sync {
     /// A `RedisResult` containing the number of jobs in the queue.
     async fn count_jobs(&mut self, queue_name: &str) -> RedisResult<u64>;
 
+    /// Counts the number of jobs of the specified queue in the given state.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to count the jobs in.
+    /// * `state` - The state of the jobs to count.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the number of jobs in the given state.
+    async fn count_jobs_by_state(&mut self, queue_name: &str, state: JobState) -> RedisResult<u64>;
+
     /// Updates the progress of a job.
     ///
     /// # Arguments
@@ -72,8 +170,8 @@ pub trait QueueServiceTrait: Send + Sync {
     ///
     /// # Returns
     ///
-    /// A `RedisResult` containing the progress value of the job.
-    async fn get_job_progress(&mut self, queue_name: &str, job_id: &str) -> RedisResult<u32>;
+    /// A `RedisResult` containing the progress value of the job, or `None` if none was set.
+    async fn get_job_progress(&mut self, queue_name: &str, job_id: &str) -> RedisResult<Option<u32>>;
 
     /// Moves a job to the failed queue.
     ///
@@ -99,4 +197,371 @@ pub trait QueueServiceTrait: Send + Sync {
     ///
     /// A `RedisResult` indicating the success or failure of the operation.
     async fn log_job_status(&mut self, queue_name: &str, job: &JobData, status: &str) -> RedisResult<()>;
+
+    /// Marks a job as completed and records its throughput and latency metrics.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job` - A reference to the job data.
+    /// * `processing_ms` - The time spent processing the job, in milliseconds.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn mark_completed(&mut self, queue_name: &str, job: &JobData, processing_ms: u64) -> RedisResult<()>;
+
+    /// Retrieves the per-minute data points of a queue metric.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to read the metrics of.
+    /// * `kind` - The metric to read.
+    /// * `range` - The time range to read.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing one data point per minute, oldest first.
+    async fn get_metrics(&mut self, queue_name: &str, kind: MetricKind, range: MetricsRange) -> RedisResult<Vec<MetricPoint>>;
+
+    /// Reads entries from the specified queue's log, most recent first.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to read the log of.
+    /// * `job_id` - Only return the entries of this job, if set.
+    /// * `range` - The page of matching entries to return.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the matching entries.
+    async fn read_logs(&mut self, queue_name: &str, job_id: Option<&str>, range: LogRange) -> RedisResult<Vec<LogEntry>>;
+
+    /// Appends a line to the log of a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    /// * `line` - The line to append.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the number of lines in the job log.
+    async fn job_log(&mut self, queue_name: &str, job_id: &str, line: &str) -> RedisResult<u64>;
+
+    /// Reads lines from the log of a job, oldest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    /// * `start` - The index of the first line, negative values count from the end.
+    /// * `end` - The index of the last line, inclusive, negative values count from the end.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the requested lines.
+    async fn get_job_logs(&mut self, queue_name: &str, job_id: &str, start: isize, end: isize) -> RedisResult<Vec<String>>;
+
+    /// Removes a job from the queue and its failed queue, together with its progress and log lines.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the job was found in a queue.
+    async fn remove_job(&mut self, queue_name: &str, job_id: &str) -> RedisResult<bool>;
+
+    /// Adds several jobs to the specified queue.
+    ///
+    /// The default implementation adds the jobs one by one, backends override
+    /// it to insert them in a single round trip.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to add the jobs to.
+    /// * `jobs` - The jobs to add, in order.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn add_jobs(&mut self, queue_name: &str, jobs: Vec<JobData>) -> RedisResult<()> {
+        for job in jobs {
+            self.add_job(queue_name, job).await?;
+        }
+        Ok(())
+    }
+
+    /// Lists the jobs of the specified queue in the given state.
+    ///
+    /// Waiting jobs are listed in the order they will be fetched, failed jobs
+    /// most recently failed first.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to list the jobs of.
+    /// * `state` - The state of the jobs to list.
+    /// * `offset` - The number of jobs to skip.
+    /// * `limit` - The maximum number of jobs to return.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the jobs.
+    async fn list_jobs(&mut self, queue_name: &str, state: JobState, offset: usize, limit: usize) -> RedisResult<Vec<JobData>>;
+
+    /// Finds a waiting or failed job by its ID.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the state and data of the job, or `None` if it is not found.
+    async fn get_job(&mut self, queue_name: &str, job_id: &str) -> RedisResult<Option<(JobState, JobData)>> {
+        for state in [JobState::Waiting, JobState::Failed] {
+            let jobs = self.list_jobs(queue_name, state, 0, usize::MAX).await?;
+            if let Some(job) = jobs.into_iter().find(|job| job.id == job_id) {
+                return Ok(Some((state, job)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Moves a failed job back to the waiting jobs, ready to be fetched immediately.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the failed job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the job was found in the failed queue.
+    async fn retry_job(&mut self, queue_name: &str, job_id: &str) -> RedisResult<bool>;
+
+    /// Removes a failed job from the failed jobs of a queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job failed in.
+    /// * `job_id` - The ID of the failed job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the job was found in the failed queue.
+    async fn remove_failed_job(&mut self, queue_name: &str, job_id: &str) -> RedisResult<bool>;
+
+    /// Moves a failed job to the waiting jobs of another queue, ready to be fetched immediately.
+    ///
+    /// The job keeps its attempts and failure history. It is added through the queue service of
+    /// the target queue, which may use another backend, before it is removed from the failed
+    /// queue: an interrupted move leaves a copy rather than losing the job.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job failed in.
+    /// * `job_id` - The ID of the failed job.
+    /// * `target` - The queue service of the target queue.
+    /// * `target_queue` - The name of the queue to move the job to.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the job was found in the failed queue.
+    async fn move_failed_job(
+        &mut self,
+        queue_name: &str,
+        job_id: &str,
+        target: &mut dyn QueueServiceTrait,
+        target_queue: &str,
+    ) -> RedisResult<bool> {
+        let jobs: Vec<JobData> = self
+            .list_jobs(queue_name, JobState::Failed, 0, usize::MAX)
+            .await?
+            .into_iter()
+            .filter(|job| job.id == job_id)
+            .map(|job| JobData { delay: None, ..job })
+            .collect();
+        if jobs.is_empty() {
+            return Ok(false);
+        }
+        target.add_jobs(target_queue, jobs).await?;
+        self.remove_failed_job(queue_name, job_id).await
+    }
+
+    /// Lists the failed jobs of a queue selected by a filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to list the failed jobs of.
+    /// * `filter` - The filter selecting the jobs.
+    /// * `offset` - The number of selected jobs to skip.
+    /// * `limit` - The maximum number of jobs to return.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the selected jobs, most recently failed first.
+    async fn list_failed_jobs(
+        &mut self,
+        queue_name: &str,
+        filter: &FailedJobFilter,
+        offset: usize,
+        limit: usize,
+    ) -> RedisResult<Vec<JobData>> {
+        let jobs = self.list_jobs(queue_name, JobState::Failed, 0, usize::MAX).await?;
+        Ok(jobs.into_iter().filter(|job| filter.matches(job)).skip(offset).take(limit).collect())
+    }
+
+    /// Moves the failed jobs selected by a filter back to the waiting jobs.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the jobs failed in.
+    /// * `filter` - The filter selecting the jobs.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the IDs of the retried jobs.
+    async fn retry_failed_jobs(&mut self, queue_name: &str, filter: &FailedJobFilter) -> RedisResult<Vec<String>> {
+        let mut retried = Vec::new();
+        for job in self.list_failed_jobs(queue_name, filter, 0, usize::MAX).await? {
+            if !retried.contains(&job.id) && self.retry_job(queue_name, &job.id).await? {
+                retried.push(job.id);
+            }
+        }
+        Ok(retried)
+    }
+
+    /// Moves the failed jobs selected by a filter to the waiting jobs of another queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the jobs failed in.
+    /// * `filter` - The filter selecting the jobs.
+    /// * `target` - The queue service of the target queue.
+    /// * `target_queue` - The name of the queue to move the jobs to.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the IDs of the moved jobs.
+    async fn move_failed_jobs(
+        &mut self,
+        queue_name: &str,
+        filter: &FailedJobFilter,
+        target: &mut dyn QueueServiceTrait,
+        target_queue: &str,
+    ) -> RedisResult<Vec<String>> {
+        let mut moved = Vec::new();
+        for job in self.list_failed_jobs(queue_name, filter, 0, usize::MAX).await? {
+            if !moved.contains(&job.id) && self.move_failed_job(queue_name, &job.id, target, target_queue).await? {
+                moved.push(job.id);
+            }
+        }
+        Ok(moved)
+    }
+
+    /// Pauses the specified queue, workers stop fetching its jobs until it is resumed.
+    ///
+    /// Jobs can still be added to a paused queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to pause.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn pause_queue(&mut self, queue_name: &str) -> RedisResult<()>;
+
+    /// Resumes a paused queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to resume.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn resume_queue(&mut self, queue_name: &str) -> RedisResult<()>;
+
+    /// Tells whether the specified queue is paused.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the queue is paused.
+    async fn is_paused(&mut self, queue_name: &str) -> RedisResult<bool>;
+
+    /// Removes the jobs in the given state older than a grace period, together with their progress and log lines.
+    ///
+    /// Waiting jobs are aged from the moment they became available, failed jobs
+    /// from the moment they failed.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to clean.
+    /// * `state` - The state of the jobs to remove.
+    /// * `grace_secs` - The minimum age, in seconds, of the removed jobs.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the number of removed jobs.
+    async fn clean_jobs(&mut self, queue_name: &str, state: JobState, grace_secs: u64) -> RedisResult<u64>;
+
+    /// Lists the queues holding any data: jobs, progress, logs, metrics or a pause flag.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the names of the queues, sorted.
+    async fn list_queues(&mut self) -> RedisResult<Vec<String>>;
+
+    /// Requests the cancellation of a job.
+    ///
+    /// The worker processing the job picks the request up within
+    /// `CANCEL_POLL_INTERVAL_MS` and signals the cancellation token of its
+    /// handler; a job still waiting is cancelled as soon as a worker fetches
+    /// it. Requests not picked up within `CANCELLATION_TTL_SECS` are dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn cancel_job(&mut self, queue_name: &str, job_id: &str) -> RedisResult<()>;
+
+    /// Consumes the cancellation request of a job, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the job's cancellation was requested.
+    async fn take_job_cancellation(&mut self, queue_name: &str, job_id: &str) -> RedisResult<bool>;
+
+    /// Returns a new handle on the same queues, which can be used concurrently with this one.
+    ///
+    /// # Returns
+    ///
+    /// The boxed copy of the queue service.
+    fn clone_box(&self) -> Box<dyn QueueServiceTrait>;
+}
+
+impl Clone for Box<dyn QueueServiceTrait> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
 }
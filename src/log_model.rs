@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use chrono::Utc;
+use crate::job_model::JobData;
+
+/// The severity of a log entry.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    #[default]
+    Info,
+    Warn,
+    Error,
+}
+
+/// A structured entry of a queue log.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LogEntry {
+    /// The time the entry was written, in RFC 3339 format.
+    pub timestamp: String,
+    /// The severity of the entry.
+    #[serde(default)]
+    pub level: LogLevel,
+    /// The ID of the job the entry relates to, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub job_id: Option<String>,
+    /// The event being logged, e.g. `completed` or `failed`.
+    pub event: String,
+    /// Additional structured data attached to the entry.
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub fields: Map<String, Value>,
+}
+
+impl LogEntry {
+    /// Creates a new `LogEntry` timestamped now.
+    ///
+    /// # Arguments
+    ///
+    /// * `level` - The severity of the entry.
+    /// * `event` - The event being logged.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of `LogEntry`.
+    pub fn new(level: LogLevel, event: &str) -> Self {
+        Self {
+            timestamp: Utc::now().to_rfc3339(),
+            level,
+            job_id: None,
+            event: event.to_string(),
+            fields: Map::new(),
+        }
+    }
+
+    /// Creates the entry logging a status change of a job, with its message as it is stored.
+    ///
+    /// The algorithm of a compressed message is recorded in a `compression` field.
+    ///
+    /// # Arguments
+    ///
+    /// * `job` - The job whose status changed.
+    /// * `status` - The new status of the job.
+    ///
+    /// # Returns
+    ///
+    /// A new `info` instance of `LogEntry`.
+    pub fn job_status(job: &JobData, status: &str) -> Self {
+        let entry = Self::new(LogLevel::Info, status)
+            .with_job_id(&job.id)
+            .with_field("message", job.message.as_str());
+        match job.compression {
+            Some(compression) => entry.with_field("compression", compression.as_str()),
+            None => entry,
+        }
+    }
+
+    /// Sets the ID of the job the entry relates to.
+    pub fn with_job_id(mut self, job_id: &str) -> Self {
+        self.job_id = Some(job_id.to_string());
+        self
+    }
+
+    /// Attaches a structured field to the entry.
+    pub fn with_field(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.fields.insert(key.to_string(), value.into());
+        self
+    }
+
+    /// Parses an entry read from a log list.
+    ///
+    /// Plain `"<timestamp> - <message>"` entries written by older versions are
+    /// converted to `info` entries whose event is the message.
+    ///
+    /// # Arguments
+    ///
+    /// * `raw` - The raw entry as stored in Redis.
+    ///
+    /// # Returns
+    ///
+    /// The parsed `LogEntry`.
+    pub fn parse(raw: &str) -> Self {
+        if let Ok(entry) = serde_json::from_str(raw) {
+            return entry;
+        }
+        let (timestamp, event) = raw.split_once(" - ").unwrap_or(("", raw));
+        Self {
+            timestamp: timestamp.to_string(),
+            level: LogLevel::Info,
+            job_id: None,
+            event: event.to_string(),
+            fields: Map::new(),
+        }
+    }
+}
+
+/// A page of log entries, counted from the most recent one.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogRange {
+    /// The number of matching entries to skip.
+    pub offset: usize,
+    /// The maximum number of entries to return.
+    pub limit: usize,
+}
+
+impl Default for LogRange {
+    fn default() -> Self {
+        Self { offset: 0, limit: 100 }
+    }
+}
+
+/// Returns the items of a list between two inclusive indexes, with the semantics of `LRANGE`.
+#[cfg(any(feature = "test-util", feature = "sqlite"))]
+pub(crate) fn list_range<T: Clone>(list: &[T], start: isize, end: isize) -> Vec<T> {
+    let len = list.len() as isize;
+    let start = if start < 0 { (len + start).max(0) } else { start };
+    let end = if end < 0 { len + end } else { end.min(len - 1) };
+    if start > end {
+        return Vec::new();
+    }
+    list[start as usize..=end as usize].to_vec()
+}
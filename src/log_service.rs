@@ -1,11 +1,29 @@
-use redis::{Client, Commands, RedisResult};
-use std::sync::Arc;
-use tokio::sync::Mutex;
-use chrono::Utc;
+use redis::{AsyncCommands, RedisResult};
+use crate::connection_service::RedisConnection;
+use crate::log_model::{LogEntry, LogRange};
+use crate::queue_keys::{QueueKeys, DEFAULT_KEY_PREFIX};
+use crate::queue_service::range_stop;
+
+/// Default maximum number of entries kept in a queue log.
+pub const DEFAULT_MAX_LOG_ENTRIES: usize = 10_000;
+
+/// Default maximum number of lines kept in a job log.
+pub const DEFAULT_MAX_JOB_LOG_LINES: usize = 1_000;
+
+/// Number of entries read per request when filtering a log.
+const LOG_SCAN_PAGE_SIZE: isize = 500;
 
 /// Service responsible for logging job events to Redis.
+///
+/// Entries are stored as JSON, most recent first, in the queue log list
+/// which is trimmed to a maximum number of entries on every write. Lines
+/// written by job handlers are kept apart, oldest first, in one list per job.
+#[derive(Clone)]
 pub struct LogService {
-    client: Arc<Mutex<Client>>,
+    con: RedisConnection,
+    max_entries: usize,
+    max_job_lines: usize,
+    key_prefix: String,
 }
 
 impl LogService {
@@ -13,30 +31,180 @@ impl LogService {
     ///
     /// # Arguments
     ///
-    /// * `client` - An `Arc` wrapped `Mutex` containing the Redis client.
+    /// * `con` - The Redis connection.
     ///
     /// # Returns
     ///
     /// A new instance of `LogService`.
-    pub fn new(client: Arc<Mutex<Client>>) -> Self {
-        Self { client }
+    pub fn new(con: impl Into<RedisConnection>) -> Self {
+        Self {
+            con: con.into(),
+            max_entries: DEFAULT_MAX_LOG_ENTRIES,
+            max_job_lines: DEFAULT_MAX_JOB_LOG_LINES,
+            key_prefix: DEFAULT_KEY_PREFIX.to_string(),
+        }
+    }
+
+    /// Sets the maximum number of entries kept in each queue log.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_entries` - The maximum number of log entries per queue, at least 1.
+    ///
+    /// # Returns
+    ///
+    /// The `LogService` using the given log cap.
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries.max(1);
+        self
+    }
+
+    /// Sets the maximum number of lines kept in each job log, older lines are dropped first.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_job_lines` - The maximum number of log lines per job, at least 1.
+    ///
+    /// # Returns
+    ///
+    /// The `LogService` using the given job log cap.
+    pub fn with_max_job_lines(mut self, max_job_lines: usize) -> Self {
+        self.max_job_lines = max_job_lines.max(1);
+        self
     }
 
-    /// Logs a message to the specified queue's log.
+    /// Sets the prefix of the log keys.
     ///
     /// # Arguments
     ///
-    /// * `queue_name` - The name of the queue to log the message to.
-    /// * `message` - The message to log.
+    /// * `key_prefix` - The key prefix, `bull` by default.
+    ///
+    /// # Returns
+    ///
+    /// The `LogService` using the given key prefix.
+    pub fn with_key_prefix(mut self, key_prefix: &str) -> Self {
+        self.key_prefix = key_prefix.to_string();
+        self
+    }
+
+    fn keys(&self, queue_name: &str) -> QueueKeys {
+        QueueKeys::with_prefix(&self.key_prefix, queue_name)
+    }
+
+    /// Logs an entry to the specified queue's log.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to log the entry to.
+    /// * `entry` - The entry to log.
     ///
     /// # Returns
     ///
     /// A `RedisResult` indicating the success or failure of the operation.
-    pub async fn log(&self, queue_name: &str, message: &str) -> RedisResult<()> {
-        let log_queue_name = format!("{}:log", queue_name);
-        let log_entry = format!("{} - {}", Utc::now().to_rfc3339(), message);
-        let mut conn = self.client.lock().await.get_connection()?;
-        let _: () = conn.lpush(log_queue_name, log_entry)?;
+    pub async fn log(&mut self, queue_name: &str, entry: &LogEntry) -> RedisResult<()> {
+        let log_key = self.keys(queue_name).log();
+        let entry_json = serde_json::to_string(entry).map_err(|e| {
+            redis::RedisError::from((redis::ErrorKind::TypeError, "Failed to serialize log entry", e.to_string()))
+        })?;
+        let _: () = redis::pipe()
+            .atomic()
+            .lpush(&log_key, entry_json).ignore()
+            .ltrim(&log_key, 0, self.max_entries as isize - 1).ignore()
+            .query_async(&mut self.con)
+            .await?;
         Ok(())
     }
+
+    /// Reads entries from the specified queue's log, most recent first.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to read the log of.
+    /// * `job_id` - Only return the entries of this job, if set.
+    /// * `range` - The page of matching entries to return.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the matching entries.
+    pub async fn read_logs(&mut self, queue_name: &str, job_id: Option<&str>, range: LogRange) -> RedisResult<Vec<LogEntry>> {
+        let log_key = self.keys(queue_name).log();
+        if range.limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        let job_id = match job_id {
+            Some(job_id) => job_id,
+            None => {
+                if range.offset > isize::MAX as usize {
+                    return Ok(Vec::new());
+                }
+                let start = range.offset as isize;
+                let stop = range_stop(range.offset, range.limit);
+                let raw: Vec<String> = self.con.lrange(&log_key, start, stop).await?;
+                return Ok(raw.iter().map(|entry| LogEntry::parse(entry)).collect());
+            }
+        };
+
+        let mut entries = Vec::new();
+        let mut skipped = 0;
+        let mut start = 0;
+        loop {
+            let raw: Vec<String> = self.con.lrange(&log_key, start, start + LOG_SCAN_PAGE_SIZE - 1).await?;
+            for entry in raw.iter().map(|entry| LogEntry::parse(entry)) {
+                if entry.job_id.as_deref() != Some(job_id) {
+                    continue;
+                }
+                if skipped < range.offset {
+                    skipped += 1;
+                    continue;
+                }
+                entries.push(entry);
+                if entries.len() == range.limit {
+                    return Ok(entries);
+                }
+            }
+            if (raw.len() as isize) < LOG_SCAN_PAGE_SIZE {
+                return Ok(entries);
+            }
+            start += LOG_SCAN_PAGE_SIZE;
+        }
+    }
+
+    /// Appends a line to the log of a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    /// * `line` - The line to append.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the number of lines in the job log.
+    pub async fn job_log(&mut self, queue_name: &str, job_id: &str, line: &str) -> RedisResult<u64> {
+        let key = self.keys(queue_name).job_logs(job_id);
+        let (count,): (u64,) = redis::pipe()
+            .atomic()
+            .rpush(&key, line)
+            .ltrim(&key, -(self.max_job_lines as isize), -1).ignore()
+            .query_async(&mut self.con)
+            .await?;
+        Ok(count.min(self.max_job_lines as u64))
+    }
+
+    /// Reads lines from the log of a job, oldest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    /// * `start` - The index of the first line, negative values count from the end.
+    /// * `end` - The index of the last line, inclusive, negative values count from the end.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the requested lines.
+    pub async fn get_job_logs(&mut self, queue_name: &str, job_id: &str, start: isize, end: isize) -> RedisResult<Vec<String>> {
+        self.con.lrange(self.keys(queue_name).job_logs(job_id), start, end).await
+    }
 }
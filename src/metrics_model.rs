@@ -0,0 +1,143 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Upper bounds, in milliseconds, of the duration histogram buckets.
+///
+/// Durations above the last bound fall into an overflow bucket reported with `u64::MAX`.
+pub const HISTOGRAM_BUCKETS_MS: [u64; 11] = [10, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 30_000, 60_000];
+
+/// The kind of metric collected for a queue.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricKind {
+    /// Number of jobs completed per minute.
+    Completed,
+    /// Number of jobs failed per minute.
+    Failed,
+    /// Histogram of the time jobs waited in the queue before being processed.
+    WaitTime,
+    /// Histogram of the time spent processing jobs.
+    ProcessingTime,
+}
+
+impl MetricKind {
+    /// Returns the name used for this metric in Redis keys.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MetricKind::Completed => "completed",
+            MetricKind::Failed => "failed",
+            MetricKind::WaitTime => "wait_time",
+            MetricKind::ProcessingTime => "processing_time",
+        }
+    }
+
+    /// Returns `true` if this metric is stored as a duration histogram.
+    pub fn is_histogram(&self) -> bool {
+        matches!(self, MetricKind::WaitTime | MetricKind::ProcessingTime)
+    }
+}
+
+/// A time range, in unix seconds, used to query metrics.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MetricsRange {
+    /// The start of the range, inclusive.
+    pub start: i64,
+    /// The end of the range, inclusive.
+    pub end: i64,
+}
+
+impl MetricsRange {
+    /// Creates a range covering the last `minutes` minutes up to now.
+    pub fn last_minutes(minutes: i64) -> Self {
+        let end = chrono::Utc::now().timestamp();
+        Self { start: end - minutes * 60, end }
+    }
+
+    /// Restricts the range to the retention period, from `retention_secs` ago up to now.
+    ///
+    /// # Arguments
+    ///
+    /// * `retention_secs` - The number of seconds data points are kept for.
+    ///
+    /// # Returns
+    ///
+    /// The part of the range holding data points, empty if it is entirely outside the retention period.
+    pub fn clamp_to_retention(&self, retention_secs: i64) -> Self {
+        let now = chrono::Utc::now().timestamp();
+        Self { start: self.start.max(now - retention_secs), end: self.end.min(now) }
+    }
+
+    /// Returns the start of every minute bucket covered by the range.
+    pub fn minutes(&self) -> Vec<i64> {
+        if self.end < self.start {
+            return Vec::new();
+        }
+        let first = minute_bucket(self.start);
+        let last = minute_bucket(self.end);
+        (0..=(last - first) / 60).map(|i| first + i * 60).collect()
+    }
+}
+
+/// A single histogram bucket.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct HistogramBucket {
+    /// The upper bound of the bucket in milliseconds, `u64::MAX` for the overflow bucket.
+    pub le_ms: u64,
+    /// The number of observations falling into the bucket.
+    pub count: u64,
+}
+
+/// The value of a metric for one minute.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MetricPoint {
+    /// The start of the minute, in unix seconds.
+    pub timestamp: i64,
+    /// The number of events (or observations for histograms) during the minute.
+    pub count: u64,
+    /// The histogram buckets, empty for counter metrics.
+    pub buckets: Vec<HistogramBucket>,
+}
+
+impl MetricPoint {
+    /// Builds a data point from the stored fields of a minute.
+    ///
+    /// # Arguments
+    ///
+    /// * `timestamp` - The start of the minute, in unix seconds.
+    /// * `kind` - The metric the fields belong to.
+    /// * `fields` - The `count` field and, for histograms, the count of each bucket keyed by its upper bound.
+    ///
+    /// # Returns
+    ///
+    /// The data point, with every bucket listed for histogram metrics.
+    pub fn from_fields(timestamp: i64, kind: MetricKind, fields: &HashMap<String, u64>) -> Self {
+        let buckets = if kind.is_histogram() {
+            HISTOGRAM_BUCKETS_MS
+                .iter()
+                .copied()
+                .chain(std::iter::once(u64::MAX))
+                .map(|le_ms| HistogramBucket {
+                    le_ms,
+                    count: fields.get(&le_ms.to_string()).copied().unwrap_or(0),
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        Self { timestamp, count: fields.get("count").copied().unwrap_or(0), buckets }
+    }
+}
+
+/// Returns the start of the minute containing the given unix timestamp.
+pub fn minute_bucket(timestamp: i64) -> i64 {
+    timestamp - timestamp.rem_euclid(60)
+}
+
+/// Returns the upper bound of the histogram bucket a duration falls into.
+pub fn histogram_bucket(duration_ms: u64) -> u64 {
+    HISTOGRAM_BUCKETS_MS
+        .iter()
+        .copied()
+        .find(|le| duration_ms <= *le)
+        .unwrap_or(u64::MAX)
+}
@@ -0,0 +1,150 @@
+use redis::RedisResult;
+use crate::connection_service::RedisConnection;
+use crate::queue_keys::{QueueKeys, DEFAULT_KEY_PREFIX};
+use std::collections::HashMap;
+use chrono::Utc;
+use crate::metrics_model::{histogram_bucket, minute_bucket, MetricKind, MetricPoint, MetricsRange};
+
+/// Default number of minutes metrics data points are kept for.
+pub const DEFAULT_METRICS_RETENTION_MINUTES: u64 = 24 * 60;
+
+/// Service responsible for storing per-minute queue metrics in Redis.
+///
+/// Each metric is stored in one key per minute which expires after the
+/// retention period, so the amount of stored data points stays bounded.
+#[derive(Clone)]
+pub struct MetricsService {
+    con: RedisConnection,
+    retention_minutes: u64,
+    key_prefix: String,
+}
+
+impl MetricsService {
+    /// Creates a new `MetricsService`.
+    ///
+    /// # Arguments
+    ///
+    /// * `con` - The Redis connection.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of `MetricsService`.
+    pub fn new(con: impl Into<RedisConnection>) -> Self {
+        Self {
+            con: con.into(),
+            retention_minutes: DEFAULT_METRICS_RETENTION_MINUTES,
+            key_prefix: DEFAULT_KEY_PREFIX.to_string(),
+        }
+    }
+
+    /// Sets the number of minutes data points are kept for.
+    pub fn with_retention_minutes(mut self, retention_minutes: u64) -> Self {
+        self.retention_minutes = retention_minutes.max(1);
+        self
+    }
+
+    /// Sets the prefix of the metrics keys.
+    pub fn with_key_prefix(mut self, key_prefix: &str) -> Self {
+        self.key_prefix = key_prefix.to_string();
+        self
+    }
+
+    fn keys(&self, queue_name: &str) -> QueueKeys {
+        QueueKeys::with_prefix(&self.key_prefix, queue_name)
+    }
+
+    fn retention_secs(&self) -> i64 {
+        (self.retention_minutes * 60) as i64
+    }
+
+    /// Increments a counter metric for the current minute.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the event belongs to.
+    /// * `kind` - The counter metric to increment.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    pub async fn record_count(&mut self, queue_name: &str, kind: MetricKind) -> RedisResult<()> {
+        let key = self.keys(queue_name).metric(kind, minute_bucket(Utc::now().timestamp()));
+        let _: () = redis::pipe()
+            .atomic()
+            .incr(&key, 1).ignore()
+            .expire(&key, self.retention_secs()).ignore()
+            .query_async(&mut self.con)
+            .await?;
+        Ok(())
+    }
+
+    /// Records a duration in a histogram metric for the current minute.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the observation belongs to.
+    /// * `kind` - The histogram metric to record into.
+    /// * `duration_ms` - The observed duration in milliseconds.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    pub async fn record_duration(&mut self, queue_name: &str, kind: MetricKind, duration_ms: u64) -> RedisResult<()> {
+        let key = self.keys(queue_name).metric(kind, minute_bucket(Utc::now().timestamp()));
+        let _: () = redis::pipe()
+            .atomic()
+            .hincr(&key, histogram_bucket(duration_ms).to_string(), 1).ignore()
+            .hincr(&key, "count", 1).ignore()
+            .expire(&key, self.retention_secs()).ignore()
+            .query_async(&mut self.con)
+            .await?;
+        Ok(())
+    }
+
+    /// Retrieves the data points of a metric over a time range.
+    ///
+    /// Minutes without any recorded event are returned with a zero count.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to read the metrics of.
+    /// * `kind` - The metric to read.
+    /// * `range` - The time range to read, clamped to the retention period and to the current minute.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing one data point per minute, oldest first.
+    pub async fn get_metrics(&mut self, queue_name: &str, kind: MetricKind, range: MetricsRange) -> RedisResult<Vec<MetricPoint>> {
+        let range = range.clamp_to_retention(self.retention_secs());
+        let minutes = range.minutes();
+        if minutes.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let keys = self.keys(queue_name);
+        if kind.is_histogram() {
+            let mut pipe = redis::pipe();
+            for minute in &minutes {
+                pipe.hgetall(keys.metric(kind, *minute));
+            }
+            let values: Vec<HashMap<String, u64>> = pipe.query_async(&mut self.con).await?;
+            Ok(minutes
+                .into_iter()
+                .zip(values)
+                .map(|(timestamp, fields)| MetricPoint::from_fields(timestamp, kind, &fields))
+                .collect())
+        } else {
+            let keys: Vec<String> = minutes.iter().map(|minute| keys.metric(kind, *minute)).collect();
+            let values: Vec<Option<u64>> = redis::cmd("MGET").arg(&keys).query_async(&mut self.con).await?;
+            Ok(minutes
+                .into_iter()
+                .zip(values)
+                .map(|(timestamp, count)| MetricPoint {
+                    timestamp,
+                    count: count.unwrap_or(0),
+                    buckets: Vec::new(),
+                })
+                .collect())
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use axum::extract::State;
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+use hyper::server::conn::http1;
+use hyper_util::rt::{TokioIo, TokioTimer};
+use hyper_util::service::TowerToHyperService;
+use prometheus::core::Collector;
+use prometheus::{Encoder, GaugeVec, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::task::{self, JoinHandle};
+
+/// How long a metrics connection may take to send its request headers.
+const HEADER_READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Service exposing queue and worker metrics in the Prometheus text format.
+pub struct PrometheusService {
+    registry: Registry,
+    queue_jobs: IntGaugeVec,
+    job_duration: HistogramVec,
+    jobs_completed: IntCounterVec,
+    jobs_failed: IntCounterVec,
+    job_retries: IntCounterVec,
+    worker_active: IntGaugeVec,
+    worker_concurrency: IntGaugeVec,
+}
+
+impl PrometheusService {
+    /// Creates a new `PrometheusService` with all metrics registered.
+    ///
+    /// # Returns
+    ///
+    /// A `prometheus::Result` containing the new instance of `PrometheusService`.
+    pub fn new() -> prometheus::Result<Self> {
+        let registry = Registry::new();
+        let queue_jobs = IntGaugeVec::new(
+            Opts::new("bullmq_queue_jobs", "Number of jobs in the queue per state."),
+            &["queue", "state"],
+        )?;
+        let job_duration = HistogramVec::new(
+            HistogramOpts::new("bullmq_job_duration_seconds", "Time spent processing jobs.")
+                .buckets(vec![0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0]),
+            &["queue"],
+        )?;
+        let jobs_completed = IntCounterVec::new(
+            Opts::new("bullmq_jobs_completed_total", "Number of jobs completed."),
+            &["queue"],
+        )?;
+        let jobs_failed = IntCounterVec::new(
+            Opts::new("bullmq_jobs_failed_total", "Number of jobs failed."),
+            &["queue"],
+        )?;
+        let job_retries = IntCounterVec::new(
+            Opts::new("bullmq_job_retries_total", "Number of job retries."),
+            &["queue"],
+        )?;
+        let worker_active = IntGaugeVec::new(
+            Opts::new("bullmq_worker_active_jobs", "Number of jobs currently being processed."),
+            &["queue"],
+        )?;
+        let worker_concurrency = IntGaugeVec::new(
+            Opts::new("bullmq_worker_concurrency", "Maximum number of jobs processed concurrently."),
+            &["queue"],
+        )?;
+
+        registry.register(Box::new(queue_jobs.clone()))?;
+        registry.register(Box::new(job_duration.clone()))?;
+        registry.register(Box::new(jobs_completed.clone()))?;
+        registry.register(Box::new(jobs_failed.clone()))?;
+        registry.register(Box::new(job_retries.clone()))?;
+        registry.register(Box::new(worker_active.clone()))?;
+        registry.register(Box::new(worker_concurrency.clone()))?;
+
+        Ok(Self {
+            registry,
+            queue_jobs,
+            job_duration,
+            jobs_completed,
+            jobs_failed,
+            job_retries,
+            worker_active,
+            worker_concurrency,
+        })
+    }
+
+    /// Sets the number of jobs of a queue in the given state, as reported by `count_jobs`.
+    pub fn set_queue_depth(&self, queue_name: &str, state: &str, count: u64) {
+        self.queue_jobs.with_label_values(&[queue_name, state]).set(count as i64);
+    }
+
+    /// Records a completed job and its processing duration.
+    pub fn observe_completed(&self, queue_name: &str, duration_secs: f64) {
+        self.jobs_completed.with_label_values(&[queue_name]).inc();
+        self.job_duration.with_label_values(&[queue_name]).observe(duration_secs);
+    }
+
+    /// Records a failed job.
+    pub fn observe_failed(&self, queue_name: &str) {
+        self.jobs_failed.with_label_values(&[queue_name]).inc();
+    }
+
+    /// Records a job retry.
+    pub fn observe_retry(&self, queue_name: &str) {
+        self.job_retries.with_label_values(&[queue_name]).inc();
+    }
+
+    /// Sets the maximum number of jobs a worker processes concurrently.
+    pub fn set_concurrency(&self, queue_name: &str, concurrency: u64) {
+        self.worker_concurrency.with_label_values(&[queue_name]).set(concurrency as i64);
+    }
+
+    /// Increments the number of jobs currently being processed.
+    pub fn job_started(&self, queue_name: &str) {
+        self.worker_active.with_label_values(&[queue_name]).inc();
+    }
+
+    /// Decrements the number of jobs currently being processed.
+    pub fn job_finished(&self, queue_name: &str) {
+        self.worker_active.with_label_values(&[queue_name]).dec();
+    }
+
+    /// Renders all metrics in the Prometheus text exposition format.
+    ///
+    /// The worker utilization is derived from the active jobs and the concurrency
+    /// at render time, so it is always consistent with both gauges.
+    pub fn render(&self) -> String {
+        let mut families = self.registry.gather();
+        families.extend(self.utilization());
+
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&families, &mut buffer).unwrap_or_default();
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+
+    /// Serves the `/metrics` endpoint on the given address.
+    ///
+    /// Connections that do not send their request headers within 10 seconds are closed.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address to listen on, port `0` picks a free port.
+    ///
+    /// # Returns
+    ///
+    /// An `io::Result` containing the bound address and the handle of the server task.
+    pub async fn serve(self: Arc<Self>, addr: SocketAddr) -> std::io::Result<(SocketAddr, JoinHandle<()>)> {
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        let router = Router::new().route("/metrics", get(metrics)).with_state(self);
+        let handle = task::spawn(async move {
+            loop {
+                let stream = match listener.accept().await {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        diagnostic!(warn, "Error accepting metrics connection: {}", e);
+                        continue;
+                    }
+                };
+                let service = TowerToHyperService::new(router.clone());
+                task::spawn(async move {
+                    let connection = http1::Builder::new()
+                        .timer(TokioTimer::new())
+                        .header_read_timeout(HEADER_READ_TIMEOUT)
+                        .serve_connection(TokioIo::new(stream), service);
+                    // Timed out and reset connections only concern the scraper.
+                    let _ = connection.await;
+                });
+            }
+        });
+        Ok((local_addr, handle))
+    }
+
+    fn utilization(&self) -> Vec<prometheus::proto::MetricFamily> {
+        let utilization = match GaugeVec::new(
+            Opts::new("bullmq_worker_utilization", "Ratio of active jobs to worker concurrency."),
+            &["queue"],
+        ) {
+            Ok(utilization) => utilization,
+            Err(_) => return Vec::new(),
+        };
+        for family in self.worker_concurrency.collect() {
+            for metric in family.get_metric() {
+                let queue_name = match metric.get_label().iter().find(|label| label.name() == "queue") {
+                    Some(label) => label.value(),
+                    None => continue,
+                };
+                let concurrency = metric.get_gauge().get_value();
+                if concurrency > 0.0 {
+                    let active_jobs = self.worker_active.with_label_values(&[queue_name]).get() as f64;
+                    utilization.with_label_values(&[queue_name]).set(active_jobs / concurrency);
+                }
+            }
+        }
+        utilization.collect()
+    }
+}
+
+async fn metrics(State(service): State<Arc<PrometheusService>>) -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], service.render())
+}
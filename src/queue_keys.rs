@@ -0,0 +1,169 @@
+use crate::metrics_model::MetricKind;
+
+/// Default prefix of every queue key.
+pub const DEFAULT_KEY_PREFIX: &str = "bull";
+
+/// Escapes a key segment so it can not be mistaken for a key separator or hash tag.
+///
+/// `%`, `:`, `{` and `}` are percent-encoded, so a job ID such as `log` or
+/// `1:logs` can never produce the same key as another key of the queue.
+///
+/// # Arguments
+///
+/// * `segment` - The raw key segment, such as a queue name or job ID.
+///
+/// # Returns
+///
+/// The escaped key segment.
+pub fn escape_segment(segment: &str) -> String {
+    let mut escaped = String::with_capacity(segment.len());
+    for c in segment.chars() {
+        match c {
+            '%' => escaped.push_str("%25"),
+            ':' => escaped.push_str("%3A"),
+            '{' => escaped.push_str("%7B"),
+            '}' => escaped.push_str("%7D"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Reverses [`escape_segment`].
+///
+/// # Arguments
+///
+/// * `segment` - The escaped key segment.
+///
+/// # Returns
+///
+/// The raw key segment.
+pub fn unescape_segment(segment: &str) -> String {
+    let mut unescaped = String::with_capacity(segment.len());
+    let mut rest = segment;
+    while let Some(index) = rest.find('%') {
+        unescaped.push_str(&rest[..index]);
+        let (c, len) = match rest.get(index..index + 3) {
+            Some("%25") => ('%', 3),
+            Some("%3A") => (':', 3),
+            Some("%7B") => ('{', 3),
+            Some("%7D") => ('}', 3),
+            _ => ('%', 1),
+        };
+        unescaped.push(c);
+        rest = &rest[index + len..];
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+/// Builds the Redis keys of a queue.
+///
+/// Keys have the form `<prefix>:{<queue>}:<kind>[:<id>]`. The queue name is
+/// wrapped in a `{...}` hash tag, so all keys of a queue live in the same Redis
+/// Cluster slot and can be used together in transactions and multi-key commands.
+/// Queue names and job IDs are escaped with [`escape_segment`], while the prefix
+/// is used as is so it can itself contain `:` separated namespaces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueueKeys {
+    base: String,
+}
+
+impl QueueKeys {
+    /// Creates the key builder of a queue using the default `bull` prefix.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of `QueueKeys`.
+    pub fn new(queue_name: &str) -> Self {
+        Self::with_prefix(DEFAULT_KEY_PREFIX, queue_name)
+    }
+
+    /// Creates the key builder of a queue using the given prefix.
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix` - The prefix of every key, e.g. the name of the application.
+    /// * `queue_name` - The name of the queue.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of `QueueKeys`.
+    pub fn with_prefix(prefix: &str, queue_name: &str) -> Self {
+        Self { base: format!("{}:{{{}}}", prefix, escape_segment(queue_name)) }
+    }
+
+    /// The sorted set of waiting jobs.
+    pub fn wait(&self) -> String {
+        format!("{}:wait", self.base)
+    }
+
+    /// The stream of jobs of the Redis Streams backend.
+    pub fn stream(&self) -> String {
+        format!("{}:stream", self.base)
+    }
+
+    /// The sorted set of delayed jobs, scored by due time in milliseconds.
+    pub fn delayed(&self) -> String {
+        format!("{}:delayed", self.base)
+    }
+
+    /// The sorted set of failed jobs.
+    pub fn failed(&self) -> String {
+        format!("{}:failed", self.base)
+    }
+
+    /// The flag set while the queue is paused.
+    pub fn paused(&self) -> String {
+        format!("{}:paused", self.base)
+    }
+
+    /// The list of queue log entries.
+    pub fn log(&self) -> String {
+        format!("{}:log", self.base)
+    }
+
+    /// The hash holding the progress of a job, and the stream entry of a fetched job on the Streams backend.
+    pub fn job(&self, job_id: &str) -> String {
+        format!("{}:job:{}", self.base, escape_segment(job_id))
+    }
+
+    /// The list of log lines of a job.
+    pub fn job_logs(&self, job_id: &str) -> String {
+        format!("{}:job:{}:logs", self.base, escape_segment(job_id))
+    }
+
+    /// The flag set while the cancellation of a job is requested.
+    pub fn job_cancel(&self, job_id: &str) -> String {
+        format!("{}:job:{}:cancel", self.base, escape_segment(job_id))
+    }
+
+    /// The key holding a metric for one minute.
+    pub fn metric(&self, kind: MetricKind, minute: i64) -> String {
+        format!("{}:metrics:{}:{}", self.base, kind.as_str(), minute)
+    }
+
+    /// The `SCAN` pattern matching every key of every queue using the given prefix.
+    pub fn scan_pattern(prefix: &str) -> String {
+        let mut pattern = String::with_capacity(prefix.len() + 6);
+        for c in prefix.chars() {
+            if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+                pattern.push('\\');
+            }
+            pattern.push(c);
+        }
+        pattern.push_str(":{*}:*");
+        pattern
+    }
+
+    /// Returns the name of the queue a key belongs to, if it is a queue key using the given prefix.
+    pub fn queue_name(prefix: &str, key: &str) -> Option<String> {
+        let rest = key.strip_prefix(prefix)?.strip_prefix(":{")?;
+        let (queue_name, _) = rest.split_once("}:")?;
+        Some(unescape_segment(queue_name))
+    }
+}
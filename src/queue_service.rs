@@ -1,15 +1,77 @@
 use async_trait::async_trait;
-use redis::aio::{ConnectionManager, ConnectionManagerConfig};
 use redis::{AsyncCommands, RedisError, RedisResult};
 use serde_json;
 use chrono::Utc;
+use std::collections::BTreeSet;
+use std::time::Duration;
 use crate::config_service::ConfigService;
-use crate::job_model::JobData;
+use crate::connection_service::RedisConnection;
+use crate::job_model::{JobData, JobState};
+use crate::log_model::{LogEntry, LogRange};
+use crate::log_service::LogService;
+use crate::metrics_model::{MetricKind, MetricPoint, MetricsRange};
+use crate::metrics_service::MetricsService;
+use crate::queue_keys::{QueueKeys, DEFAULT_KEY_PREFIX};
 use crate::QueueServiceTrait;
 
+/// Interval at which a worker waiting on a paused queue checks whether it was resumed.
+pub const PAUSED_POLL_INTERVAL_MS: u64 = 1000;
+
+/// Interval at which a worker checks whether the cancellation of the job it processes was requested.
+pub const CANCEL_POLL_INTERVAL_MS: u64 = 500;
+
+/// Time after which a cancellation request no worker picked up is dropped.
+pub const CANCELLATION_TTL_SECS: u64 = 86_400;
+
+/// Number of keys `SCAN` is asked to visit per call when listing queues.
+const SCAN_COUNT: u64 = 1000;
+
+/// Maximum number of due delayed jobs moved to the waiting jobs at once.
+const PROMOTE_BATCH_SIZE: usize = 100;
+
+/// Moves the due jobs of the delayed sorted set (`KEYS[1]`) to the waiting jobs (`KEYS[2]`),
+/// scored with their due time in milliseconds.
+const PROMOTE_SCRIPT: &str = r"
+local due = redis.call('ZRANGEBYSCORE', KEYS[1], '-inf', ARGV[1], 'WITHSCORES', 'LIMIT', 0, ARGV[2])
+for i = 1, #due, 2 do
+    redis.call('ZADD', KEYS[2], due[i + 1], due[i])
+    redis.call('ZREM', KEYS[1], due[i])
+end
+return #due / 2
+";
+
+/// Returns the inclusive stop index of a page for `ZRANGE`-like commands, `-1` when it reaches the end.
+pub(crate) fn range_stop(offset: usize, limit: usize) -> isize {
+    offset
+        .checked_add(limit - 1)
+        .filter(|stop| *stop <= isize::MAX as usize)
+        .map(|stop| stop as isize)
+        .unwrap_or(-1)
+}
+
+/// Waits until the queue may have been resumed, but no longer than the fetch timeout.
+pub(crate) async fn wait_while_paused(timeout_secs: f64) {
+    let mut wait = Duration::from_millis(PAUSED_POLL_INTERVAL_MS);
+    if timeout_secs > 0.0 {
+        wait = wait.min(Duration::from_secs_f64(timeout_secs));
+    }
+    tokio::time::sleep(wait).await;
+}
+
 /// Service responsible for managing a Redis queue.
+///
+/// Waiting jobs are kept in a sorted set scored with the time they became
+/// ready, in milliseconds, and popped lowest score first, so they are fetched
+/// in the order they were added. Jobs with a delay wait in the delayed sorted
+/// set, scored with their due time in seconds, until a fetch moves them to the
+/// waiting jobs.
+#[derive(Clone)]
 pub struct QueueService {
-    con: ConnectionManager,
+    con: RedisConnection,
+    blocking_con: Option<RedisConnection>,
+    metrics: MetricsService,
+    logs: LogService,
+    key_prefix: String,
 }
 
 impl QueueService {
@@ -22,21 +84,171 @@ impl QueueService {
     /// # Returns
     ///
     /// A new instance of `QueueService`.
-    pub fn new(con: ConnectionManager) -> Self {
-        Self { con }
+    pub fn new(con: impl Into<RedisConnection>) -> Self {
+        let con = con.into();
+        let metrics = MetricsService::new(con.clone());
+        let logs = LogService::new(con.clone());
+        Self { con, blocking_con: None, metrics, logs, key_prefix: DEFAULT_KEY_PREFIX.to_string() }
     }
 
-    /// Connects to the Redis server.
+    /// Creates a new `QueueService` connected according to the given configuration.
+    ///
+    /// The configured key prefix is used for every key of the service.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The Redis configuration.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the new instance of `QueueService`.
+    pub async fn from_config(config: &ConfigService) -> RedisResult<Self> {
+        Ok(Self::new(config.connect().await?).with_key_prefix(&config.key_prefix))
+    }
+
+    /// Sets the prefix of every key of the service.
+    ///
+    /// Applications sharing a Redis server use different prefixes to keep their queues apart.
+    ///
+    /// # Arguments
+    ///
+    /// * `key_prefix` - The key prefix, `bull` by default.
+    ///
+    /// # Returns
+    ///
+    /// The `QueueService` using the given key prefix.
+    pub fn with_key_prefix(mut self, key_prefix: &str) -> Self {
+        self.key_prefix = key_prefix.to_string();
+        self.metrics = self.metrics.with_key_prefix(key_prefix);
+        self.logs = self.logs.with_key_prefix(key_prefix);
+        self
+    }
+
+    /// Sets the maximum number of lines kept in each job log.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_lines` - The maximum number of log lines per job.
+    ///
+    /// # Returns
+    ///
+    /// The `QueueService` using the given job log cap.
+    pub fn with_max_job_log_lines(mut self, max_lines: usize) -> Self {
+        self.logs = self.logs.with_max_job_lines(max_lines);
+        self
+    }
+
+    /// Sets the maximum number of entries kept in each queue log.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_entries` - The maximum number of log entries per queue.
+    ///
+    /// # Returns
+    ///
+    /// The `QueueService` using the given log cap.
+    pub fn with_max_log_entries(mut self, max_entries: usize) -> Self {
+        self.logs = self.logs.with_max_entries(max_entries);
+        self
+    }
+
+    /// Sets the number of minutes metrics data points are kept for.
+    ///
+    /// # Arguments
+    ///
+    /// * `retention_minutes` - The retention period of the metrics, in minutes.
+    ///
+    /// # Returns
+    ///
+    /// The `QueueService` using the given metrics retention.
+    pub fn with_metrics_retention(mut self, retention_minutes: u64) -> Self {
+        self.metrics = self.metrics.with_retention_minutes(retention_minutes);
+        self
+    }
+
+    /// Sets a dedicated connection used for blocking commands.
+    ///
+    /// Blocking commands such as `BZPOPMIN` hold their connection until a job
+    /// arrives, so they must not share the multiplexed connection used by the
+    /// other operations.
+    ///
+    /// # Arguments
+    ///
+    /// * `con` - The Redis connection reserved for blocking commands.
+    ///
+    /// # Returns
+    ///
+    /// The `QueueService` using the given blocking connection.
+    pub fn with_blocking_connection(mut self, con: impl Into<RedisConnection>) -> Self {
+        self.blocking_con = Some(con.into());
+        self
+    }
+
+    /// Connects to the Redis server, or to the Redis Cluster when cluster nodes are configured.
     ///
     /// # Returns
     ///
     /// A Redis connection.
-    pub async fn connect() -> ConnectionManager {
+    pub async fn connect() -> RedisConnection {
         let config = ConfigService::new();
-        let redis_client = config.get_client().unwrap();
-        let con_manager = ConnectionManager::new_with_config(redis_client, ConnectionManagerConfig::new().set_max_delay(5000)).await.unwrap();
+        config.connect().await.unwrap()
+    }
+
+    /// Returns the key builder of a queue.
+    fn keys(&self, queue_name: &str) -> QueueKeys {
+        QueueKeys::with_prefix(&self.key_prefix, queue_name)
+    }
+
+    /// Returns the sorted set holding the jobs in the given state.
+    fn state_key(&self, queue_name: &str, state: JobState) -> String {
+        let keys = self.keys(queue_name);
+        match state {
+            JobState::Waiting => keys.wait(),
+            JobState::Failed => keys.failed(),
+        }
+    }
+
+    /// Adds a serialized job to the sorted set with the given key, scored with the current time.
+    async fn push_job(&mut self, key: &str, job: &JobData) -> RedisResult<()> {
+        let job_json = serde_json::to_string(job).unwrap();
+        let score = Utc::now().timestamp();
+        let _: () = self.con.zadd(key, job_json, score).await?;
+        Ok(())
+    }
+
+    /// Queues the commands adding a job to the waiting jobs, or to the delayed jobs until it is due.
+    fn enqueue(pipe: &mut redis::Pipeline, keys: &QueueKeys, job: &JobData, delay_secs: i64, now_ms: i64) {
+        let job_json = serde_json::to_string(job).unwrap();
+        match Some(delay_secs).filter(|delay| *delay > 0) {
+            Some(delay) => pipe.zadd(keys.delayed(), job_json, now_ms + delay.saturating_mul(1000)).ignore(),
+            None => pipe.zadd(keys.wait(), job_json, now_ms).ignore(),
+        };
+    }
 
-        con_manager
+    /// Moves the due delayed jobs of a queue to its waiting jobs.
+    async fn promote_delayed(&mut self, queue_name: &str) -> RedisResult<()> {
+        let keys = self.keys(queue_name);
+        let _: u64 = redis::Script::new(PROMOTE_SCRIPT)
+            .key(keys.delayed())
+            .key(keys.wait())
+            .arg(Utc::now().timestamp_millis())
+            .arg(PROMOTE_BATCH_SIZE)
+            .invoke_async(&mut self.con)
+            .await?;
+        Ok(())
+    }
+
+    /// Returns the members of a sorted set holding the job with the given ID.
+    pub(crate) async fn find_job_members(&mut self, key: &str, job_id: &str) -> RedisResult<Vec<String>> {
+        let members: Vec<String> = self.con.zrange(key, 0, -1).await?;
+        Ok(members
+            .into_iter()
+            .filter(|member| {
+                serde_json::from_str::<JobData>(member)
+                    .map(|job| job.id == job_id)
+                    .unwrap_or(false)
+            })
+            .collect())
     }
 }
 
@@ -52,32 +264,104 @@ impl QueueServiceTrait for QueueService {
     /// # Returns
     ///
     /// A `RedisResult` indicating the success or failure of the operation.
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "enqueue", skip(self, job), fields(queue = queue_name, job_id = %job.id)))]
     async fn add_job(&mut self, queue_name: &str, job: JobData) -> RedisResult<()> {
-        let job_json = serde_json::to_string(&job).unwrap();
-        let score = Utc::now().timestamp() + job.delay.unwrap_or(0);
-        let _: () = self.con.zadd(queue_name, job_json, score).await?;
+        let delay_secs = job.delay.unwrap_or(0);
+        self.add_job_after(queue_name, job, delay_secs).await
+    }
+
+    /// Adds a job to the specified queue, available after the given delay rather than its own.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to add the job to.
+    /// * `job` - The job data to add to the queue.
+    /// * `delay_secs` - The delay in seconds before the job can be fetched.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn add_job_after(&mut self, queue_name: &str, job: JobData, delay_secs: i64) -> RedisResult<()> {
+        let mut pipe = redis::pipe();
+        Self::enqueue(&mut pipe, &self.keys(queue_name), &job, delay_secs, Utc::now().timestamp_millis());
+        let _: () = pipe.query_async(&mut self.con).await?;
         Ok(())
     }
 
     /// Retrieves the next job from the specified queue.
     ///
+    /// The due delayed jobs are moved to the waiting jobs first, then the oldest waiting job is popped.
+    ///
     /// # Arguments
     ///
     /// * `queue_name` - The name of the queue to retrieve the job from.
     ///
     /// # Returns
     ///
-    /// 
-    /// 
-    /// A `RedisResult` containing an optional job JSON string.
+    /// A `RedisResult` containing the job JSON string and its score, or `None` while the queue is paused.
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "fetch", skip(self), fields(queue = queue_name)))]
     async fn get_next_job(&mut self, queue_name: &str) -> RedisResult<Option<Vec<String>>> {
-        match self.con.zpopmax::<String, Option<Vec<String>>>(queue_name.to_string(), 1).await {
+        if self.is_paused(queue_name).await? {
+            return Ok(None);
+        }
+        self.promote_delayed(queue_name).await?;
+        match self.con.zpopmin::<String, Option<Vec<String>>>(self.keys(queue_name).wait(), 1).await {
             Ok(Some(next_job)) => Ok(Some(next_job)),
             Ok(None) => Err(RedisError::from((redis::ErrorKind::TypeError, "No job found"))),
             Err(e) => Err(e),
         }
     }
 
+    /// Waits for the next job from the specified queue.
+    ///
+    /// Uses `BZPOPMIN` on the blocking connection when one is set, falling
+    /// back to the shared connection otherwise, and wakes up in time to move
+    /// the next delayed job to the waiting jobs once it is due. While the queue
+    /// is paused, returns `None` after waiting for at most a second.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to retrieve the job from.
+    /// * `timeout_secs` - The maximum time in seconds to block, `0` blocks forever.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the job JSON string and its score, or `None` on timeout.
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "fetch", skip(self), fields(queue = queue_name)))]
+    async fn wait_for_next_job(&mut self, queue_name: &str, timeout_secs: f64) -> RedisResult<Option<Vec<String>>> {
+        if self.is_paused(queue_name).await? {
+            wait_while_paused(timeout_secs).await;
+            return Ok(None);
+        }
+        let deadline = (timeout_secs > 0.0)
+            .then(|| tokio::time::Instant::now() + Duration::from_secs_f64(timeout_secs));
+        let keys = self.keys(queue_name);
+        loop {
+            self.promote_delayed(queue_name).await?;
+            let mut block = None;
+            if let Some(deadline) = deadline {
+                let now = tokio::time::Instant::now();
+                if deadline <= now {
+                    return Ok(None);
+                }
+                block = Some(deadline - now);
+            }
+            let next_due: Vec<(String, i64)> = self.con.zrange_withscores(keys.delayed(), 0, 0).await?;
+            if let Some((_, due)) = next_due.first() {
+                let until_due = Duration::from_millis((due - Utc::now().timestamp_millis()).max(0) as u64);
+                block = Some(block.map_or(until_due, |block| block.min(until_due)));
+            }
+
+            // A zero timeout blocks forever, so a block due right away waits for a millisecond instead.
+            let block_secs = block.map_or(0.0, |block| block.as_secs_f64().max(0.001));
+            let con = self.blocking_con.as_mut().unwrap_or(&mut self.con);
+            let popped: Option<(String, String, String)> = con.bzpopmin(keys.wait(), block_secs).await?;
+            if let Some((_, job_json, score)) = popped {
+                return Ok(Some(vec![job_json, score]));
+            }
+        }
+    }
+
     /// Counts the number of jobs in the specified queue.
     ///
     /// # Arguments
@@ -88,8 +372,28 @@ impl QueueServiceTrait for QueueService {
     ///
     /// A `RedisResult` containing the number of jobs in the queue.
     async fn count_jobs(&mut self, queue_name: &str) -> RedisResult<u64> {
-        let count: u64 = self.con.zcard(queue_name).await?;
-        Ok(count)
+        self.count_jobs_by_state(queue_name, JobState::Waiting).await
+    }
+
+    /// Counts the number of jobs of the specified queue in the given state.
+    ///
+    /// Waiting jobs include the delayed jobs.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to count the jobs in.
+    /// * `state` - The state of the jobs to count.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the number of jobs in the given state.
+    async fn count_jobs_by_state(&mut self, queue_name: &str, state: JobState) -> RedisResult<u64> {
+        let count: u64 = self.con.zcard(self.state_key(queue_name, state)).await?;
+        if state != JobState::Waiting {
+            return Ok(count);
+        }
+        let delayed: u64 = self.con.zcard(self.keys(queue_name).delayed()).await?;
+        Ok(count + delayed)
     }
 
     /// Moves a job to the failed queue.
@@ -103,8 +407,8 @@ impl QueueServiceTrait for QueueService {
     ///
     /// A `RedisResult` indicating the success or failure of the operation.
     async fn move_to_failed(&mut self, queue_name: &str, job: JobData) -> RedisResult<()> {
-        let failed_queue_name = format!("{}:failed", queue_name);
-        self.add_job(&failed_queue_name, job).await
+        self.push_job(&self.keys(queue_name).failed(), &job).await?;
+        self.metrics.record_count(queue_name, MetricKind::Failed).await
     }
 
     /// Logs the status of a job.
@@ -119,10 +423,8 @@ impl QueueServiceTrait for QueueService {
     ///
     /// A `RedisResult` indicating the success or failure of the operation.
     async fn log_job_status(&mut self, queue_name: &str, job: &JobData, status: &str) -> RedisResult<()> {
-        let log_queue_name = format!("{}:log", queue_name);
-        let log_entry = format!("{} - {}: {}", Utc::now().to_rfc3339(), status, job.message);
-        let _: () = self.con.lpush(log_queue_name, log_entry).await?;
-        Ok(())
+        let entry = LogEntry::job_status(job, status);
+        self.logs.log(queue_name, &entry).await
     }
 
     /// Updates the progress of a job.
@@ -137,7 +439,7 @@ impl QueueServiceTrait for QueueService {
     ///
     /// A `RedisResult` indicating the success or failure of the operation.
     async fn update_job_progress(&mut self, queue_name: &str, job_id: &str, progress: u32) -> RedisResult<()> {
-        let job_key = format!("{}:{}", queue_name, job_id);
+        let job_key = self.keys(queue_name).job(job_id);
         let _: () = self.con.hset(job_key, "progress", progress).await?;
         Ok(())
     }
@@ -151,10 +453,393 @@ impl QueueServiceTrait for QueueService {
     ///
     /// # Returns
     ///
-    /// A `RedisResult` containing the progress value of the job.
-    async fn get_job_progress(&mut self, queue_name: &str, job_id: &str) -> RedisResult<u32> {
-        let job_key = format!("{}:{}", queue_name, job_id);
-        let progress: u32 = self.con.hget(job_key, "progress").await?;
-        Ok(progress)
+    /// A `RedisResult` containing the progress value of the job, or `None` if none was set.
+    async fn get_job_progress(&mut self, queue_name: &str, job_id: &str) -> RedisResult<Option<u32>> {
+        let job_key = self.keys(queue_name).job(job_id);
+        self.con.hget(job_key, "progress").await
+    }
+
+    /// Marks a job as completed and records its throughput and latency metrics.
+    ///
+    /// The wait time is measured from the moment the job became available,
+    /// i.e. its timestamp plus its delay, to the start of its processing.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job` - A reference to the job data.
+    /// * `processing_ms` - The time spent processing the job, in milliseconds.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn mark_completed(&mut self, queue_name: &str, job: &JobData, processing_ms: u64) -> RedisResult<()> {
+        self.metrics.record_count(queue_name, MetricKind::Completed).await?;
+        self.metrics.record_duration(queue_name, MetricKind::ProcessingTime, processing_ms).await?;
+        if let Some(wait_ms) = job.wait_time_ms(processing_ms) {
+            self.metrics.record_duration(queue_name, MetricKind::WaitTime, wait_ms).await?;
+        }
+        Ok(())
+    }
+
+    /// Retrieves the per-minute data points of a queue metric.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to read the metrics of.
+    /// * `kind` - The metric to read.
+    /// * `range` - The time range to read.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing one data point per minute, oldest first.
+    async fn get_metrics(&mut self, queue_name: &str, kind: MetricKind, range: MetricsRange) -> RedisResult<Vec<MetricPoint>> {
+        self.metrics.get_metrics(queue_name, kind, range).await
+    }
+
+    /// Reads entries from the specified queue's log, most recent first.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to read the log of.
+    /// * `job_id` - Only return the entries of this job, if set.
+    /// * `range` - The page of matching entries to return.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the matching entries.
+    async fn read_logs(&mut self, queue_name: &str, job_id: Option<&str>, range: LogRange) -> RedisResult<Vec<LogEntry>> {
+        self.logs.read_logs(queue_name, job_id, range).await
+    }
+
+    /// Appends a line to the log of a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    /// * `line` - The line to append.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the number of lines in the job log.
+    async fn job_log(&mut self, queue_name: &str, job_id: &str, line: &str) -> RedisResult<u64> {
+        self.logs.job_log(queue_name, job_id, line).await
+    }
+
+    /// Reads lines from the log of a job, oldest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    /// * `start` - The index of the first line, negative values count from the end.
+    /// * `end` - The index of the last line, inclusive, negative values count from the end.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the requested lines.
+    async fn get_job_logs(&mut self, queue_name: &str, job_id: &str, start: isize, end: isize) -> RedisResult<Vec<String>> {
+        self.logs.get_job_logs(queue_name, job_id, start, end).await
+    }
+
+    /// Removes a job from the queue, its delayed jobs and its failed queue, together with its progress and log lines.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the job was found in a queue.
+    async fn remove_job(&mut self, queue_name: &str, job_id: &str) -> RedisResult<bool> {
+        let keys = self.keys(queue_name);
+        let waiting = self.find_job_members(&keys.wait(), job_id).await?;
+        let delayed = self.find_job_members(&keys.delayed(), job_id).await?;
+        let failed = self.find_job_members(&keys.failed(), job_id).await?;
+        let found = !waiting.is_empty() || !delayed.is_empty() || !failed.is_empty();
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        if !waiting.is_empty() {
+            pipe.zrem(keys.wait(), waiting).ignore();
+        }
+        if !delayed.is_empty() {
+            pipe.zrem(keys.delayed(), delayed).ignore();
+        }
+        if !failed.is_empty() {
+            pipe.zrem(keys.failed(), failed).ignore();
+        }
+        pipe.del(keys.job(job_id)).ignore()
+            .del(keys.job_logs(job_id)).ignore()
+            .del(keys.job_cancel(job_id)).ignore();
+        let _: () = pipe.query_async(&mut self.con).await?;
+        Ok(found)
+    }
+
+    /// Adds several jobs to the specified queue in a single transaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to add the jobs to.
+    /// * `jobs` - The jobs to add, in order.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "enqueue", skip(self, jobs), fields(queue = queue_name, jobs = jobs.len())))]
+    async fn add_jobs(&mut self, queue_name: &str, jobs: Vec<JobData>) -> RedisResult<()> {
+        let keys = self.keys(queue_name);
+        let now_ms = Utc::now().timestamp_millis();
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        // Each job gets its own millisecond so the batch is fetched in order.
+        for (index, job) in jobs.into_iter().enumerate() {
+            Self::enqueue(&mut pipe, &keys, &job, job.delay.unwrap_or(0), now_ms + index as i64);
+        }
+        let _: () = pipe.query_async(&mut self.con).await?;
+        Ok(())
+    }
+
+    /// Lists the jobs of the specified queue in the given state.
+    ///
+    /// Waiting jobs are listed in the order they will be fetched, followed by
+    /// the delayed jobs by due time. Failed jobs are listed most recent first.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to list the jobs of.
+    /// * `state` - The state of the jobs to list.
+    /// * `offset` - The number of jobs to skip.
+    /// * `limit` - The maximum number of jobs to return.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the jobs.
+    async fn list_jobs(&mut self, queue_name: &str, state: JobState, offset: usize, limit: usize) -> RedisResult<Vec<JobData>> {
+        if limit == 0 || offset > isize::MAX as usize {
+            return Ok(Vec::new());
+        }
+        let keys = self.keys(queue_name);
+        let members: Vec<String> = match state {
+            JobState::Failed => self.con.zrevrange(keys.failed(), offset as isize, range_stop(offset, limit)).await?,
+            JobState::Waiting => {
+                let ready: usize = self.con.zcard(keys.wait()).await?;
+                let mut members: Vec<String> = Vec::new();
+                if offset < ready {
+                    members = self.con.zrange(keys.wait(), offset as isize, range_stop(offset, limit)).await?;
+                }
+                if members.len() < limit {
+                    let offset = offset.saturating_sub(ready);
+                    let delayed: Vec<String> = self
+                        .con
+                        .zrange(keys.delayed(), offset as isize, range_stop(offset, limit - members.len()))
+                        .await?;
+                    members.extend(delayed);
+                }
+                members
+            }
+        };
+        Ok(members.iter().filter_map(|member| serde_json::from_str(member).ok()).collect())
+    }
+
+    /// Moves a failed job back to the waiting jobs, ready to be fetched immediately.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the failed job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the job was found in the failed queue.
+    async fn retry_job(&mut self, queue_name: &str, job_id: &str) -> RedisResult<bool> {
+        let keys = self.keys(queue_name);
+        let failed = self.find_job_members(&keys.failed(), job_id).await?;
+        if failed.is_empty() {
+            return Ok(false);
+        }
+        let now_ms = Utc::now().timestamp_millis();
+        let mut pipe = redis::pipe();
+        pipe.atomic().zrem(keys.failed(), &failed).ignore();
+        for member in failed {
+            pipe.zadd(keys.wait(), member, now_ms).ignore();
+        }
+        let _: () = pipe.query_async(&mut self.con).await?;
+        Ok(true)
+    }
+
+    /// Removes a failed job from the failed jobs of a queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job failed in.
+    /// * `job_id` - The ID of the failed job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the job was found in the failed queue.
+    async fn remove_failed_job(&mut self, queue_name: &str, job_id: &str) -> RedisResult<bool> {
+        let failed_key = self.keys(queue_name).failed();
+        let failed = self.find_job_members(&failed_key, job_id).await?;
+        if failed.is_empty() {
+            return Ok(false);
+        }
+        let _: () = self.con.zrem(failed_key, &failed).await?;
+        Ok(true)
+    }
+
+    /// Pauses the specified queue, workers stop fetching its jobs until it is resumed.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to pause.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn pause_queue(&mut self, queue_name: &str) -> RedisResult<()> {
+        let _: () = self.con.set(self.keys(queue_name).paused(), 1).await?;
+        Ok(())
+    }
+
+    /// Resumes a paused queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to resume.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn resume_queue(&mut self, queue_name: &str) -> RedisResult<()> {
+        let _: () = self.con.del(self.keys(queue_name).paused()).await?;
+        Ok(())
+    }
+
+    /// Tells whether the specified queue is paused.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the queue is paused.
+    async fn is_paused(&mut self, queue_name: &str) -> RedisResult<bool> {
+        self.con.exists(self.keys(queue_name).paused()).await
+    }
+
+    /// Removes the jobs in the given state older than a grace period, together with their progress and log lines.
+    ///
+    /// Waiting jobs are aged by the time they became ready, delayed jobs by their due time.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to clean.
+    /// * `state` - The state of the jobs to remove.
+    /// * `grace_secs` - The minimum age, in seconds, of the removed jobs.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the number of removed jobs.
+    async fn clean_jobs(&mut self, queue_name: &str, state: JobState, grace_secs: u64) -> RedisResult<u64> {
+        let keys = self.keys(queue_name);
+        let cutoff_ms = Utc::now().timestamp_millis() - grace_secs as i64 * 1000;
+        let cutoff = cutoff_ms.div_euclid(1000);
+        // The jobs of a bulk are scored a millisecond apart, possibly ahead of the clock,
+        // so without a grace period every waiting job is removed whatever its score.
+        let ready_cutoff_ms = if grace_secs == 0 { i64::MAX } else { cutoff_ms };
+        let sets = match state {
+            JobState::Waiting => vec![(keys.wait(), ready_cutoff_ms), (keys.delayed(), cutoff_ms)],
+            JobState::Failed => vec![(keys.failed(), cutoff)],
+        };
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        let mut removed = 0;
+        for (key, max_score) in sets {
+            let members: Vec<String> = self.con.zrangebyscore(&key, "-inf", max_score).await?;
+            if members.is_empty() {
+                continue;
+            }
+            pipe.zrem(&key, &members).ignore();
+            for job in members.iter().filter_map(|member| serde_json::from_str::<JobData>(member).ok()) {
+                pipe.del(keys.job(&job.id)).ignore()
+                    .del(keys.job_logs(&job.id)).ignore();
+            }
+            removed += members.len();
+        }
+        if removed > 0 {
+            let _: () = pipe.query_async(&mut self.con).await?;
+        }
+        Ok(removed as u64)
+    }
+
+    /// Lists the queues holding any key under the key prefix.
+    ///
+    /// On a Redis Cluster, `SCAN` only covers the node it is routed to.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the names of the queues, sorted.
+    async fn list_queues(&mut self) -> RedisResult<Vec<String>> {
+        let pattern = QueueKeys::scan_pattern(&self.key_prefix);
+        let mut queues = BTreeSet::new();
+        let mut cursor: u64 = 0;
+        loop {
+            let (next, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(&pattern)
+                .arg("COUNT")
+                .arg(SCAN_COUNT)
+                .query_async(&mut self.con)
+                .await?;
+            queues.extend(keys.iter().filter_map(|key| QueueKeys::queue_name(&self.key_prefix, key)));
+            if next == 0 {
+                return Ok(queues.into_iter().collect());
+            }
+            cursor = next;
+        }
+    }
+
+    /// Requests the cancellation of a job, with a flag expiring after `CANCELLATION_TTL_SECS`.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn cancel_job(&mut self, queue_name: &str, job_id: &str) -> RedisResult<()> {
+        let _: () = self.con.set_ex(self.keys(queue_name).job_cancel(job_id), 1, CANCELLATION_TTL_SECS).await?;
+        Ok(())
+    }
+
+    /// Consumes the cancellation request of a job, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the job's cancellation was requested.
+    async fn take_job_cancellation(&mut self, queue_name: &str, job_id: &str) -> RedisResult<bool> {
+        let removed: u64 = self.con.del(self.keys(queue_name).job_cancel(job_id)).await?;
+        Ok(removed > 0)
+    }
+
+    /// Returns a new handle on the same queues.
+    ///
+    /// # Returns
+    ///
+    /// The boxed copy of the queue service.
+    fn clone_box(&self) -> Box<dyn QueueServiceTrait> {
+        Box::new(self.clone())
     }
 }
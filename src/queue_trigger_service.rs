@@ -1,22 +1,160 @@
+use crate::config_service::ConfigService;
 use crate::job_model::JobData;
-use crate::queue_service::QueueService;
+use crate::queue_service::CANCEL_POLL_INTERVAL_MS;
+use crate::sandbox_service::{panic_reason, SandboxChild, SandboxEvent, SandboxMessage, SandboxOptions, SandboxOutcome, SandboxPool};
 use crate::QueueServiceTrait;
+use futures::future::{BoxFuture, FutureExt};
+use futures::stream::{FuturesUnordered, StreamExt};
+use redis::{RedisError, RedisResult};
+use serde::Serialize;
 use serde_json;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::sync::mpsc;
 use tokio::task;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::io::Write;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::process::Stdio;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+pub use crate::connection_service::RetryMethod;
+pub use tokio_util::sync::CancellationToken;
+#[cfg(feature = "prometheus")]
+use crate::job_model::JobState;
+#[cfg(feature = "prometheus")]
+use crate::prometheus_service::PrometheusService;
+#[cfg(feature = "webhook")]
+use crate::webhook_service::{WebhookOutcome, WebhookService};
+
+/// First delay before retrying a job the endpoint asked to retry later, doubled on each consecutive request.
+pub const BACKOFF_BASE_MS: u64 = 1_000;
+
+/// Maximum delay before retrying a job the endpoint asked to retry later.
+pub const MAX_BACKOFF_MS: u64 = 60_000;
+
+/// Time given to a handler to return once its job timed out or was cancelled, before it is dropped.
+pub const CANCEL_GRACE_MS: u64 = 1_000;
+
+/// The format of the line, or lines, the worker writes on stdout for each processed job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// Tab-aligned text for humans.
+    #[default]
+    Text,
+    /// One pretty-printed JSON object per job.
+    Json,
+    /// One JSON object per job, on a single line.
+    Jsonl,
+}
+
+impl OutputFormat {
+    /// Returns the name of the format.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutputFormat::Text => "text",
+            OutputFormat::Json => "json",
+            OutputFormat::Jsonl => "jsonl",
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "jsonl" => Ok(OutputFormat::Jsonl),
+            _ => Err("expected json, jsonl or text".to_string()),
+        }
+    }
+}
+
+/// What the worker reports about a processed job.
+#[derive(Serialize)]
+struct JobReport<'a> {
+    queue: &'a str,
+    id: &'a str,
+    timestamp: &'a str,
+    message: &'a str,
+    attempt: u32,
+    /// `completed`, `failed`, `retrying` or `postponed`.
+    status: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<&'a str>,
+    duration_ms: u64,
+}
+
+/// A job being processed by a sandbox process, resolving to the process, the job, its outcome and its start time.
+type SandboxedJob = Pin<Box<dyn Future<Output = (SandboxChild, JobData, JobOutcome, Instant)> + Send>>;
+
+/// A job handler run in the worker process, see [`QueueTriggerService::with_handler`].
+type JobHandler = Arc<dyn Fn(JobData, CancellationToken) -> BoxFuture<'static, Result<(), String>> + Send + Sync>;
+
+/// Why the worker stopped a job before its handler returned.
+enum Interruption {
+    /// The attempt took longer than the job's timeout.
+    TimedOut(Duration),
+    /// The cancellation of the job was requested.
+    Cancelled,
+}
+
+impl Interruption {
+    /// Returns the outcome of the interrupted attempt.
+    fn outcome(self) -> JobOutcome {
+        match self {
+            Interruption::TimedOut(timeout) => JobOutcome::failed(format!("timed out after {}ms", timeout.as_millis())),
+            Interruption::Cancelled => JobOutcome::Cancelled,
+        }
+    }
+}
+
+/// How processing a job ended.
+enum JobOutcome {
+    /// The job completed.
+    Completed,
+    /// The attempt failed for the given reason, with the stack trace or context reported by the handler.
+    Failed { reason: String, stack: Option<String> },
+    /// The job could not be handled now and should be retried after a delay, without consuming an attempt.
+    #[cfg_attr(not(feature = "webhook"), allow(dead_code))]
+    Backoff { retry_after: Option<Duration>, reason: String },
+    /// The job was cancelled; it is failed without being retried.
+    Cancelled,
+}
+
+impl JobOutcome {
+    /// Returns the outcome of an attempt failed for the given reason, without stack trace.
+    fn failed(reason: String) -> Self {
+        JobOutcome::Failed { reason, stack: None }
+    }
+}
 
 /// Service responsible for triggering actions based on queue messages.
+#[derive(Clone)]
 pub struct QueueTriggerService {
     queue_name: String,
-}
-use serde::{Deserialize, Serialize};
-
-#[derive(Serialize, Deserialize, Debug)]
-pub enum RetryMethod {
-    Reconnect,
-    AskRedirect,
-    MovedRedirect,
-    WaitAndRetry,
-    RetryImmediately,
+    exec: Option<String>,
+    sandbox: Option<SandboxOptions>,
+    handler: Option<JobHandler>,
+    job_timeout: Option<Duration>,
+    output: OutputFormat,
+    quiet: bool,
+    #[cfg(feature = "webhook")]
+    webhook: Option<Arc<WebhookService>>,
+    #[cfg(feature = "prometheus")]
+    prometheus: Option<Arc<PrometheusService>>,
 }
 
 impl QueueTriggerService {
@@ -30,51 +168,756 @@ impl QueueTriggerService {
     ///
     /// A new instance of `QueueTriggerService`.
     pub fn new(queue_name: String) -> Self {
-        Self { queue_name }
+        Self {
+            queue_name,
+            exec: None,
+            sandbox: None,
+            handler: None,
+            job_timeout: None,
+            output: OutputFormat::Text,
+            quiet: false,
+            #[cfg(feature = "webhook")]
+            webhook: None,
+            #[cfg(feature = "prometheus")]
+            prometheus: None,
+        }
     }
 
-    /// Starts the trigger to monitor the queue for messages.
+    /// Reports the queue and worker metrics to the given Prometheus exporter.
+    ///
+    /// # Arguments
+    ///
+    /// * `prometheus` - The exporter the metrics are reported to.
+    ///
+    /// # Returns
     ///
-    /// This function spawns a new asynchronous task that continuously fetches
-    /// and processes messages from the queue.
+    /// The `QueueTriggerService` reporting to the exporter.
+    #[cfg(feature = "prometheus")]
+    pub fn with_prometheus(mut self, prometheus: Arc<PrometheusService>) -> Self {
+        self.prometheus = Some(prometheus);
+        self
+    }
+
+    /// Sets the format of the report written on stdout for each processed job, text by default.
+    ///
+    /// Diagnostics are always written on stderr, so stdout only holds job reports.
+    pub fn with_output(mut self, output: OutputFormat) -> Self {
+        self.output = output;
+        self
+    }
+
+    /// Stops writing a report on stdout for each processed job; diagnostics are still written on stderr.
+    pub fn with_quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// Processes each job by running a shell command instead of printing it.
+    ///
+    /// The command gets the job message on stdin and the `JOB_ID`, `QUEUE`
+    /// and `ATTEMPT` environment variables. Exit code 0 completes the job;
+    /// any other exit code fails it, with the command's stderr as the reason.
     ///
     /// # Arguments
     ///
-    /// * `refresh_time_milli` - The time in milliseconds to wait before checking the queue again.
-    pub async fn start(&self, refresh_time_milli: u64) {
-        let con_manager = QueueService::connect().await;
-        let mut queue_service = QueueService::new(con_manager);
+    /// * `command` - The command line, run with `sh -c`.
+    ///
+    /// # Returns
+    ///
+    /// The `QueueTriggerService` running the command.
+    pub fn with_exec(mut self, command: &str) -> Self {
+        self.exec = Some(command.to_string());
+        self
+    }
 
-        let queue_name = self.queue_name.clone();
+    /// Processes each job with an async function instead of printing it.
+    ///
+    /// The handler gets the job and a cancellation token, cancelled when the
+    /// job times out or its cancellation is requested with
+    /// [`QueueServiceTrait::cancel_job`]. Returning `Ok` completes the job;
+    /// returning an error or panicking fails it. A handler still running
+    /// `CANCEL_GRACE_MS` after its token was cancelled is dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `handler` - The function processing each job.
+    ///
+    /// # Returns
+    ///
+    /// The `QueueTriggerService` running the handler.
+    pub fn with_handler<F, Fut>(mut self, handler: F) -> Self
+    where
+        F: Fn(JobData, CancellationToken) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), String>> + Send + 'static,
+    {
+        self.handler = Some(Arc::new(move |job, token| handler(job, token).boxed()));
+        self
+    }
+
+    /// Fails the attempts taking longer than the given time, for the jobs not setting their own `timeout`.
+    ///
+    /// This timeout takes precedence over the one configured for the queue in
+    /// `queue_timeouts_ms`.
+    ///
+    /// A timed out attempt is failed, and retried while the job has retries
+    /// left, with a `timed out after <ms>ms` reason. The handler's cancellation
+    /// token is cancelled, and exec commands and sandbox processes are killed.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The maximum time an attempt may take.
+    ///
+    /// # Returns
+    ///
+    /// The `QueueTriggerService` enforcing the timeout.
+    pub fn with_job_timeout(mut self, timeout: Duration) -> Self {
+        self.job_timeout = Some(timeout);
+        self
+    }
+
+    /// Processes the jobs in a pool of sandbox processes instead of printing them.
+    ///
+    /// Each process is a long-lived command reading one job per line on its
+    /// stdin and answering on its stdout, see [`crate::sandbox_service`], so a
+    /// handler that panics, aborts or leaks memory cannot take the worker down.
+    /// A process exiting before answering fails the attempt, and is replaced
+    /// like processes reaching their job or memory limit.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The command, size and limits of the pool.
+    ///
+    /// # Returns
+    ///
+    /// The `QueueTriggerService` running the jobs in the sandbox processes.
+    pub fn with_sandbox(mut self, options: SandboxOptions) -> Self {
+        self.sandbox = Some(options);
+        self
+    }
+
+    /// Processes each job by POSTing it to an HTTP endpoint instead of printing it.
+    ///
+    /// A 2xx response completes the job. A 429 or 503 response puts the job
+    /// back with a delay of at most `MAX_BACKOFF_MS`, from the `Retry-After`
+    /// header or doubling from `BACKOFF_BASE_MS`, and pauses the worker for that
+    /// delay. Any other response fails the job.
+    ///
+    /// # Arguments
+    ///
+    /// * `webhook` - The endpoint the jobs are forwarded to.
+    ///
+    /// # Returns
+    ///
+    /// The `QueueTriggerService` forwarding the jobs.
+    #[cfg(feature = "webhook")]
+    pub fn with_webhook(mut self, webhook: WebhookService) -> Self {
+        self.webhook = Some(Arc::new(webhook));
+        self
+    }
+
+    /// Starts the trigger to monitor the queue for messages.
+    ///
+    /// This function spawns a new asynchronous task that blocks on the queue
+    /// until a message arrives, then processes it. The queue service of the
+    /// configured backend is used; with Redis, a dedicated connection is used
+    /// for the blocking fetch so it does not stall other commands. Without a
+    /// job timeout set on the trigger, the one configured for the queue in
+    /// `queue_timeouts_ms` applies.
+    ///
+    /// # Arguments
+    ///
+    /// * `block_timeout_milli` - The maximum time in milliseconds to block waiting for a job,
+    ///   also used as the back-off delay after a fetch error.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating whether the configuration is valid and the queue service could be created,
+    /// before the task is spawned.
+    pub async fn start(&self, block_timeout_milli: u64) -> RedisResult<()> {
+        let config = ConfigService::load()?;
+        let mut queue_service = config.queue_service_for(&self.queue_name).await?;
+        let mut trigger = self.clone();
+        if trigger.job_timeout.is_none() {
+            trigger.job_timeout = config.job_timeout_for(&self.queue_name);
+        }
         task::spawn(async move {
-            loop {
-                match queue_service.get_next_job(&queue_name).await {
-                    Ok(Some(job_json)) => {
-                        if job_json.len() > 0 {
-
-                            let job: JobData = serde_json::from_str(&job_json[0]).unwrap();
-                            let timestamp = &job_json[1];
-    
-                            println!(
-                                "queue:\t\t{}\ntimestamp:\t{}\nid:\t\t{}\nmessage:\t{}\n",
-                                queue_name, job.timestamp, job.id, job.message
-                            );
+            trigger.run(&mut *queue_service, block_timeout_milli, None).await;
+        });
+        Ok(())
+    }
+
+    /// Processes the jobs of the queue with the given queue service until enough jobs were processed.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_service` - The queue service to fetch the jobs from.
+    /// * `block_timeout_milli` - The maximum time in milliseconds to block waiting for a job,
+    ///   also used as the back-off delay after a fetch error.
+    /// * `max_jobs` - The number of jobs to process before returning, `None` processes jobs forever.
+    ///
+    /// # Returns
+    ///
+    /// The number of processed jobs.
+    pub async fn run(&self, queue_service: &mut dyn QueueServiceTrait, block_timeout_milli: u64, max_jobs: Option<u64>) -> u64 {
+        if let Some(sandbox) = &self.sandbox {
+            return self.run_sandboxed(queue_service, sandbox, block_timeout_milli, max_jobs).await;
+        }
+        let queue_name = &self.queue_name;
+        let block_timeout_secs = block_timeout_milli as f64 / 1000.0;
+        #[cfg(feature = "prometheus")]
+        let prometheus = &self.prometheus;
+        #[cfg(feature = "prometheus")]
+        if let Some(prometheus) = prometheus {
+            prometheus.set_concurrency(queue_name, 1);
+        }
+        let mut processed = 0;
+        let mut backoff_streak = 0;
+        while max_jobs.is_none_or(|max_jobs| processed < max_jobs) {
+            #[cfg(feature = "prometheus")]
+            self.report_queue_depth(queue_service).await;
+
+            match queue_service.wait_for_next_job(queue_name, block_timeout_secs).await {
+                Ok(Some(job_json)) => {
+                    if let Some(job) = parse_job(&job_json) {
+                        let process = async {
+                            let started_at = Instant::now();
+                            #[cfg(feature = "prometheus")]
+                            if let Some(prometheus) = prometheus {
+                                prometheus.job_started(queue_name);
+                            }
+
+                            self.log_status(queue_service, &job, "active").await;
+                            let outcome = self.process_interruptible(queue_service, &job).await;
+                            self.finish_job(queue_service, &job, outcome, started_at, backoff_streak).await
+                        };
+                        #[cfg(feature = "tracing")]
+                        let process = tracing::Instrument::instrument(process, crate::tracing_service::job_span(queue_name, &job));
+                        match process.await {
+                            Some(delay) => {
+                                backoff_streak += 1;
+                                tokio::time::sleep(delay).await;
+                            }
+                            None => backoff_streak = 0,
                         }
+                        processed += 1;
                     }
-                    Ok(None) => {
-                        println!("No jobs found. Retrying...");
+                }
+                Ok(None) => {}
+                Err(e) => fetch_failed(&e, block_timeout_milli).await,
+            }
+        }
+        processed
+    }
+
+    /// Processes the jobs of the queue in the sandbox processes, as many at a time as there are processes.
+    async fn run_sandboxed(
+        &self,
+        queue_service: &mut dyn QueueServiceTrait,
+        options: &SandboxOptions,
+        block_timeout_milli: u64,
+        max_jobs: Option<u64>,
+    ) -> u64 {
+        let queue_name = &self.queue_name;
+        let block_timeout_secs = block_timeout_milli as f64 / 1000.0;
+        let mut pool = SandboxPool::new(options.clone());
+        #[cfg(feature = "prometheus")]
+        if let Some(prometheus) = &self.prometheus {
+            prometheus.set_concurrency(queue_name, pool.size() as u64);
+        }
+        let (events_tx, mut events) = mpsc::unbounded_channel();
+        let mut in_flight: FuturesUnordered<SandboxedJob> = FuturesUnordered::new();
+        let mut tokens: HashMap<String, CancellationToken> = HashMap::new();
+        let mut cancel_poll = tokio::time::interval(Duration::from_millis(CANCEL_POLL_INTERVAL_MS));
+        cancel_poll.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let (mut started, mut processed) = (0, 0);
+        loop {
+            // Hand out jobs while processes are idle, blocking only when no job is in flight.
+            while in_flight.len() < pool.size() && max_jobs.is_none_or(|max_jobs| started < max_jobs) {
+                #[cfg(feature = "prometheus")]
+                self.report_queue_depth(queue_service).await;
+                let fetched = if in_flight.is_empty() {
+                    match queue_service.wait_for_next_job(queue_name, block_timeout_secs).await {
+                        Ok(fetched) => fetched,
+                        Err(e) => {
+                            fetch_failed(&e, block_timeout_milli).await;
+                            break;
+                        }
                     }
+                } else {
+                    // An empty queue is reported as an error, and fetch errors surface once the jobs in flight are done.
+                    queue_service.get_next_job(queue_name).await.ok().flatten()
+                };
+                let Some(job_json) = fetched else { break };
+                let Some(job) = parse_job(&job_json) else { continue };
+                started += 1;
+                #[cfg(feature = "prometheus")]
+                if let Some(prometheus) = &self.prometheus {
+                    prometheus.job_started(queue_name);
+                }
+                self.log_status(queue_service, &job, "active").await;
+                let started_at = Instant::now();
+                if self.take_cancellation(queue_service, &job).await {
+                    self.finish_job(queue_service, &job, JobOutcome::Cancelled, started_at, 0).await;
+                    processed += 1;
+                    continue;
+                }
+                if let Some(outcome) = unreadable(&job) {
+                    self.finish_job(queue_service, &job, outcome, started_at, 0).await;
+                    processed += 1;
+                    continue;
+                }
+                let mut child = match pool.checkout() {
+                    Ok(child) => child,
                     Err(e) => {
-                        if e.is_timeout() || e.is_connection_dropped() || e.is_connection_refusal()
-                        {
-                            eprintln!("Error fetching job: {}. Reconnecting...", e);
-                        } else {
-                            eprintln!("Error fetching job: {}. Retrying...", e);
+                        let outcome = JobOutcome::failed(format!("cannot start sandbox process {:?}: {}", options.command, e));
+                        self.finish_job(queue_service, &job, outcome, started_at, 0).await;
+                        processed += 1;
+                        tokio::time::sleep(Duration::from_millis(block_timeout_milli)).await;
+                        continue;
+                    }
+                };
+                let (queue_name, events_tx) = (queue_name.clone(), events_tx.clone());
+                let token = CancellationToken::new();
+                tokens.insert(job.id.clone(), token.clone());
+                let timeout = self.timeout_of(&job);
+                #[cfg(feature = "tracing")]
+                let span = crate::tracing_service::job_span(&queue_name, &job);
+                let process = async move {
+                    let processed = tokio::select! {
+                        outcome = child.process(&queue_name, &job, &events_tx) => Ok(outcome),
+                        interruption = deadline(timeout) => Err(interruption),
+                        _ = token.cancelled() => Err(Interruption::Cancelled),
+                    };
+                    let outcome = match processed {
+                        Ok(SandboxOutcome::Completed) => JobOutcome::Completed,
+                        Ok(SandboxOutcome::Failed { reason, stack }) => JobOutcome::Failed { reason, stack },
+                        Ok(SandboxOutcome::Crashed(reason)) => JobOutcome::failed(reason),
+                        Err(interruption) => {
+                            child.kill().await;
+                            interruption.outcome()
+                        }
+                    };
+                    (child, job, outcome, started_at)
+                };
+                #[cfg(feature = "tracing")]
+                let process = tracing::Instrument::instrument(process, span);
+                in_flight.push(Box::pin(process));
+            }
+
+            if in_flight.is_empty() {
+                if max_jobs.is_some_and(|max_jobs| started >= max_jobs) {
+                    return processed;
+                }
+                continue;
+            }
+            let can_start = in_flight.len() < pool.size() && max_jobs.is_none_or(|max_jobs| started < max_jobs);
+            tokio::select! {
+                Some(event) = events.recv() => self.apply_sandbox_event(queue_service, event).await,
+                Some((child, job, outcome, started_at)) = in_flight.next() => {
+                    while let Ok(event) = events.try_recv() {
+                        self.apply_sandbox_event(queue_service, event).await;
+                    }
+                    pool.checkin(child);
+                    tokens.remove(&job.id);
+                    self.finish_job(queue_service, &job, outcome, started_at, 0).await;
+                    processed += 1;
+                }
+                _ = cancel_poll.tick() => {
+                    for (job_id, token) in &tokens {
+                        match queue_service.take_job_cancellation(queue_name, job_id).await {
+                            Ok(true) => token.cancel(),
+                            Ok(false) => {}
+                            Err(e) => diagnostic!(warn, "Error checking cancellation of job {}: {}", job_id, e),
                         }
                     }
                 }
-                tokio::time::sleep(tokio::time::Duration::from_millis(refresh_time_milli)).await;
+                _ = tokio::time::sleep(Duration::from_millis(block_timeout_milli)), if can_start => {}
             }
-        });
+        }
     }
+
+    /// Stores the progress or log line reported by a sandbox process.
+    async fn apply_sandbox_event(&self, queue_service: &mut dyn QueueServiceTrait, event: SandboxEvent) {
+        let result = match &event.message {
+            SandboxMessage::Progress { progress } => {
+                queue_service.update_job_progress(&self.queue_name, &event.job_id, (*progress).min(100)).await
+            }
+            SandboxMessage::Log { line } => queue_service.job_log(&self.queue_name, &event.job_id, line).await.map(|_| ()),
+            SandboxMessage::Completed | SandboxMessage::Failed { .. } => Ok(()),
+        };
+        if let Err(e) = result {
+            diagnostic!(warn, "Error storing sandbox report of job {}: {}", event.job_id, e);
+        }
+    }
+
+    /// Applies the outcome of a job: completes it, fails or retries it, or postpones it.
+    ///
+    /// # Returns
+    ///
+    /// The delay to wait before fetching the next job when the job was postponed.
+    async fn finish_job(
+        &self,
+        queue_service: &mut dyn QueueServiceTrait,
+        job: &JobData,
+        outcome: JobOutcome,
+        started_at: Instant,
+        backoff_streak: u32,
+    ) -> Option<Duration> {
+        let queue_name = &self.queue_name;
+        let processing_ms = started_at.elapsed().as_millis() as u64;
+        #[cfg(feature = "prometheus")]
+        if let Some(prometheus) = &self.prometheus {
+            prometheus.job_finished(queue_name);
+        }
+        let (status, reason, delay) = match outcome {
+            JobOutcome::Completed => {
+                #[cfg(feature = "prometheus")]
+                if let Some(prometheus) = &self.prometheus {
+                    prometheus.observe_completed(queue_name, started_at.elapsed().as_secs_f64());
+                }
+                if let Err(e) = queue_service.mark_completed(queue_name, job, processing_ms).await {
+                    diagnostic!(error, "Error completing job {}: {}", job.id, e);
+                }
+                self.log_status(queue_service, job, "completed").await;
+                ("completed", None, None)
+            }
+            JobOutcome::Failed { reason, stack } => {
+                let status = self.fail_job(queue_service, job.clone(), &reason, stack, true, processing_ms).await;
+                (status, Some(reason), None)
+            }
+            JobOutcome::Cancelled => {
+                let reason = "cancelled".to_string();
+                let status = self.fail_job(queue_service, job.clone(), &reason, None, false, processing_ms).await;
+                (status, Some(reason), None)
+            }
+            JobOutcome::Backoff { retry_after, reason } => {
+                let delay = retry_after
+                    .map(|retry_after| retry_after.min(Duration::from_millis(MAX_BACKOFF_MS)))
+                    .unwrap_or_else(|| backoff_delay(backoff_streak));
+                self.postpone_job(queue_service, job.clone(), delay, &reason).await;
+                ("postponed", Some(reason), Some(delay))
+            }
+        };
+        self.report(job, status, reason.as_deref(), processing_ms);
+        delay
+    }
+
+    /// Processes a job, stopping it once it times out or its cancellation is requested.
+    ///
+    /// The cancellation is checked before the job starts, then every
+    /// `CANCEL_POLL_INTERVAL_MS` while it runs.
+    async fn process_interruptible(&self, queue_service: &mut dyn QueueServiceTrait, job: &JobData) -> JobOutcome {
+        if self.take_cancellation(queue_service, job).await {
+            return JobOutcome::Cancelled;
+        }
+        if let Some(outcome) = unreadable(job) {
+            return outcome;
+        }
+        let token = CancellationToken::new();
+        let mut log_lines = Vec::new();
+        let outcome = {
+            let process = self.process_job(job, token.clone(), &mut log_lines);
+            tokio::pin!(process);
+            tokio::select! {
+                outcome = &mut process => outcome,
+                interruption = self.watch_job(queue_service, job) => {
+                    token.cancel();
+                    // The handler may still return, to stop cleanly, but the interruption decides the outcome.
+                    let _ = tokio::time::timeout(Duration::from_millis(CANCEL_GRACE_MS), &mut process).await;
+                    interruption.outcome()
+                }
+            }
+        };
+        for line in log_lines {
+            if let Err(e) = queue_service.job_log(&self.queue_name, &job.id, &line).await {
+                diagnostic!(warn, "Error logging stderr of job {}: {}", job.id, e);
+            }
+        }
+        outcome
+    }
+
+    /// Waits until a job times out or its cancellation is requested.
+    async fn watch_job(&self, queue_service: &mut dyn QueueServiceTrait, job: &JobData) -> Interruption {
+        let timeout = self.timeout_of(job);
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            let mut wait = Duration::from_millis(CANCEL_POLL_INTERVAL_MS);
+            if let Some(deadline) = deadline {
+                wait = wait.min(deadline.saturating_duration_since(Instant::now()));
+            }
+            tokio::time::sleep(wait).await;
+            if let (Some(timeout), Some(deadline)) = (timeout, deadline) {
+                if Instant::now() >= deadline {
+                    return Interruption::TimedOut(timeout);
+                }
+            }
+            if self.take_cancellation(queue_service, job).await {
+                return Interruption::Cancelled;
+            }
+        }
+    }
+
+    /// Consumes the cancellation request of a job, reporting backend errors as no request.
+    async fn take_cancellation(&self, queue_service: &mut dyn QueueServiceTrait, job: &JobData) -> bool {
+        match queue_service.take_job_cancellation(&self.queue_name, &job.id).await {
+            Ok(cancelled) => cancelled,
+            Err(e) => {
+                diagnostic!(warn, "Error checking cancellation of job {}: {}", job.id, e);
+                false
+            }
+        }
+    }
+
+    /// Returns the maximum time an attempt to process the job may take: its own timeout, or the worker's.
+    fn timeout_of(&self, job: &JobData) -> Option<Duration> {
+        job.timeout.map(Duration::from_millis).or(self.job_timeout)
+    }
+
+    /// Processes a job with the handler, by forwarding it to the webhook or by running the
+    /// exec command when one is set, or by printing it otherwise.
+    ///
+    /// The lines the job writes on stderr are added to `log_lines`.
+    async fn process_job(&self, job: &JobData, token: CancellationToken, log_lines: &mut Vec<String>) -> JobOutcome {
+        if let Some(handler) = &self.handler {
+            return match AssertUnwindSafe(handler(job.clone(), token)).catch_unwind().await {
+                Ok(Ok(())) => JobOutcome::Completed,
+                Ok(Err(reason)) => JobOutcome::failed(reason),
+                Err(payload) => JobOutcome::failed(panic_reason(payload.as_ref())),
+            };
+        }
+        #[cfg(feature = "webhook")]
+        if let Some(webhook) = &self.webhook {
+            return tokio::select! {
+                outcome = webhook.forward(&self.queue_name, job) => match outcome {
+                    WebhookOutcome::Delivered => JobOutcome::Completed,
+                    WebhookOutcome::Backoff { retry_after, reason } => JobOutcome::Backoff { retry_after, reason },
+                    WebhookOutcome::Failed(reason) => JobOutcome::failed(reason),
+                },
+                _ = token.cancelled() => JobOutcome::Cancelled,
+            };
+        }
+        match &self.exec {
+            Some(command) => match self.exec_job(command, job, &token, log_lines).await {
+                Ok(()) => JobOutcome::Completed,
+                Err(reason) => JobOutcome::failed(reason),
+            },
+            None => JobOutcome::Completed,
+        }
+    }
+
+    /// Writes the report of a processed job on stdout, in the configured format.
+    fn report(&self, job: &JobData, status: &str, reason: Option<&str>, duration_ms: u64) {
+        if self.quiet {
+            return;
+        }
+        let report = JobReport {
+            queue: &self.queue_name,
+            id: &job.id,
+            timestamp: &job.timestamp,
+            message: &job.message,
+            attempt: job.attempt(),
+            status,
+            reason,
+            duration_ms,
+        };
+        let mut output = match self.output {
+            OutputFormat::Text => format!(
+                "queue:\t\t{}\ntimestamp:\t{}\nid:\t\t{}\nmessage:\t{}\n",
+                report.queue, report.timestamp, report.id, report.message
+            ),
+            OutputFormat::Json => serde_json::to_string_pretty(&report).unwrap_or_default(),
+            OutputFormat::Jsonl => serde_json::to_string(&report).unwrap_or_default(),
+        };
+        if self.output == OutputFormat::Text && status != "completed" {
+            output.push_str(&format!("status:\t\t{}\nreason:\t\t{}\n", status, reason.unwrap_or_default()));
+        }
+        let mut stdout = std::io::stdout().lock();
+        if let Err(e) = writeln!(stdout, "{}", output).and_then(|_| stdout.flush()) {
+            diagnostic!(error, "Error writing the report of job {}: {}", job.id, e);
+        }
+    }
+
+    /// Runs the exec command for a job, adding its stderr lines to `log_lines`, and kills it once the token is cancelled.
+    async fn exec_job(&self, command: &str, job: &JobData, token: &CancellationToken, log_lines: &mut Vec<String>) -> Result<(), String> {
+        let mut child = shell(command)
+            .env("JOB_ID", &job.id)
+            .env("QUEUE", &self.queue_name)
+            .env("ATTEMPT", job.attempt().to_string())
+            .stdin(Stdio::piped())
+            .stdout(self.command_stdout())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("cannot run {:?}: {}", command, e))?;
+
+        // Written while waiting so a command that does not read its stdin cannot block the worker.
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let payload = job.message.clone();
+        let write_payload = async move {
+            let _ = stdin.write_all(payload.as_bytes()).await;
+        };
+        let output = tokio::select! {
+            (_, output) = async { tokio::join!(write_payload, child.wait_with_output()) } => output,
+            // Dropping the child kills it.
+            _ = token.cancelled() => return Err(format!("command {:?} killed", command)),
+        };
+        let output = output.map_err(|e| format!("cannot wait for {:?}: {}", command, e))?;
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        log_lines.extend(stderr.lines().map(str::to_string));
+        if output.status.success() {
+            Ok(())
+        } else if stderr.trim().is_empty() {
+            Err(format!("command {}", output.status))
+        } else {
+            Err(format!("command {}: {}", output.status, stderr.trim()))
+        }
+    }
+
+    /// Returns where the exec command writes its stdout: the worker's stdout for text
+    /// reports, stderr when stdout is reserved for JSON reports or silenced.
+    fn command_stdout(&self) -> Stdio {
+        if self.output == OutputFormat::Text && !self.quiet {
+            Stdio::inherit()
+        } else {
+            Stdio::from(std::io::stderr())
+        }
+    }
+
+    /// Reports the number of waiting and failed jobs of the queue to Prometheus.
+    #[cfg(feature = "prometheus")]
+    async fn report_queue_depth(&self, queue_service: &mut dyn QueueServiceTrait) {
+        if let Some(prometheus) = &self.prometheus {
+            for state in [JobState::Waiting, JobState::Failed] {
+                if let Ok(count) = queue_service.count_jobs_by_state(&self.queue_name, state).await {
+                    prometheus.set_queue_depth(&self.queue_name, state.as_str(), count);
+                }
+            }
+        }
+    }
+
+    /// Writes a status change of a job to the queue log: `active`, `completed`, `postponed`, `retrying` or `failed`.
+    async fn log_status(&self, queue_service: &mut dyn QueueServiceTrait, job: &JobData, status: &str) {
+        if let Err(e) = queue_service.log_job_status(&self.queue_name, job, status).await {
+            diagnostic!(warn, "Error logging status of job {}: {}", job.id, e);
+        }
+    }
+
+    /// Adds a job back to the queue, available after the given delay, without consuming an attempt.
+    async fn postpone_job(&self, queue_service: &mut dyn QueueServiceTrait, job: JobData, delay: Duration, reason: &str) {
+        let queue_name = &self.queue_name;
+        let delay_secs = delay.as_millis().div_ceil(1000) as i64;
+        diagnostic!(warn, "Job {} postponed by {}s: {}", job.id, delay_secs, reason);
+        if let Err(e) = queue_service.job_log(queue_name, &job.id, &format!("postponed by {}s: {}", delay_secs, reason)).await {
+            diagnostic!(warn, "Error logging postponement of job {}: {}", job.id, e);
+        }
+        self.log_status(queue_service, &job, "postponed").await;
+        let id = job.id.clone();
+        if let Err(e) = queue_service.add_job_after(queue_name, job, delay_secs).await {
+            diagnostic!(error, "Error postponing job {}: {}", id, e);
+        }
+    }
+
+    /// Records a failed attempt in the failure history of the job, then adds the job back to the
+    /// queue if it is retryable and has retries left, or moves it to the failed queue otherwise.
+    ///
+    /// # Returns
+    ///
+    /// The new status of the job, `retrying` or `failed`.
+    async fn fail_job(
+        &self,
+        queue_service: &mut dyn QueueServiceTrait,
+        mut job: JobData,
+        reason: &str,
+        stack: Option<String>,
+        retryable: bool,
+        duration_ms: u64,
+    ) -> &'static str {
+        let queue_name = &self.queue_name;
+        let attempt = job.attempt();
+        job.record_failure(reason, stack, duration_ms);
+        if let Err(e) = queue_service.job_log(queue_name, &job.id, &format!("attempt {} failed: {}", attempt, reason)).await {
+            diagnostic!(warn, "Error logging failure of job {}: {}", job.id, e);
+        }
+
+        let retry = retryable && attempt <= job.retries.unwrap_or(0);
+        #[cfg(feature = "prometheus")]
+        if let Some(prometheus) = &self.prometheus {
+            if retry {
+                prometheus.observe_retry(queue_name);
+            } else {
+                prometheus.observe_failed(queue_name);
+            }
+        }
+        let status = if retry { "retrying" } else { "failed" };
+        diagnostic!(warn, "Job {} failed on attempt {}, {}: {}", job.id, attempt, status, reason);
+        self.log_status(queue_service, &job, status).await;
+        let id = job.id.clone();
+        let result = if retry {
+            // The job was delayed when it was first added, a retry is due right away.
+            job.delay = None;
+            queue_service.add_job(queue_name, job).await
+        } else {
+            queue_service.move_to_failed(queue_name, job).await
+        };
+        if let Err(e) = result {
+            diagnostic!(error, "Error moving job {} after a failure: {}", id, e);
+        }
+        status
+    }
+}
+
+/// Deserializes a fetched job, skipping it with a warning if it is malformed, since it cannot be failed without its ID.
+fn parse_job(job_json: &[String]) -> Option<JobData> {
+    match serde_json::from_str(job_json.first()?) {
+        Ok(job) => Some(job),
+        Err(e) => {
+            diagnostic!(warn, "Skipping malformed job: {}", e);
+            None
+        }
+    }
+}
+
+/// Fails a job whose message is still compressed, because the queue service could not decompress it.
+fn unreadable(job: &JobData) -> Option<JobOutcome> {
+    let compression = job.compression?;
+    Some(JobOutcome::failed(format!("cannot decompress the {} message of the job", compression.as_str())))
+}
+
+/// Returns the delay before retrying a job after the given number of consecutive back-off requests.
+fn backoff_delay(streak: u32) -> Duration {
+    let delay_ms = BACKOFF_BASE_MS.saturating_mul(1u64.checked_shl(streak).unwrap_or(u64::MAX));
+    Duration::from_millis(delay_ms.min(MAX_BACKOFF_MS))
+}
+
+/// Resolves once the timeout elapses, never if there is none.
+async fn deadline(timeout: Option<Duration>) -> Interruption {
+    match timeout {
+        Some(timeout) => {
+            tokio::time::sleep(timeout).await;
+            Interruption::TimedOut(timeout)
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Reports an error fetching a job, then waits before the next attempt.
+async fn fetch_failed(e: &RedisError, block_timeout_milli: u64) {
+    if e.is_timeout() || e.is_connection_dropped() || e.is_connection_refusal() {
+        diagnostic!(warn, "Error fetching job: {}. Reconnecting...", e);
+    } else {
+        diagnostic!(warn, "Error fetching job: {}. Retrying...", e);
+    }
+    tokio::time::sleep(Duration::from_millis(block_timeout_milli)).await;
+}
+
+/// Builds the command running a command line with the platform shell.
+pub(crate) fn shell(command: &str) -> Command {
+    #[cfg(windows)]
+    let (program, flag) = ("cmd", "/C");
+    #[cfg(not(windows))]
+    let (program, flag) = ("sh", "-c");
+    let mut shell = Command::new(program);
+    shell.arg(flag).arg(command);
+    shell
 }
@@ -0,0 +1,408 @@
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::io::{self, BufRead, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout};
+use tokio::sync::mpsc::UnboundedSender;
+use crate::job_model::JobData;
+
+/// Environment variable set to `1` in sandbox processes, so a binary can tell it was started as one.
+pub const SANDBOX_ENV: &str = "BULLMQ_SANDBOX";
+
+/// Time given to a recycled process to exit once its stdin is closed, before it is killed.
+pub const RETIRE_GRACE_MS: u64 = 1_000;
+
+/// A job sent to a sandbox process, as one JSON line on its stdin.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SandboxRequest {
+    /// The name of the queue the job belongs to.
+    pub queue: String,
+    /// The attempt being made, starting at 1.
+    pub attempt: u32,
+    /// The job to process.
+    pub job: JobData,
+}
+
+/// A message written by a sandbox process, as one JSON line on its stdout.
+///
+/// A process writes any number of `progress` and `log` messages while it
+/// processes a job, then exactly one `completed` or `failed` message.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SandboxMessage {
+    /// Sets the progress of the job, between 0 and 100.
+    Progress { progress: u32 },
+    /// Appends a line to the job log.
+    Log { line: String },
+    /// The job completed.
+    Completed,
+    /// The job failed for the given reason, with an optional stack trace or context.
+    Failed {
+        reason: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        stack: Option<String>,
+    },
+}
+
+/// A `progress` or `log` message of a sandbox process, with the job it relates to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SandboxEvent {
+    /// The ID of the job being processed.
+    pub job_id: String,
+    /// The message.
+    pub message: SandboxMessage,
+}
+
+/// How a sandbox process handled a job.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SandboxOutcome {
+    /// The process reported the job as completed.
+    Completed,
+    /// The process reported the job as failed for the given reason, with an optional stack trace or context.
+    Failed { reason: String, stack: Option<String> },
+    /// The process exited, or closed its stdout, before reporting the outcome of the job.
+    Crashed(String),
+}
+
+/// The options of a pool of sandbox processes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SandboxOptions {
+    /// The command line starting a sandbox process, run with `sh -c`.
+    pub command: String,
+    /// The number of processes, which is also the number of jobs processed concurrently.
+    pub processes: usize,
+    /// The number of jobs after which a process is replaced, never if unset.
+    pub max_jobs_per_process: Option<u64>,
+    /// The resident memory, in bytes, above which a process is replaced after its job, never if unset.
+    ///
+    /// The memory of a process is only read on Linux.
+    pub max_memory_bytes: Option<u64>,
+}
+
+impl SandboxOptions {
+    /// Creates the options of a pool of one process, never recycled.
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - The command line starting a sandbox process.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of `SandboxOptions`.
+    pub fn new(command: &str) -> Self {
+        Self { command: command.to_string(), processes: 1, max_jobs_per_process: None, max_memory_bytes: None }
+    }
+
+    /// Sets the number of processes, at least one.
+    pub fn with_processes(mut self, processes: usize) -> Self {
+        self.processes = processes.max(1);
+        self
+    }
+
+    /// Replaces each process after it processed the given number of jobs.
+    pub fn with_max_jobs_per_process(mut self, max_jobs: u64) -> Self {
+        self.max_jobs_per_process = Some(max_jobs.max(1));
+        self
+    }
+
+    /// Replaces each process whose resident memory exceeds the given number of bytes after a job.
+    pub fn with_max_memory_bytes(mut self, max_memory_bytes: u64) -> Self {
+        self.max_memory_bytes = Some(max_memory_bytes);
+        self
+    }
+}
+
+/// A running sandbox process.
+pub struct SandboxChild {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    stdout: Lines<BufReader<ChildStdout>>,
+    jobs: u64,
+    crashed: bool,
+}
+
+impl SandboxChild {
+    /// Starts a sandbox process.
+    ///
+    /// Its stdin and stdout carry the protocol, its stderr is the worker's.
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - The command line starting the process.
+    ///
+    /// # Returns
+    ///
+    /// An `io::Result` containing the process.
+    pub fn spawn(command: &str) -> io::Result<Self> {
+        let mut child = crate::queue_trigger_service::shell(command)
+            .env(SANDBOX_ENV, "1")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()?;
+        let stdin = child.stdin.take();
+        let stdout = BufReader::new(child.stdout.take().expect("stdout is piped")).lines();
+        Ok(Self { child, stdin, stdout, jobs: 0, crashed: false })
+    }
+
+    /// Returns the OS identifier of the process, unless it exited.
+    pub fn id(&self) -> Option<u32> {
+        self.child.id()
+    }
+
+    /// Returns the number of jobs the process was given.
+    pub fn jobs(&self) -> u64 {
+        self.jobs
+    }
+
+    /// Sends a job to the process and waits for its outcome.
+    ///
+    /// `progress` and `log` messages are forwarded as they arrive, and lines
+    /// that are not protocol messages are forwarded as log lines.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job` - The job to process.
+    /// * `events` - Where the `progress` and `log` messages are forwarded.
+    ///
+    /// # Returns
+    ///
+    /// How the process handled the job.
+    pub async fn process(&mut self, queue_name: &str, job: &JobData, events: &UnboundedSender<SandboxEvent>) -> SandboxOutcome {
+        self.jobs += 1;
+        let request = SandboxRequest { queue: queue_name.to_string(), attempt: job.attempt(), job: job.clone() };
+        let mut line = match serde_json::to_string(&request) {
+            Ok(line) => line,
+            Err(e) => return SandboxOutcome::Failed { reason: format!("cannot serialize job: {}", e), stack: None },
+        };
+        line.push('\n');
+        let sent = match self.stdin.as_mut() {
+            Some(stdin) => stdin.write_all(line.as_bytes()).await.and(stdin.flush().await),
+            None => Err(io::Error::from(io::ErrorKind::BrokenPipe)),
+        };
+        if sent.is_err() {
+            return self.crash().await;
+        }
+
+        loop {
+            let line = match self.stdout.next_line().await {
+                Ok(Some(line)) => line,
+                Ok(None) | Err(_) => return self.crash().await,
+            };
+            let message = match serde_json::from_str::<SandboxMessage>(&line) {
+                Ok(SandboxMessage::Completed) => return SandboxOutcome::Completed,
+                Ok(SandboxMessage::Failed { reason, stack }) => return SandboxOutcome::Failed { reason, stack },
+                Ok(message) => message,
+                Err(_) if line.trim().is_empty() => continue,
+                Err(_) => SandboxMessage::Log { line },
+            };
+            let _ = events.send(SandboxEvent { job_id: job.id.clone(), message });
+        }
+    }
+
+    /// Marks the process as crashed and describes how it exited.
+    async fn crash(&mut self) -> SandboxOutcome {
+        self.crashed = true;
+        self.stdin = None;
+        match tokio::time::timeout(Duration::from_millis(RETIRE_GRACE_MS), self.child.wait()).await {
+            Ok(Ok(status)) => SandboxOutcome::Crashed(format!("sandbox process exited with {}", status)),
+            Ok(Err(e)) => SandboxOutcome::Crashed(format!("sandbox process failed: {}", e)),
+            Err(_) => {
+                let _ = self.child.start_kill();
+                SandboxOutcome::Crashed("sandbox process closed its stdout".to_string())
+            }
+        }
+    }
+
+    /// Kills the process while it processes a job, so it is replaced once given back to the pool.
+    pub async fn kill(&mut self) {
+        self.crashed = true;
+        self.stdin = None;
+        let _ = self.child.kill().await;
+    }
+
+    /// Returns why the process must be replaced before its next job, if it must.
+    fn recycle_reason(&self, options: &SandboxOptions) -> Option<String> {
+        if self.crashed {
+            return Some("it crashed or was killed".to_string());
+        }
+        if options.max_jobs_per_process.is_some_and(|max_jobs| self.jobs >= max_jobs) {
+            return Some(format!("it processed {} jobs", self.jobs));
+        }
+        let max_memory = options.max_memory_bytes?;
+        let memory = resident_memory(self.id()?)?;
+        (memory > max_memory).then(|| format!("it uses {} bytes of memory", memory))
+    }
+
+    /// Closes the stdin of the process, so it can exit, and kills it if it is still running after a grace period.
+    fn retire(mut self) {
+        self.stdin = None;
+        tokio::spawn(async move {
+            if tokio::time::timeout(Duration::from_millis(RETIRE_GRACE_MS), self.child.wait()).await.is_err() {
+                let _ = self.child.kill().await;
+            }
+        });
+    }
+}
+
+/// A pool of sandbox processes, started on demand and replaced when they crash or reach their limits.
+pub struct SandboxPool {
+    options: SandboxOptions,
+    idle: Vec<SandboxChild>,
+}
+
+impl SandboxPool {
+    /// Creates an empty pool.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The options of the pool.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of `SandboxPool`.
+    pub fn new(options: SandboxOptions) -> Self {
+        Self { options, idle: Vec::new() }
+    }
+
+    /// Returns the number of processes of the pool.
+    pub fn size(&self) -> usize {
+        self.options.processes
+    }
+
+    /// Takes an idle process from the pool, starting a new one if none is idle.
+    ///
+    /// # Returns
+    ///
+    /// An `io::Result` containing the process.
+    pub fn checkout(&mut self) -> io::Result<SandboxChild> {
+        match self.idle.pop() {
+            Some(child) => Ok(child),
+            None => SandboxChild::spawn(&self.options.command),
+        }
+    }
+
+    /// Gives a process back to the pool after a job, retiring it if it crashed or reached its limits.
+    pub fn checkin(&mut self, child: SandboxChild) {
+        match child.recycle_reason(&self.options) {
+            Some(reason) => {
+                diagnostic!(info, "Replacing sandbox process {:?} because {}", child.id(), reason);
+                child.retire();
+            }
+            None => self.idle.push(child),
+        }
+    }
+}
+
+/// Describes the payload of a caught handler panic.
+pub(crate) fn panic_reason(payload: &(dyn Any + Send)) -> String {
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string());
+    format!("handler panicked: {}", message)
+}
+
+/// Returns the resident memory of a process, in bytes.
+#[cfg(target_os = "linux")]
+fn resident_memory(pid: u32) -> Option<u64> {
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kilobytes: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+/// Returns the resident memory of a process, in bytes.
+#[cfg(not(target_os = "linux"))]
+fn resident_memory(_pid: u32) -> Option<u64> {
+    None
+}
+
+/// Lets a sandboxed job handler report its progress and log lines.
+pub struct SandboxContext<'a> {
+    output: &'a mut dyn Write,
+}
+
+impl SandboxContext<'_> {
+    /// Sets the progress of the job, between 0 and 100.
+    pub fn progress(&mut self, progress: u32) -> io::Result<()> {
+        write_message(self.output, &SandboxMessage::Progress { progress })
+    }
+
+    /// Appends a line to the job log.
+    pub fn log(&mut self, line: &str) -> io::Result<()> {
+        write_message(self.output, &SandboxMessage::Log { line: line.to_string() })
+    }
+}
+
+fn write_message(output: &mut dyn Write, message: &SandboxMessage) -> io::Result<()> {
+    let line = serde_json::to_string(message).map_err(io::Error::other)?;
+    writeln!(output, "{}", line)?;
+    output.flush()
+}
+
+/// Serves jobs in a sandbox process, reading them from stdin and writing their outcome on stdout.
+///
+/// Call it from the `main` of the sandbox binary. A handler returning an error
+/// or panicking fails the job; the handler must not write on stdout, which
+/// carries the protocol, and can write diagnostics on stderr.
+///
+/// # Arguments
+///
+/// * `handler` - The function processing each job.
+///
+/// # Returns
+///
+/// An `io::Result` containing the number of jobs processed once stdin is closed.
+pub fn serve<F>(handler: F) -> io::Result<u64>
+where
+    F: FnMut(&SandboxRequest, &mut SandboxContext) -> Result<(), String>,
+{
+    serve_with(io::stdin().lock(), io::stdout().lock(), handler)
+}
+
+/// Serves jobs read from the given input, writing their outcome on the given output.
+///
+/// # Arguments
+///
+/// * `input` - Where the jobs are read from, one JSON line each.
+/// * `output` - Where the protocol messages are written.
+/// * `handler` - The function processing each job.
+///
+/// # Returns
+///
+/// An `io::Result` containing the number of jobs processed once the input is closed.
+pub fn serve_with<R, W, F>(input: R, mut output: W, mut handler: F) -> io::Result<u64>
+where
+    R: BufRead,
+    W: Write,
+    F: FnMut(&SandboxRequest, &mut SandboxContext) -> Result<(), String>,
+{
+    let mut processed = 0;
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let message = match serde_json::from_str::<SandboxRequest>(&line) {
+            Ok(request) => {
+                let mut context = SandboxContext { output: &mut output };
+                match panic::catch_unwind(AssertUnwindSafe(|| handler(&request, &mut context))) {
+                    Ok(Ok(())) => SandboxMessage::Completed,
+                    Ok(Err(reason)) => SandboxMessage::Failed { reason, stack: None },
+                    Err(payload) => SandboxMessage::Failed { reason: panic_reason(payload.as_ref()), stack: None },
+                }
+            }
+            Err(e) => SandboxMessage::Failed { reason: format!("invalid job: {}", e), stack: None },
+        };
+        write_message(&mut output, &message)?;
+        processed += 1;
+    }
+    Ok(processed)
+}
@@ -0,0 +1,899 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use redis::{RedisError, RedisResult};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use crate::job_model::{JobData, JobState};
+use crate::log_model::{list_range, LogEntry, LogRange};
+use crate::log_service::{DEFAULT_MAX_JOB_LOG_LINES, DEFAULT_MAX_LOG_ENTRIES};
+use crate::metrics_model::{histogram_bucket, minute_bucket, MetricKind, MetricPoint, MetricsRange};
+use crate::metrics_service::DEFAULT_METRICS_RETENTION_MINUTES;
+use crate::queue_service::CANCELLATION_TTL_SECS;
+use crate::QueueServiceTrait;
+
+/// Default interval at which `wait_for_next_job` polls the database.
+pub const DEFAULT_POLL_INTERVAL_MS: u64 = 100;
+
+/// Schema of the database, created when it is opened.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS jobs (
+    queue TEXT NOT NULL,
+    state TEXT NOT NULL,
+    member TEXT NOT NULL,
+    score INTEGER NOT NULL,
+    job_id TEXT NOT NULL,
+    PRIMARY KEY (queue, state, member)
+);
+CREATE INDEX IF NOT EXISTS jobs_by_score ON jobs (queue, state, score, member);
+CREATE INDEX IF NOT EXISTS jobs_by_id ON jobs (queue, job_id);
+CREATE TABLE IF NOT EXISTS progress (
+    queue TEXT NOT NULL,
+    job_id TEXT NOT NULL,
+    progress INTEGER NOT NULL,
+    PRIMARY KEY (queue, job_id)
+);
+CREATE TABLE IF NOT EXISTS logs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    queue TEXT NOT NULL,
+    job_id TEXT,
+    entry TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS logs_by_queue ON logs (queue, id);
+CREATE TABLE IF NOT EXISTS job_logs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    queue TEXT NOT NULL,
+    job_id TEXT NOT NULL,
+    line TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS job_logs_by_job ON job_logs (queue, job_id, id);
+CREATE TABLE IF NOT EXISTS metrics (
+    queue TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    minute INTEGER NOT NULL,
+    field TEXT NOT NULL,
+    value INTEGER NOT NULL,
+    PRIMARY KEY (queue, kind, minute, field)
+);
+CREATE TABLE IF NOT EXISTS paused (
+    queue TEXT NOT NULL PRIMARY KEY
+);
+CREATE TABLE IF NOT EXISTS cancelled (
+    queue TEXT NOT NULL,
+    job_id TEXT NOT NULL,
+    requested_at INTEGER NOT NULL,
+    PRIMARY KEY (queue, job_id)
+);
+";
+
+/// Converts a SQLite error into a `RedisError`, the error type of `QueueServiceTrait`.
+fn sqlite_error(e: rusqlite::Error) -> RedisError {
+    RedisError::from((redis::ErrorKind::IoError, "SQLite error", e.to_string()))
+}
+
+/// Implementation of `QueueServiceTrait` storing queues in an embedded SQLite database.
+///
+/// Jobs are stored with the same semantics as `QueueService`: they are scored
+/// with the time they become ready, their enqueue time plus their delay, and
+/// only fetched once that time has passed, in the order they became ready and
+/// then in the order they were added.
+/// Logs and metrics are capped like in Redis. Every write is committed to the
+/// write-ahead log with `synchronous = FULL`, so acknowledged jobs survive a
+/// crash, and several processes can share the same database file.
+#[derive(Clone)]
+pub struct SqliteQueueService {
+    con: Arc<Mutex<Connection>>,
+    max_entries: usize,
+    max_job_lines: usize,
+    retention_minutes: u64,
+    poll_interval: Duration,
+}
+
+impl SqliteQueueService {
+    /// Opens the SQLite database at the given path, creating it if needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the database file.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the new instance of `SqliteQueueService`.
+    pub fn open(path: impl AsRef<Path>) -> RedisResult<Self> {
+        let con = Connection::open(path).map_err(sqlite_error)?;
+        con.pragma_update(None, "journal_mode", "WAL").map_err(sqlite_error)?;
+        Self::with_connection(con)
+    }
+
+    /// Opens a private in-memory SQLite database, lost when the service is dropped.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the new instance of `SqliteQueueService`.
+    pub fn open_in_memory() -> RedisResult<Self> {
+        Self::with_connection(Connection::open_in_memory().map_err(sqlite_error)?)
+    }
+
+    fn with_connection(con: Connection) -> RedisResult<Self> {
+        con.pragma_update(None, "synchronous", "FULL").map_err(sqlite_error)?;
+        con.busy_timeout(Duration::from_secs(5)).map_err(sqlite_error)?;
+        con.execute_batch(SCHEMA).map_err(sqlite_error)?;
+        Ok(Self {
+            con: Arc::new(Mutex::new(con)),
+            max_entries: DEFAULT_MAX_LOG_ENTRIES,
+            max_job_lines: DEFAULT_MAX_JOB_LOG_LINES,
+            retention_minutes: DEFAULT_METRICS_RETENTION_MINUTES,
+            poll_interval: Duration::from_millis(DEFAULT_POLL_INTERVAL_MS),
+        })
+    }
+
+    /// Sets the maximum number of lines kept in each job log.
+    pub fn with_max_job_log_lines(mut self, max_lines: usize) -> Self {
+        self.max_job_lines = max_lines.max(1);
+        self
+    }
+
+    /// Sets the maximum number of entries kept in each queue log.
+    pub fn with_max_log_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries.max(1);
+        self
+    }
+
+    /// Sets the number of minutes metrics data points are kept for.
+    pub fn with_metrics_retention(mut self, retention_minutes: u64) -> Self {
+        self.retention_minutes = retention_minutes.max(1);
+        self
+    }
+
+    /// Sets the interval at which `wait_for_next_job` polls the database.
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval.max(Duration::from_millis(1));
+        self
+    }
+
+    /// Runs a closure on the database connection on the blocking thread pool.
+    async fn run<T, F>(&self, f: F) -> RedisResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> rusqlite::Result<T> + Send + 'static,
+    {
+        let con = Arc::clone(&self.con);
+        tokio::task::spawn_blocking(move || {
+            let mut con = con.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            f(&mut con)
+        })
+        .await
+        .map_err(|e| RedisError::from((redis::ErrorKind::IoError, "SQLite task failed", e.to_string())))?
+        .map_err(sqlite_error)
+    }
+
+    /// Adds serialized jobs to the given state in one transaction, replacing the score of identical jobs.
+    ///
+    /// Waiting jobs are scored by the time they become ready, after `delay_secs` or their own delay
+    /// when it is `None`, failed jobs by the time they failed.
+    async fn push_jobs(&self, queue_name: &str, state: JobState, jobs: &[JobData], delay_secs: Option<i64>) -> RedisResult<()> {
+        let now = Utc::now().timestamp();
+        let delay = |job: &JobData| match state {
+            JobState::Waiting => delay_secs.or(job.delay).unwrap_or(0),
+            JobState::Failed => 0,
+        };
+        let rows: Vec<(String, i64, String)> = jobs
+            .iter()
+            .map(|job| (serde_json::to_string(job).unwrap(), now + delay(job), job.id.clone()))
+            .collect();
+        let queue_name = queue_name.to_string();
+        self.run(move |con| {
+            let tx = con.transaction()?;
+            for (member, score, job_id) in rows {
+                tx.execute(
+                    "INSERT INTO jobs (queue, state, member, score, job_id) VALUES (?1, ?2, ?3, ?4, ?5)
+                     ON CONFLICT (queue, state, member) DO UPDATE SET score = excluded.score",
+                    params![queue_name, state.as_str(), member, score, job_id],
+                )?;
+            }
+            tx.commit()
+        })
+        .await
+    }
+
+    /// Pops the oldest ready waiting job, unless the queue is paused.
+    async fn pop_job(&self, queue_name: &str) -> RedisResult<Option<Vec<String>>> {
+        let queue_name = queue_name.to_string();
+        let now = Utc::now().timestamp();
+        self.run(move |con| {
+            con.query_row(
+                "DELETE FROM jobs WHERE rowid = (
+                     SELECT rowid FROM jobs WHERE queue = ?1 AND state = 'waiting' AND score <= ?2
+                     AND NOT EXISTS (SELECT 1 FROM paused WHERE queue = ?1)
+                     ORDER BY score, rowid LIMIT 1
+                 ) RETURNING member, score",
+                params![queue_name, now],
+                |row| Ok(vec![row.get::<_, String>(0)?, row.get::<_, i64>(1)?.to_string()]),
+            )
+            .optional()
+        })
+        .await
+    }
+
+    /// Records a count, and a duration for histogram metrics, in the current minute.
+    async fn record_metric(&self, queue_name: &str, kind: MetricKind, duration_ms: Option<u64>) -> RedisResult<()> {
+        let minute = minute_bucket(Utc::now().timestamp());
+        let oldest = minute_bucket(Utc::now().timestamp() - (self.retention_minutes * 60) as i64);
+        let queue_name = queue_name.to_string();
+        self.run(move |con| {
+            let tx = con.transaction()?;
+            let mut fields = vec!["count".to_string()];
+            fields.extend(duration_ms.map(|duration_ms| histogram_bucket(duration_ms).to_string()));
+            for field in fields {
+                tx.execute(
+                    "INSERT INTO metrics (queue, kind, minute, field, value) VALUES (?1, ?2, ?3, ?4, 1)
+                     ON CONFLICT (queue, kind, minute, field) DO UPDATE SET value = value + 1",
+                    params![queue_name, kind.as_str(), minute, field],
+                )?;
+            }
+            tx.execute("DELETE FROM metrics WHERE queue = ?1 AND minute < ?2", params![queue_name, oldest])?;
+            tx.commit()
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl QueueServiceTrait for SqliteQueueService {
+    /// Adds a job to the specified queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to add the job to.
+    /// * `job` - The job data to add to the queue.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn add_job(&mut self, queue_name: &str, job: JobData) -> RedisResult<()> {
+        self.push_jobs(queue_name, JobState::Waiting, &[job], None).await
+    }
+
+    /// Adds a job to the specified queue, available after the given delay rather than its own.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to add the job to.
+    /// * `job` - The job data to add to the queue.
+    /// * `delay_secs` - The delay in seconds before the job can be fetched.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn add_job_after(&mut self, queue_name: &str, job: JobData, delay_secs: i64) -> RedisResult<()> {
+        self.push_jobs(queue_name, JobState::Waiting, &[job], Some(delay_secs)).await
+    }
+
+    /// Retrieves the next job from the specified queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to retrieve the job from.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the job JSON string and its score, `None` while the queue is paused,
+    /// or an error if the queue is empty.
+    async fn get_next_job(&mut self, queue_name: &str) -> RedisResult<Option<Vec<String>>> {
+        if self.is_paused(queue_name).await? {
+            return Ok(None);
+        }
+        match self.pop_job(queue_name).await? {
+            Some(next_job) => Ok(Some(next_job)),
+            None => Err(RedisError::from((redis::ErrorKind::TypeError, "No job found"))),
+        }
+    }
+
+    /// Waits for the next job from the specified queue, polling the database.
+    /// A paused queue yields no job until it is resumed.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to retrieve the job from.
+    /// * `timeout_secs` - The maximum time in seconds to block, `0` blocks forever.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the job JSON string and its score, or `None` on timeout.
+    async fn wait_for_next_job(&mut self, queue_name: &str, timeout_secs: f64) -> RedisResult<Option<Vec<String>>> {
+        let deadline = (timeout_secs > 0.0)
+            .then(|| tokio::time::Instant::now() + Duration::from_secs_f64(timeout_secs));
+        loop {
+            if let Some(next_job) = self.pop_job(queue_name).await? {
+                return Ok(Some(next_job));
+            }
+            let next_poll = tokio::time::Instant::now() + self.poll_interval;
+            match deadline {
+                Some(deadline) if deadline <= tokio::time::Instant::now() => return Ok(None),
+                Some(deadline) => tokio::time::sleep_until(next_poll.min(deadline)).await,
+                None => tokio::time::sleep_until(next_poll).await,
+            }
+        }
+    }
+
+    /// Counts the number of jobs in the specified queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to count the jobs in.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the number of jobs in the queue.
+    async fn count_jobs(&mut self, queue_name: &str) -> RedisResult<u64> {
+        self.count_jobs_by_state(queue_name, JobState::Waiting).await
+    }
+
+    /// Counts the number of jobs of the specified queue in the given state.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to count the jobs in.
+    /// * `state` - The state of the jobs to count.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the number of jobs in the given state.
+    async fn count_jobs_by_state(&mut self, queue_name: &str, state: JobState) -> RedisResult<u64> {
+        let queue_name = queue_name.to_string();
+        self.run(move |con| {
+            con.query_row(
+                "SELECT COUNT(*) FROM jobs WHERE queue = ?1 AND state = ?2",
+                params![queue_name, state.as_str()],
+                |row| row.get(0),
+            )
+        })
+        .await
+    }
+
+    /// Updates the progress of a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    /// * `progress` - The progress value to update.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn update_job_progress(&mut self, queue_name: &str, job_id: &str, progress: u32) -> RedisResult<()> {
+        let (queue_name, job_id) = (queue_name.to_string(), job_id.to_string());
+        self.run(move |con| {
+            con.execute(
+                "INSERT INTO progress (queue, job_id, progress) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (queue, job_id) DO UPDATE SET progress = excluded.progress",
+                params![queue_name, job_id, progress],
+            )
+            .map(|_| ())
+        })
+        .await
+    }
+
+    /// Retrieves the progress of a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the progress value of the job, or `None` if none was set.
+    async fn get_job_progress(&mut self, queue_name: &str, job_id: &str) -> RedisResult<Option<u32>> {
+        let (queue_name, job_id) = (queue_name.to_string(), job_id.to_string());
+        self.run(move |con| {
+            con.query_row(
+                "SELECT progress FROM progress WHERE queue = ?1 AND job_id = ?2",
+                params![queue_name, job_id],
+                |row| row.get(0),
+            )
+            .optional()
+        })
+        .await
+    }
+
+    /// Moves a job to the failed queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job` - The job data to move to the failed queue.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn move_to_failed(&mut self, queue_name: &str, job: JobData) -> RedisResult<()> {
+        self.push_jobs(queue_name, JobState::Failed, &[job], None).await?;
+        self.record_metric(queue_name, MetricKind::Failed, None).await
+    }
+
+    /// Logs the status of a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job` - A reference to the job data.
+    /// * `status` - The status message to log.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn log_job_status(&mut self, queue_name: &str, job: &JobData, status: &str) -> RedisResult<()> {
+        let entry = LogEntry::job_status(job, status);
+        let entry_json = serde_json::to_string(&entry).map_err(|e| {
+            RedisError::from((redis::ErrorKind::TypeError, "Failed to serialize log entry", e.to_string()))
+        })?;
+        let (queue_name, job_id, max_entries) = (queue_name.to_string(), job.id.clone(), self.max_entries as i64);
+        self.run(move |con| {
+            let tx = con.transaction()?;
+            tx.execute(
+                "INSERT INTO logs (queue, job_id, entry) VALUES (?1, ?2, ?3)",
+                params![queue_name, job_id, entry_json],
+            )?;
+            tx.execute(
+                "DELETE FROM logs WHERE queue = ?1 AND id <= (
+                     SELECT id FROM logs WHERE queue = ?1 ORDER BY id DESC LIMIT 1 OFFSET ?2
+                 )",
+                params![queue_name, max_entries],
+            )?;
+            tx.commit()
+        })
+        .await
+    }
+
+    /// Marks a job as completed and records its throughput and latency metrics.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job` - A reference to the job data.
+    /// * `processing_ms` - The time spent processing the job, in milliseconds.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn mark_completed(&mut self, queue_name: &str, job: &JobData, processing_ms: u64) -> RedisResult<()> {
+        self.record_metric(queue_name, MetricKind::Completed, None).await?;
+        self.record_metric(queue_name, MetricKind::ProcessingTime, Some(processing_ms)).await?;
+        if let Some(wait_ms) = job.wait_time_ms(processing_ms) {
+            self.record_metric(queue_name, MetricKind::WaitTime, Some(wait_ms)).await?;
+        }
+        Ok(())
+    }
+
+    /// Retrieves the per-minute data points of a queue metric.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to read the metrics of.
+    /// * `kind` - The metric to read.
+    /// * `range` - The time range to read, clamped to the retention period and to the current minute.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing one data point per minute, oldest first.
+    async fn get_metrics(&mut self, queue_name: &str, kind: MetricKind, range: MetricsRange) -> RedisResult<Vec<MetricPoint>> {
+        let range = range.clamp_to_retention((self.retention_minutes * 60) as i64);
+        let minutes = range.minutes();
+        let (first, last) = match (minutes.first(), minutes.last()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return Ok(Vec::new()),
+        };
+        let queue_name = queue_name.to_string();
+        let rows: Vec<(i64, String, u64)> = self
+            .run(move |con| {
+                let mut statement = con.prepare(
+                    "SELECT minute, field, value FROM metrics
+                     WHERE queue = ?1 AND kind = ?2 AND minute BETWEEN ?3 AND ?4",
+                )?;
+                let rows = statement.query_map(params![queue_name, kind.as_str(), first, last], |row| {
+                    Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+                })?;
+                rows.collect()
+            })
+            .await?;
+
+        let mut fields: HashMap<i64, HashMap<String, u64>> = HashMap::new();
+        for (minute, field, value) in rows {
+            fields.entry(minute).or_default().insert(field, value);
+        }
+        Ok(minutes
+            .into_iter()
+            .map(|timestamp| MetricPoint::from_fields(timestamp, kind, &fields.remove(&timestamp).unwrap_or_default()))
+            .collect())
+    }
+
+    /// Reads entries from the specified queue's log, most recent first.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to read the log of.
+    /// * `job_id` - Only return the entries of this job, if set.
+    /// * `range` - The page of matching entries to return.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the matching entries.
+    async fn read_logs(&mut self, queue_name: &str, job_id: Option<&str>, range: LogRange) -> RedisResult<Vec<LogEntry>> {
+        let (queue_name, job_id) = (queue_name.to_string(), job_id.map(str::to_string));
+        let raw: Vec<String> = self
+            .run(move |con| {
+                let mut statement = con.prepare(
+                    "SELECT entry FROM logs WHERE queue = ?1 AND (?2 IS NULL OR job_id = ?2)
+                     ORDER BY id DESC LIMIT ?3 OFFSET ?4",
+                )?;
+                let rows = statement.query_map(
+                    params![queue_name, job_id, range.limit as i64, range.offset as i64],
+                    |row| row.get(0),
+                )?;
+                rows.collect()
+            })
+            .await?;
+        Ok(raw.iter().map(|entry| LogEntry::parse(entry)).collect())
+    }
+
+    /// Appends a line to the log of a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    /// * `line` - The line to append.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the number of lines in the job log.
+    async fn job_log(&mut self, queue_name: &str, job_id: &str, line: &str) -> RedisResult<u64> {
+        let (queue_name, job_id, line) = (queue_name.to_string(), job_id.to_string(), line.to_string());
+        let max_job_lines = self.max_job_lines as i64;
+        self.run(move |con| {
+            let tx = con.transaction()?;
+            tx.execute(
+                "INSERT INTO job_logs (queue, job_id, line) VALUES (?1, ?2, ?3)",
+                params![queue_name, job_id, line],
+            )?;
+            tx.execute(
+                "DELETE FROM job_logs WHERE queue = ?1 AND job_id = ?2 AND id <= (
+                     SELECT id FROM job_logs WHERE queue = ?1 AND job_id = ?2 ORDER BY id DESC LIMIT 1 OFFSET ?3
+                 )",
+                params![queue_name, job_id, max_job_lines],
+            )?;
+            let count: u64 = tx.query_row(
+                "SELECT COUNT(*) FROM job_logs WHERE queue = ?1 AND job_id = ?2",
+                params![queue_name, job_id],
+                |row| row.get(0),
+            )?;
+            tx.commit()?;
+            Ok(count)
+        })
+        .await
+    }
+
+    /// Reads lines from the log of a job, oldest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    /// * `start` - The index of the first line, negative values count from the end.
+    /// * `end` - The index of the last line, inclusive, negative values count from the end.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the requested lines.
+    async fn get_job_logs(&mut self, queue_name: &str, job_id: &str, start: isize, end: isize) -> RedisResult<Vec<String>> {
+        let (queue_name, job_id) = (queue_name.to_string(), job_id.to_string());
+        let lines: Vec<String> = self
+            .run(move |con| {
+                let mut statement =
+                    con.prepare("SELECT line FROM job_logs WHERE queue = ?1 AND job_id = ?2 ORDER BY id")?;
+                let rows = statement.query_map(params![queue_name, job_id], |row| row.get(0))?;
+                rows.collect()
+            })
+            .await?;
+        Ok(list_range(&lines, start, end))
+    }
+
+    /// Removes a job from the queue and its failed queue, together with its progress and log lines.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the job was found in a queue.
+    async fn remove_job(&mut self, queue_name: &str, job_id: &str) -> RedisResult<bool> {
+        let (queue_name, job_id) = (queue_name.to_string(), job_id.to_string());
+        self.run(move |con| {
+            let tx = con.transaction()?;
+            let removed = tx.execute("DELETE FROM jobs WHERE queue = ?1 AND job_id = ?2", params![queue_name, job_id])?;
+            tx.execute("DELETE FROM progress WHERE queue = ?1 AND job_id = ?2", params![queue_name, job_id])?;
+            tx.execute("DELETE FROM job_logs WHERE queue = ?1 AND job_id = ?2", params![queue_name, job_id])?;
+            tx.execute("DELETE FROM cancelled WHERE queue = ?1 AND job_id = ?2", params![queue_name, job_id])?;
+            tx.commit()?;
+            Ok(removed > 0)
+        })
+        .await
+    }
+
+    /// Adds several jobs to the specified queue in a single transaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to add the jobs to.
+    /// * `jobs` - The jobs to add, in order.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn add_jobs(&mut self, queue_name: &str, jobs: Vec<JobData>) -> RedisResult<()> {
+        self.push_jobs(queue_name, JobState::Waiting, &jobs, None).await
+    }
+
+    /// Lists the jobs of the specified queue in the given state.
+    ///
+    /// Waiting jobs are listed in the order they will be fetched, the delayed
+    /// ones last by due time. Failed jobs are listed most recent first.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to list the jobs of.
+    /// * `state` - The state of the jobs to list.
+    /// * `offset` - The number of jobs to skip.
+    /// * `limit` - The maximum number of jobs to return.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the jobs.
+    async fn list_jobs(&mut self, queue_name: &str, state: JobState, offset: usize, limit: usize) -> RedisResult<Vec<JobData>> {
+        let queue_name = queue_name.to_string();
+        let (offset, limit) = (offset.min(i64::MAX as usize) as i64, limit.min(i64::MAX as usize) as i64);
+        let order = match state {
+            JobState::Waiting => "score, rowid",
+            JobState::Failed => "score DESC, member DESC",
+        };
+        let members: Vec<String> = self
+            .run(move |con| {
+                let mut statement = con.prepare(&format!(
+                    "SELECT member FROM jobs WHERE queue = ?1 AND state = ?2
+                     ORDER BY {} LIMIT ?3 OFFSET ?4",
+                    order
+                ))?;
+                let rows = statement.query_map(params![queue_name, state.as_str(), limit, offset], |row| row.get(0))?;
+                rows.collect()
+            })
+            .await?;
+        Ok(members.iter().filter_map(|member| serde_json::from_str(member).ok()).collect())
+    }
+
+    /// Moves a failed job back to the waiting jobs, ready to be fetched immediately.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the failed job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the job was found in the failed queue.
+    async fn retry_job(&mut self, queue_name: &str, job_id: &str) -> RedisResult<bool> {
+        let (queue_name, job_id) = (queue_name.to_string(), job_id.to_string());
+        let now = Utc::now().timestamp();
+        self.run(move |con| {
+            let tx = con.transaction()?;
+            tx.execute(
+                "INSERT INTO jobs (queue, state, member, score, job_id)
+                 SELECT queue, 'waiting', member, ?3, job_id FROM jobs WHERE queue = ?1 AND state = 'failed' AND job_id = ?2
+                 ON CONFLICT (queue, state, member) DO UPDATE SET score = excluded.score",
+                params![queue_name, job_id, now],
+            )?;
+            let retried = tx.execute(
+                "DELETE FROM jobs WHERE queue = ?1 AND state = 'failed' AND job_id = ?2",
+                params![queue_name, job_id],
+            )?;
+            tx.commit()?;
+            Ok(retried > 0)
+        })
+        .await
+    }
+
+    /// Removes a failed job from the failed jobs of a queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job failed in.
+    /// * `job_id` - The ID of the failed job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the job was found in the failed queue.
+    async fn remove_failed_job(&mut self, queue_name: &str, job_id: &str) -> RedisResult<bool> {
+        let (queue_name, job_id) = (queue_name.to_string(), job_id.to_string());
+        self.run(move |con| {
+            let removed = con.execute(
+                "DELETE FROM jobs WHERE queue = ?1 AND state = 'failed' AND job_id = ?2",
+                params![queue_name, job_id],
+            )?;
+            Ok(removed > 0)
+        })
+        .await
+    }
+
+    /// Pauses the specified queue, workers stop fetching its jobs until it is resumed.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to pause.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn pause_queue(&mut self, queue_name: &str) -> RedisResult<()> {
+        let queue_name = queue_name.to_string();
+        self.run(move |con| {
+            con.execute("INSERT OR IGNORE INTO paused (queue) VALUES (?1)", params![queue_name])
+                .map(|_| ())
+        })
+        .await
+    }
+
+    /// Resumes a paused queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to resume.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn resume_queue(&mut self, queue_name: &str) -> RedisResult<()> {
+        let queue_name = queue_name.to_string();
+        self.run(move |con| {
+            con.execute("DELETE FROM paused WHERE queue = ?1", params![queue_name])
+                .map(|_| ())
+        })
+        .await
+    }
+
+    /// Tells whether the specified queue is paused.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the queue is paused.
+    async fn is_paused(&mut self, queue_name: &str) -> RedisResult<bool> {
+        let queue_name = queue_name.to_string();
+        self.run(move |con| {
+            con.query_row(
+                "SELECT EXISTS (SELECT 1 FROM paused WHERE queue = ?1)",
+                params![queue_name],
+                |row| row.get(0),
+            )
+        })
+        .await
+    }
+
+    /// Removes the jobs in the given state older than a grace period, together with their progress and log lines.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to clean.
+    /// * `state` - The state of the jobs to remove.
+    /// * `grace_secs` - The minimum age, in seconds, of the removed jobs.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the number of removed jobs.
+    async fn clean_jobs(&mut self, queue_name: &str, state: JobState, grace_secs: u64) -> RedisResult<u64> {
+        let queue_name = queue_name.to_string();
+        let max_score = Utc::now().timestamp() - grace_secs as i64;
+        self.run(move |con| {
+            let tx = con.transaction()?;
+            let job_ids: Vec<String> = {
+                let mut statement = tx.prepare(
+                    "DELETE FROM jobs WHERE queue = ?1 AND state = ?2 AND score <= ?3 RETURNING job_id",
+                )?;
+                let rows = statement.query_map(params![queue_name, state.as_str(), max_score], |row| row.get(0))?;
+                rows.collect::<rusqlite::Result<_>>()?
+            };
+            for job_id in &job_ids {
+                tx.execute("DELETE FROM progress WHERE queue = ?1 AND job_id = ?2", params![queue_name, job_id])?;
+                tx.execute("DELETE FROM job_logs WHERE queue = ?1 AND job_id = ?2", params![queue_name, job_id])?;
+            }
+            tx.commit()?;
+            Ok(job_ids.len() as u64)
+        })
+        .await
+    }
+
+    /// Lists the queues holding any data.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the names of the queues, sorted.
+    async fn list_queues(&mut self) -> RedisResult<Vec<String>> {
+        self.run(|con| {
+            let mut statement = con.prepare(
+                "SELECT queue FROM jobs UNION SELECT queue FROM progress UNION SELECT queue FROM logs \
+                 UNION SELECT queue FROM job_logs UNION SELECT queue FROM metrics UNION SELECT queue FROM paused \
+                 UNION SELECT queue FROM cancelled ORDER BY queue",
+            )?;
+            let rows = statement.query_map([], |row| row.get(0))?;
+            rows.collect()
+        })
+        .await
+    }
+
+    /// Requests the cancellation of a job, dropping requests older than `CANCELLATION_TTL_SECS`.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn cancel_job(&mut self, queue_name: &str, job_id: &str) -> RedisResult<()> {
+        let (queue_name, job_id) = (queue_name.to_string(), job_id.to_string());
+        let now = Utc::now().timestamp();
+        self.run(move |con| {
+            let tx = con.transaction()?;
+            tx.execute(
+                "DELETE FROM cancelled WHERE requested_at <= ?1",
+                params![now - CANCELLATION_TTL_SECS as i64],
+            )?;
+            tx.execute(
+                "INSERT OR REPLACE INTO cancelled (queue, job_id, requested_at) VALUES (?1, ?2, ?3)",
+                params![queue_name, job_id, now],
+            )?;
+            tx.commit()
+        })
+        .await
+    }
+
+    /// Consumes the cancellation request of a job, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the job's cancellation was requested.
+    async fn take_job_cancellation(&mut self, queue_name: &str, job_id: &str) -> RedisResult<bool> {
+        let (queue_name, job_id) = (queue_name.to_string(), job_id.to_string());
+        let min_requested_at = Utc::now().timestamp() - CANCELLATION_TTL_SECS as i64;
+        self.run(move |con| {
+            let removed = con.execute(
+                "DELETE FROM cancelled WHERE queue = ?1 AND job_id = ?2 AND requested_at > ?3",
+                params![queue_name, job_id, min_requested_at],
+            )?;
+            Ok(removed > 0)
+        })
+        .await
+    }
+
+    /// Returns a new handle on the same queues.
+    ///
+    /// # Returns
+    ///
+    /// The boxed copy of the queue service.
+    fn clone_box(&self) -> Box<dyn QueueServiceTrait> {
+        Box::new(self.clone())
+    }
+}
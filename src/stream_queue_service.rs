@@ -0,0 +1,836 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use redis::streams::{
+    StreamAutoClaimOptions, StreamAutoClaimReply, StreamId, StreamPendingCountReply, StreamRangeReply, StreamReadOptions, StreamReadReply,
+};
+use redis::{AsyncCommands, RedisError, RedisResult};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use crate::config_service::ConfigService;
+use crate::connection_service::RedisConnection;
+use crate::job_model::{JobData, JobState};
+use crate::log_model::{LogEntry, LogRange};
+use crate::metrics_model::{MetricKind, MetricPoint, MetricsRange};
+use crate::queue_keys::{QueueKeys, DEFAULT_KEY_PREFIX};
+use crate::queue_service::{range_stop, wait_while_paused, QueueService};
+use crate::QueueServiceTrait;
+
+/// Default name of the consumer group reading the job streams.
+pub const DEFAULT_STREAM_GROUP: &str = "bullmq";
+
+/// Default time, in milliseconds, after which a delivered but unacknowledged job is claimed by another consumer.
+pub const DEFAULT_CLAIM_IDLE_MS: u64 = 30_000;
+
+/// The field of a stream entry holding the job JSON.
+const JOB_FIELD: &str = "job";
+
+/// The field of the job hash holding the stream entry ID of a fetched job.
+const ENTRY_FIELD: &str = "entry";
+
+/// Maximum number of due delayed jobs moved to the stream at once.
+const PROMOTE_BATCH_SIZE: usize = 100;
+
+/// Moves the due jobs of the delayed sorted set (`KEYS[1]`) to the stream (`KEYS[2]`).
+const PROMOTE_SCRIPT: &str = r"
+local due = redis.call('ZRANGEBYSCORE', KEYS[1], '-inf', ARGV[1], 'LIMIT', 0, ARGV[2])
+for _, job in ipairs(due) do
+    redis.call('XADD', KEYS[2], '*', ARGV[3], job)
+    redis.call('ZREM', KEYS[1], job)
+end
+return #due
+";
+
+/// Acknowledges and deletes the stream entry (`KEYS[1]`) recorded in the job hash (`KEYS[2]`) when the job was fetched.
+const ACK_SCRIPT: &str = r"
+local id = redis.call('HGET', KEYS[2], ARGV[2])
+if not id then
+    return 0
+end
+redis.call('XACK', KEYS[1], ARGV[1], id)
+redis.call('XDEL', KEYS[1], id)
+redis.call('HDEL', KEYS[2], ARGV[2])
+return 1
+";
+
+/// Returns the default consumer name, unique per host and process.
+fn default_consumer() -> String {
+    let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "bullmq".to_string());
+    format!("{}-{}", host, std::process::id())
+}
+
+/// Implementation of `QueueServiceTrait` built on a Redis Stream read by a consumer group.
+///
+/// Jobs are appended with `XADD` and fetched in insertion order with
+/// `XREADGROUP`, so they stay in the pending entries list of the group until
+/// `mark_completed` or `move_to_failed` acknowledges them with `XACK`. Jobs
+/// left pending longer than the claim idle time, e.g. by a crashed worker, are
+/// replayed to another consumer with `XAUTOCLAIM`. Priorities are ignored and
+/// delayed jobs wait in a sorted set until they are due. The failed queue,
+/// progress, logs and metrics are stored like with `QueueService`.
+///
+/// `get_next_job` and `wait_for_next_job` return the job JSON and its stream entry ID. The entry ID
+/// is also recorded in the job hash, so any handle, in any process, can acknowledge the job.
+#[derive(Clone)]
+pub struct StreamQueueService {
+    con: RedisConnection,
+    blocking_con: Option<RedisConnection>,
+    inner: QueueService,
+    key_prefix: String,
+    group: String,
+    consumer: String,
+    claim_idle_ms: u64,
+    groups: Arc<Mutex<HashSet<String>>>,
+}
+
+impl StreamQueueService {
+    /// Creates a new `StreamQueueService`.
+    ///
+    /// # Arguments
+    ///
+    /// * `con` - The Redis connection.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of `StreamQueueService`.
+    pub fn new(con: impl Into<RedisConnection>) -> Self {
+        let con = con.into();
+        Self {
+            inner: QueueService::new(con.clone()),
+            con,
+            blocking_con: None,
+            key_prefix: DEFAULT_KEY_PREFIX.to_string(),
+            group: DEFAULT_STREAM_GROUP.to_string(),
+            consumer: default_consumer(),
+            claim_idle_ms: DEFAULT_CLAIM_IDLE_MS,
+            groups: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// Creates a new `StreamQueueService` connected according to the given configuration.
+    ///
+    /// The configured key prefix and stream settings are applied, and a second
+    /// connection is reserved for blocking reads.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The Redis configuration.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the new instance of `StreamQueueService`.
+    pub async fn from_config(config: &ConfigService) -> RedisResult<Self> {
+        let mut service = Self::new(config.connect().await?)
+            .with_blocking_connection(config.connect_blocking().await?)
+            .with_key_prefix(&config.key_prefix)
+            .with_group(&config.stream.group)
+            .with_claim_idle_ms(config.stream.claim_idle_ms);
+        if let Some(consumer) = &config.stream.consumer {
+            service = service.with_consumer(consumer);
+        }
+        Ok(service)
+    }
+
+    /// Sets the prefix of every key of the service.
+    pub fn with_key_prefix(mut self, key_prefix: &str) -> Self {
+        self.key_prefix = key_prefix.to_string();
+        self.inner = self.inner.with_key_prefix(key_prefix);
+        self
+    }
+
+    /// Sets the name of the consumer group, workers of the same group share the jobs.
+    pub fn with_group(mut self, group: &str) -> Self {
+        self.group = group.to_string();
+        self
+    }
+
+    /// Sets the name of this consumer within the group, unique per host and process by default.
+    pub fn with_consumer(mut self, consumer: &str) -> Self {
+        self.consumer = consumer.to_string();
+        self
+    }
+
+    /// Sets the time, in milliseconds, after which an unacknowledged job is replayed to another consumer.
+    pub fn with_claim_idle_ms(mut self, claim_idle_ms: u64) -> Self {
+        self.claim_idle_ms = claim_idle_ms.max(1);
+        self
+    }
+
+    /// Sets a dedicated connection used for blocking reads.
+    pub fn with_blocking_connection(mut self, con: impl Into<RedisConnection>) -> Self {
+        self.blocking_con = Some(con.into());
+        self
+    }
+
+    fn keys(&self, queue_name: &str) -> QueueKeys {
+        QueueKeys::with_prefix(&self.key_prefix, queue_name)
+    }
+
+    fn groups(&self) -> MutexGuard<'_, HashSet<String>> {
+        self.groups.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Creates the consumer group of a queue, once per service.
+    async fn ensure_group(&mut self, queue_name: &str) -> RedisResult<()> {
+        if self.groups().contains(queue_name) {
+            return Ok(());
+        }
+        let result: RedisResult<()> = self.con.xgroup_create_mkstream(self.keys(queue_name).stream(), &self.group, "0").await;
+        match result {
+            Err(e) if e.code() != Some("BUSYGROUP") => return Err(e),
+            _ => {}
+        }
+        self.groups().insert(queue_name.to_string());
+        Ok(())
+    }
+
+    /// Forgets the consumer group of a queue when Redis reports it missing, so it is created again.
+    fn check_group<T>(&self, queue_name: &str, result: RedisResult<T>) -> RedisResult<T> {
+        if let Err(e) = &result {
+            if e.code() == Some("NOGROUP") {
+                self.groups().remove(queue_name);
+            }
+        }
+        result
+    }
+
+    /// Moves the due delayed jobs of a queue to its stream.
+    async fn promote_delayed(&mut self, queue_name: &str) -> RedisResult<()> {
+        let keys = self.keys(queue_name);
+        let _: u64 = redis::Script::new(PROMOTE_SCRIPT)
+            .key(keys.delayed())
+            .key(keys.stream())
+            .arg(Utc::now().timestamp_millis())
+            .arg(PROMOTE_BATCH_SIZE)
+            .arg(JOB_FIELD)
+            .invoke_async(&mut self.con)
+            .await?;
+        Ok(())
+    }
+
+    /// Records the stream entry of a fetched job in its job hash so it can be acknowledged, and returns it as `[json, entry ID]`.
+    async fn take_entry(&mut self, queue_name: &str, entry: StreamId) -> RedisResult<Option<Vec<String>>> {
+        let Some(job_json) = entry.get::<String>(JOB_FIELD) else {
+            return Ok(None);
+        };
+        if let Ok(job) = serde_json::from_str::<JobData>(&job_json) {
+            let _: () = self.con.hset(self.keys(queue_name).job(&job.id), ENTRY_FIELD, &entry.id).await?;
+        }
+        Ok(Some(vec![job_json, entry.id]))
+    }
+
+    /// Fetches a job without blocking: a stale pending job first, then a new one.
+    async fn try_fetch(&mut self, queue_name: &str) -> RedisResult<Option<Vec<String>>> {
+        self.ensure_group(queue_name).await?;
+        self.promote_delayed(queue_name).await?;
+        let stream = self.keys(queue_name).stream();
+
+        let claimed: RedisResult<StreamAutoClaimReply> = self
+            .con
+            .xautoclaim_options(&stream, &self.group, &self.consumer, self.claim_idle_ms, "0-0", StreamAutoClaimOptions::default().count(1))
+            .await;
+        if let Some(entry) = self.check_group(queue_name, claimed)?.claimed.into_iter().next() {
+            return self.take_entry(queue_name, entry).await;
+        }
+
+        let options = StreamReadOptions::default().group(&self.group, &self.consumer).count(1);
+        let read: RedisResult<Option<StreamReadReply>> = self.con.xread_options(&[&stream], &[">"], &options).await;
+        let read = self.check_group(queue_name, read)?;
+        self.first_entry(queue_name, read).await
+    }
+
+    async fn first_entry(&mut self, queue_name: &str, reply: Option<StreamReadReply>) -> RedisResult<Option<Vec<String>>> {
+        match reply.and_then(|reply| reply.keys.into_iter().next()?.ids.into_iter().next()) {
+            Some(entry) => self.take_entry(queue_name, entry).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the oldest stream entries of a queue holding a job, up to an entry ID or time, and at most `count` if set.
+    async fn stream_jobs(&mut self, queue_name: &str, end: &str, count: Option<usize>) -> RedisResult<Vec<(String, JobData)>> {
+        let stream = self.keys(queue_name).stream();
+        let entries: StreamRangeReply = match count {
+            Some(count) => self.con.xrange_count(stream, "-", end, count).await?,
+            None => self.con.xrange(stream, "-", end).await?,
+        };
+        Ok(entries
+            .ids
+            .into_iter()
+            .filter_map(|entry| {
+                let job = serde_json::from_str(&entry.get::<String>(JOB_FIELD)?).ok()?;
+                Some((entry.id, job))
+            })
+            .collect())
+    }
+
+    /// Acknowledges and deletes the stream entry of a fetched job, whichever handle fetched it.
+    async fn ack(&mut self, queue_name: &str, job_id: &str) -> RedisResult<()> {
+        let keys = self.keys(queue_name);
+        let _: u64 = redis::Script::new(ACK_SCRIPT)
+            .key(keys.stream())
+            .key(keys.job(job_id))
+            .arg(&self.group)
+            .arg(ENTRY_FIELD)
+            .invoke_async(&mut self.con)
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl QueueServiceTrait for StreamQueueService {
+    /// Adds a job to the specified queue.
+    ///
+    /// Jobs with a delay are kept in the delayed sorted set until they are due.
+    /// A fetched job added again, e.g. to retry it, is acknowledged first so
+    /// it is not replayed from the pending entries.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to add the job to.
+    /// * `job` - The job data to add to the queue.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn add_job(&mut self, queue_name: &str, job: JobData) -> RedisResult<()> {
+        let delay_secs = job.delay.unwrap_or(0);
+        self.add_job_after(queue_name, job, delay_secs).await
+    }
+
+    /// Adds a job to the specified queue, available after the given delay rather than its own.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to add the job to.
+    /// * `job` - The job data to add to the queue.
+    /// * `delay_secs` - The delay in seconds before the job can be fetched.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn add_job_after(&mut self, queue_name: &str, job: JobData, delay_secs: i64) -> RedisResult<()> {
+        self.ack(queue_name, &job.id).await?;
+        let job_json = serde_json::to_string(&job).unwrap();
+        let keys = self.keys(queue_name);
+        match Some(delay_secs).filter(|delay| *delay > 0) {
+            Some(delay) => {
+                let _: () = self.con.zadd(keys.delayed(), job_json, Utc::now().timestamp_millis() + delay.saturating_mul(1000)).await?;
+            }
+            None => {
+                let _: () = self.con.xadd(keys.stream(), "*", &[(JOB_FIELD, job_json)]).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Retrieves the next job from the specified queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to retrieve the job from.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the job JSON string and its entry ID, `None` while the queue is paused,
+    /// or an error if the queue is empty.
+    async fn get_next_job(&mut self, queue_name: &str) -> RedisResult<Option<Vec<String>>> {
+        if self.inner.is_paused(queue_name).await? {
+            return Ok(None);
+        }
+        match self.try_fetch(queue_name).await? {
+            Some(next_job) => Ok(Some(next_job)),
+            None => Err(RedisError::from((redis::ErrorKind::TypeError, "No job found"))),
+        }
+    }
+
+    /// Waits for the next job from the specified queue.
+    ///
+    /// Blocks with `XREADGROUP` on the blocking connection when one is set,
+    /// waking up in time to promote delayed jobs and to claim stale ones.
+    /// While the queue is paused, returns `None` after waiting for at most a second.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to retrieve the job from.
+    /// * `timeout_secs` - The maximum time in seconds to block, `0` blocks forever.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the job JSON string and its entry ID, or `None` on timeout.
+    async fn wait_for_next_job(&mut self, queue_name: &str, timeout_secs: f64) -> RedisResult<Option<Vec<String>>> {
+        if self.inner.is_paused(queue_name).await? {
+            wait_while_paused(timeout_secs).await;
+            return Ok(None);
+        }
+        let deadline = (timeout_secs > 0.0)
+            .then(|| tokio::time::Instant::now() + Duration::from_secs_f64(timeout_secs));
+        loop {
+            if let Some(next_job) = self.try_fetch(queue_name).await? {
+                return Ok(Some(next_job));
+            }
+
+            let keys = self.keys(queue_name);
+            let mut block = Duration::from_millis(self.claim_idle_ms);
+            if let Some(deadline) = deadline {
+                let now = tokio::time::Instant::now();
+                if deadline <= now {
+                    return Ok(None);
+                }
+                block = block.min(deadline - now);
+            }
+            let next_due: Vec<(String, i64)> = self.con.zrange_withscores(keys.delayed(), 0, 0).await?;
+            if let Some((_, due)) = next_due.first() {
+                block = block.min(Duration::from_millis((due - Utc::now().timestamp_millis()).max(0) as u64));
+            }
+
+            let options = StreamReadOptions::default()
+                .group(&self.group, &self.consumer)
+                .count(1)
+                .block((block.as_millis() as usize).max(1));
+            let con = self.blocking_con.as_mut().unwrap_or(&mut self.con);
+            let read: RedisResult<Option<StreamReadReply>> = con.xread_options(&[keys.stream()], &[">"], &options).await;
+            let read = self.check_group(queue_name, read)?;
+            if let Some(next_job) = self.first_entry(queue_name, read).await? {
+                return Ok(Some(next_job));
+            }
+        }
+    }
+
+    /// Counts the number of jobs in the specified queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to count the jobs in.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the number of jobs in the queue.
+    async fn count_jobs(&mut self, queue_name: &str) -> RedisResult<u64> {
+        self.count_jobs_by_state(queue_name, JobState::Waiting).await
+    }
+
+    /// Counts the number of jobs of the specified queue in the given state.
+    ///
+    /// Waiting jobs are the delayed jobs and the stream entries not yet delivered to a consumer.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to count the jobs in.
+    /// * `state` - The state of the jobs to count.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the number of jobs in the given state.
+    async fn count_jobs_by_state(&mut self, queue_name: &str, state: JobState) -> RedisResult<u64> {
+        if state != JobState::Waiting {
+            return self.inner.count_jobs_by_state(queue_name, state).await;
+        }
+        self.ensure_group(queue_name).await?;
+        let keys = self.keys(queue_name);
+        let (length, delayed): (u64, u64) = redis::pipe()
+            .xlen(keys.stream())
+            .zcard(keys.delayed())
+            .query_async(&mut self.con)
+            .await?;
+        let pending: RedisResult<redis::streams::StreamPendingReply> = self.con.xpending(keys.stream(), &self.group).await;
+        let pending = self.check_group(queue_name, pending)?.count() as u64;
+        Ok(length.saturating_sub(pending) + delayed)
+    }
+
+    /// Updates the progress of a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    /// * `progress` - The progress value to update.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn update_job_progress(&mut self, queue_name: &str, job_id: &str, progress: u32) -> RedisResult<()> {
+        self.inner.update_job_progress(queue_name, job_id, progress).await
+    }
+
+    /// Retrieves the progress of a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the progress value of the job, or `None` if none was set.
+    async fn get_job_progress(&mut self, queue_name: &str, job_id: &str) -> RedisResult<Option<u32>> {
+        self.inner.get_job_progress(queue_name, job_id).await
+    }
+
+    /// Acknowledges a job and moves it to the failed queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job` - The job data to move to the failed queue.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn move_to_failed(&mut self, queue_name: &str, job: JobData) -> RedisResult<()> {
+        self.ack(queue_name, &job.id).await?;
+        self.inner.move_to_failed(queue_name, job).await
+    }
+
+    /// Logs the status of a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job` - A reference to the job data.
+    /// * `status` - The status message to log.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn log_job_status(&mut self, queue_name: &str, job: &JobData, status: &str) -> RedisResult<()> {
+        self.inner.log_job_status(queue_name, job, status).await
+    }
+
+    /// Acknowledges a job and records its throughput and latency metrics.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job` - A reference to the job data.
+    /// * `processing_ms` - The time spent processing the job, in milliseconds.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn mark_completed(&mut self, queue_name: &str, job: &JobData, processing_ms: u64) -> RedisResult<()> {
+        self.ack(queue_name, &job.id).await?;
+        self.inner.mark_completed(queue_name, job, processing_ms).await
+    }
+
+    /// Retrieves the per-minute data points of a queue metric.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to read the metrics of.
+    /// * `kind` - The metric to read.
+    /// * `range` - The time range to read.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing one data point per minute, oldest first.
+    async fn get_metrics(&mut self, queue_name: &str, kind: MetricKind, range: MetricsRange) -> RedisResult<Vec<MetricPoint>> {
+        self.inner.get_metrics(queue_name, kind, range).await
+    }
+
+    /// Reads entries from the specified queue's log, most recent first.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to read the log of.
+    /// * `job_id` - Only return the entries of this job, if set.
+    /// * `range` - The page of matching entries to return.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the matching entries.
+    async fn read_logs(&mut self, queue_name: &str, job_id: Option<&str>, range: LogRange) -> RedisResult<Vec<LogEntry>> {
+        self.inner.read_logs(queue_name, job_id, range).await
+    }
+
+    /// Appends a line to the log of a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    /// * `line` - The line to append.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the number of lines in the job log.
+    async fn job_log(&mut self, queue_name: &str, job_id: &str, line: &str) -> RedisResult<u64> {
+        self.inner.job_log(queue_name, job_id, line).await
+    }
+
+    /// Reads lines from the log of a job, oldest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    /// * `start` - The index of the first line, negative values count from the end.
+    /// * `end` - The index of the last line, inclusive, negative values count from the end.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the requested lines.
+    async fn get_job_logs(&mut self, queue_name: &str, job_id: &str, start: isize, end: isize) -> RedisResult<Vec<String>> {
+        self.inner.get_job_logs(queue_name, job_id, start, end).await
+    }
+
+    /// Removes a job from the stream, the delayed jobs and the failed queue, together with its progress and log lines.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the job was found in a queue.
+    async fn remove_job(&mut self, queue_name: &str, job_id: &str) -> RedisResult<bool> {
+        self.ensure_group(queue_name).await?;
+        let keys = self.keys(queue_name);
+        let entry_ids: Vec<String> = self
+            .stream_jobs(queue_name, "+", None)
+            .await?
+            .into_iter()
+            .filter(|(_, job)| job.id == job_id)
+            .map(|(entry_id, _)| entry_id)
+            .collect();
+        let delayed = self.inner.find_job_members(&keys.delayed(), job_id).await?;
+        let found = !entry_ids.is_empty() || !delayed.is_empty();
+
+        if found {
+            let mut pipe = redis::pipe();
+            pipe.atomic();
+            if !entry_ids.is_empty() {
+                pipe.xack(keys.stream(), &self.group, &entry_ids).ignore()
+                    .xdel(keys.stream(), &entry_ids).ignore();
+            }
+            if !delayed.is_empty() {
+                pipe.zrem(keys.delayed(), delayed).ignore();
+            }
+            let _: () = pipe.query_async(&mut self.con).await?;
+        }
+        Ok(self.inner.remove_job(queue_name, job_id).await? || found)
+    }
+
+    /// Lists the jobs of the specified queue in the given state.
+    ///
+    /// Waiting jobs are the stream entries in insertion order, including the
+    /// ones delivered but not acknowledged yet, followed by the delayed jobs by due time.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to list the jobs of.
+    /// * `state` - The state of the jobs to list.
+    /// * `offset` - The number of jobs to skip.
+    /// * `limit` - The maximum number of jobs to return.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the jobs.
+    async fn list_jobs(&mut self, queue_name: &str, state: JobState, offset: usize, limit: usize) -> RedisResult<Vec<JobData>> {
+        if state != JobState::Waiting {
+            return self.inner.list_jobs(queue_name, state, offset, limit).await;
+        }
+        if limit == 0 || offset > isize::MAX as usize {
+            return Ok(Vec::new());
+        }
+        let keys = self.keys(queue_name);
+        // Streams cannot be read from an offset, so only the entries up to the end of the page are read.
+        let streamed: usize = self.con.xlen(keys.stream()).await?;
+        let mut jobs: Vec<JobData> = Vec::new();
+        if offset < streamed {
+            let entries = self.stream_jobs(queue_name, "+", Some(offset.saturating_add(limit).min(isize::MAX as usize))).await?;
+            jobs = entries.into_iter().skip(offset).map(|(_, job)| job).collect();
+        }
+        if jobs.len() < limit {
+            let offset = offset.saturating_sub(streamed);
+            let delayed: Vec<String> = self
+                .con
+                .zrange(keys.delayed(), offset as isize, range_stop(offset, limit - jobs.len()))
+                .await?;
+            jobs.extend(delayed.iter().filter_map(|job_json| serde_json::from_str(job_json).ok()));
+        }
+        Ok(jobs)
+    }
+
+    /// Moves a failed job back to the stream, ready to be fetched immediately.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the failed job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the job was found in the failed queue.
+    async fn retry_job(&mut self, queue_name: &str, job_id: &str) -> RedisResult<bool> {
+        let keys = self.keys(queue_name);
+        let failed = self.inner.find_job_members(&keys.failed(), job_id).await?;
+        if failed.is_empty() {
+            return Ok(false);
+        }
+        let mut pipe = redis::pipe();
+        pipe.atomic().zrem(keys.failed(), &failed).ignore();
+        for job_json in failed {
+            pipe.xadd(keys.stream(), "*", &[(JOB_FIELD, job_json)]).ignore();
+        }
+        let _: () = pipe.query_async(&mut self.con).await?;
+        Ok(true)
+    }
+
+    /// Removes a failed job from the failed jobs of a queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job failed in.
+    /// * `job_id` - The ID of the failed job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the job was found in the failed queue.
+    async fn remove_failed_job(&mut self, queue_name: &str, job_id: &str) -> RedisResult<bool> {
+        self.inner.remove_failed_job(queue_name, job_id).await
+    }
+
+    /// Pauses the specified queue, workers stop fetching its jobs until it is resumed.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to pause.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn pause_queue(&mut self, queue_name: &str) -> RedisResult<()> {
+        self.inner.pause_queue(queue_name).await
+    }
+
+    /// Resumes a paused queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to resume.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn resume_queue(&mut self, queue_name: &str) -> RedisResult<()> {
+        self.inner.resume_queue(queue_name).await
+    }
+
+    /// Tells whether the specified queue is paused.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the queue is paused.
+    async fn is_paused(&mut self, queue_name: &str) -> RedisResult<bool> {
+        self.inner.is_paused(queue_name).await
+    }
+
+    /// Removes the jobs in the given state older than a grace period, together with their progress and log lines.
+    ///
+    /// Waiting jobs are aged by the time of their stream entry, delayed jobs by their due time.
+    /// Jobs delivered to a consumer and not acknowledged yet are being processed, so they are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to clean.
+    /// * `state` - The state of the jobs to remove.
+    /// * `grace_secs` - The minimum age, in seconds, of the removed jobs.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the number of removed jobs.
+    async fn clean_jobs(&mut self, queue_name: &str, state: JobState, grace_secs: u64) -> RedisResult<u64> {
+        if state != JobState::Waiting {
+            return self.inner.clean_jobs(queue_name, state, grace_secs).await;
+        }
+        self.ensure_group(queue_name).await?;
+        let keys = self.keys(queue_name);
+        let cutoff_ms = Utc::now().timestamp_millis() - grace_secs as i64 * 1000;
+        // Without a grace period every entry is removed, including the ones added in the current millisecond.
+        let end = if grace_secs == 0 { "+".to_string() } else { cutoff_ms.to_string() };
+        let mut streamed = self.stream_jobs(queue_name, &end, None).await?;
+        if !streamed.is_empty() {
+            let pending: RedisResult<StreamPendingCountReply> =
+                self.con.xpending_count(keys.stream(), &self.group, "-", &end, streamed.len()).await;
+            let pending: HashSet<String> = self.check_group(queue_name, pending)?.ids.into_iter().map(|pending| pending.id).collect();
+            streamed.retain(|(entry_id, _)| !pending.contains(entry_id));
+        }
+        let delayed: Vec<String> = self.con.zrangebyscore(keys.delayed(), "-inf", cutoff_ms).await?;
+        if streamed.is_empty() && delayed.is_empty() {
+            return Ok(0);
+        }
+
+        let entry_ids: Vec<&String> = streamed.iter().map(|(entry_id, _)| entry_id).collect();
+        let jobs = streamed
+            .iter()
+            .map(|(_, job)| job.clone())
+            .chain(delayed.iter().filter_map(|job_json| serde_json::from_str(job_json).ok()));
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        if !entry_ids.is_empty() {
+            pipe.xack(keys.stream(), &self.group, &entry_ids).ignore()
+                .xdel(keys.stream(), &entry_ids).ignore();
+        }
+        if !delayed.is_empty() {
+            pipe.zrem(keys.delayed(), &delayed).ignore();
+        }
+        for job in jobs {
+            pipe.del(keys.job(&job.id)).ignore()
+                .del(keys.job_logs(&job.id)).ignore();
+        }
+        let _: () = pipe.query_async(&mut self.con).await?;
+        Ok((streamed.len() + delayed.len()) as u64)
+    }
+
+    /// Lists the queues holding any key under the key prefix, streams included.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the names of the queues, sorted.
+    async fn list_queues(&mut self) -> RedisResult<Vec<String>> {
+        self.inner.list_queues().await
+    }
+
+    /// Requests the cancellation of a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn cancel_job(&mut self, queue_name: &str, job_id: &str) -> RedisResult<()> {
+        self.inner.cancel_job(queue_name, job_id).await
+    }
+
+    /// Consumes the cancellation request of a job, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the job's cancellation was requested.
+    async fn take_job_cancellation(&mut self, queue_name: &str, job_id: &str) -> RedisResult<bool> {
+        self.inner.take_job_cancellation(queue_name, job_id).await
+    }
+
+    /// Returns a new handle on the same queues.
+    ///
+    /// # Returns
+    ///
+    /// The boxed copy of the queue service.
+    fn clone_box(&self) -> Box<dyn QueueServiceTrait> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,426 @@
+use chrono::DateTime;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Row, Table, TableState};
+use ratatui::Frame;
+use redis::RedisResult;
+use std::collections::HashSet;
+use std::io;
+use std::time::{Duration, Instant};
+use crate::job_model::{JobData, JobState};
+use crate::log_model::{LogEntry, LogRange};
+use crate::QueueServiceTrait;
+
+/// Number of recent failures shown for the selected queue.
+pub const TOP_FAILURES: usize = 50;
+
+/// Number of queue log entries read to find the active jobs and show the log tail.
+pub const TOP_LOG_ENTRIES: usize = 200;
+
+/// Interval at which the keyboard is polled between two refreshes.
+const KEY_POLL_INTERVAL_MS: u64 = 50;
+
+/// The depth of a queue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueueDepth {
+    /// The name of the queue.
+    pub queue: String,
+    /// The number of waiting jobs.
+    pub waiting: u64,
+    /// The number of failed jobs.
+    pub failed: u64,
+    /// Whether the queue is paused.
+    pub paused: bool,
+}
+
+/// A job being processed by a worker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveJob {
+    /// The ID of the job.
+    pub id: String,
+    /// The time the job became active, in RFC 3339 format.
+    pub since: String,
+    /// The progress of the job, if reported.
+    pub progress: Option<u32>,
+}
+
+/// What `bullmq top` shows at one refresh.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TopSnapshot {
+    /// The depth of every monitored queue.
+    pub queues: Vec<QueueDepth>,
+    /// The active jobs of the selected queue, most recently started first.
+    pub active: Vec<ActiveJob>,
+    /// The most recent failures of the selected queue.
+    pub failures: Vec<JobData>,
+    /// The tail of the log of the selected queue, most recent first.
+    pub log: Vec<LogEntry>,
+}
+
+impl TopSnapshot {
+    /// Reads the state of the queues.
+    ///
+    /// Active jobs are the jobs whose last status in the queue log is `active`,
+    /// as written by the worker when it starts processing a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_service` - The queue service to read from.
+    /// * `queues` - The names of the monitored queues.
+    /// * `selected` - The name of the queue whose jobs and log are read.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the snapshot.
+    pub async fn read(queue_service: &mut dyn QueueServiceTrait, queues: &[String], selected: Option<&str>) -> RedisResult<Self> {
+        let mut snapshot = TopSnapshot::default();
+        for queue in queues {
+            snapshot.queues.push(QueueDepth {
+                queue: queue.clone(),
+                waiting: queue_service.count_jobs(queue).await?,
+                failed: queue_service.count_jobs_by_state(queue, JobState::Failed).await?,
+                paused: queue_service.is_paused(queue).await?,
+            });
+        }
+        let Some(queue) = selected else {
+            return Ok(snapshot);
+        };
+
+        snapshot.log = queue_service
+            .read_logs(queue, None, LogRange { offset: 0, limit: TOP_LOG_ENTRIES })
+            .await?;
+        let mut seen = HashSet::new();
+        for entry in &snapshot.log {
+            let Some(job_id) = &entry.job_id else { continue };
+            if seen.insert(job_id.as_str()) && entry.event == "active" {
+                let progress = queue_service.get_job_progress(queue, job_id).await?;
+                snapshot.active.push(ActiveJob { id: job_id.clone(), since: entry.timestamp.clone(), progress });
+            }
+        }
+        snapshot.failures = queue_service.list_jobs(queue, JobState::Failed, 0, TOP_FAILURES).await?;
+        Ok(snapshot)
+    }
+}
+
+/// The list of jobs the selection applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TopPane {
+    /// The active jobs.
+    Active,
+    /// The recent failures.
+    Failures,
+}
+
+/// The state of the `bullmq top` terminal UI.
+pub struct Top {
+    queues: Vec<String>,
+    discover: bool,
+    selected_queue: usize,
+    pane: TopPane,
+    selected_job: usize,
+    snapshot: TopSnapshot,
+    status: String,
+}
+
+impl Top {
+    /// Creates a new `Top`.
+    ///
+    /// # Arguments
+    ///
+    /// * `queues` - The names of the queues to monitor, all the queues found in the backend if empty.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of `Top`.
+    pub fn new(queues: Vec<String>) -> Self {
+        Self {
+            discover: queues.is_empty(),
+            queues,
+            selected_queue: 0,
+            pane: TopPane::Failures,
+            selected_job: 0,
+            snapshot: TopSnapshot::default(),
+            status: String::new(),
+        }
+    }
+
+    /// Returns the last snapshot read.
+    pub fn snapshot(&self) -> &TopSnapshot {
+        &self.snapshot
+    }
+
+    /// Returns the name of the selected queue, if any.
+    pub fn selected_queue(&self) -> Option<&str> {
+        self.queues.get(self.selected_queue).map(String::as_str)
+    }
+
+    /// Returns the ID of the selected job, if any.
+    pub fn selected_job(&self) -> Option<&str> {
+        match self.pane {
+            TopPane::Active => self.snapshot.active.get(self.selected_job).map(|job| job.id.as_str()),
+            TopPane::Failures => self.snapshot.failures.get(self.selected_job).map(|job| job.id.as_str()),
+        }
+    }
+
+    /// Returns the message of the last action.
+    pub fn status(&self) -> &str {
+        &self.status
+    }
+
+    /// Reads a new snapshot of the queues, keeping the selection when possible.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_service` - The queue service to read from.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    pub async fn refresh(&mut self, queue_service: &mut dyn QueueServiceTrait) -> RedisResult<()> {
+        if self.discover {
+            let selected = self.selected_queue().map(str::to_string);
+            self.queues = queue_service.list_queues().await?;
+            self.selected_queue = selected
+                .and_then(|selected| self.queues.iter().position(|queue| *queue == selected))
+                .unwrap_or(0);
+        }
+        let selected = self.selected_queue().map(str::to_string);
+        self.snapshot = TopSnapshot::read(queue_service, &self.queues, selected.as_deref()).await?;
+        self.selected_job = self.selected_job.min(self.pane_len().saturating_sub(1));
+        Ok(())
+    }
+
+    fn pane_len(&self) -> usize {
+        match self.pane {
+            TopPane::Active => self.snapshot.active.len(),
+            TopPane::Failures => self.snapshot.failures.len(),
+        }
+    }
+
+    /// Applies a key press.
+    ///
+    /// `q` or `Esc` quits, `←`/`→` select the queue, `Tab` switches between
+    /// the active jobs and the failures, `↑`/`↓` select the job, `r` retries
+    /// the selected failure, `d` or `Delete` removes the selected job, and `p`
+    /// pauses or resumes the selected queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_service` - The queue service the actions are applied with.
+    /// * `key` - The key pressed.
+    ///
+    /// # Returns
+    ///
+    /// `false` if the UI should quit.
+    pub async fn handle_key(&mut self, queue_service: &mut dyn QueueServiceTrait, key: KeyEvent) -> bool {
+        let queue_count = self.queues.len().max(1);
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return false,
+            KeyCode::Left | KeyCode::Char('h') => {
+                self.selected_queue = (self.selected_queue + queue_count - 1) % queue_count;
+                self.selected_job = 0;
+            }
+            KeyCode::Right | KeyCode::Char('l') => {
+                self.selected_queue = (self.selected_queue + 1) % queue_count;
+                self.selected_job = 0;
+            }
+            KeyCode::Tab | KeyCode::BackTab => {
+                self.pane = match self.pane {
+                    TopPane::Active => TopPane::Failures,
+                    TopPane::Failures => TopPane::Active,
+                };
+                self.selected_job = 0;
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.selected_job = self.selected_job.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected_job = (self.selected_job + 1).min(self.pane_len().saturating_sub(1));
+            }
+            KeyCode::Char('r') => self.retry_selected(queue_service).await,
+            KeyCode::Char('d') | KeyCode::Delete => self.remove_selected(queue_service).await,
+            KeyCode::Char('p') => self.toggle_pause(queue_service).await,
+            _ => return true,
+        }
+        if let Err(e) = self.refresh(queue_service).await {
+            self.status = format!("refresh failed: {}", e);
+        }
+        true
+    }
+
+    async fn retry_selected(&mut self, queue_service: &mut dyn QueueServiceTrait) {
+        let (Some(queue), Some(job_id)) = (self.selected_queue(), self.selected_job()) else {
+            self.status = "no job selected".to_string();
+            return;
+        };
+        let (queue, job_id) = (queue.to_string(), job_id.to_string());
+        self.status = match queue_service.retry_job(&queue, &job_id).await {
+            Ok(true) => format!("retried {}", job_id),
+            Ok(false) => format!("{} is not a failed job", job_id),
+            Err(e) => format!("retry failed: {}", e),
+        };
+    }
+
+    async fn remove_selected(&mut self, queue_service: &mut dyn QueueServiceTrait) {
+        let (Some(queue), Some(job_id)) = (self.selected_queue(), self.selected_job()) else {
+            self.status = "no job selected".to_string();
+            return;
+        };
+        let (queue, job_id) = (queue.to_string(), job_id.to_string());
+        self.status = match queue_service.remove_job(&queue, &job_id).await {
+            Ok(true) => format!("removed {}", job_id),
+            Ok(false) => format!("{} is not in {}", job_id, queue),
+            Err(e) => format!("remove failed: {}", e),
+        };
+    }
+
+    async fn toggle_pause(&mut self, queue_service: &mut dyn QueueServiceTrait) {
+        let Some(depth) = self.snapshot.queues.get(self.selected_queue) else {
+            return;
+        };
+        let (queue, paused) = (depth.queue.clone(), depth.paused);
+        let result = if paused {
+            queue_service.resume_queue(&queue).await
+        } else {
+            queue_service.pause_queue(&queue).await
+        };
+        self.status = match result {
+            Ok(()) if paused => format!("resumed {}", queue),
+            Ok(()) => format!("paused {}", queue),
+            Err(e) => format!("pause failed: {}", e),
+        };
+    }
+
+    /// Draws the UI.
+    pub fn render(&self, frame: &mut Frame) {
+        let [top, failures, log, footer] = Layout::vertical([
+            Constraint::Length(self.queues.len().clamp(1, 10) as u16 + 3),
+            Constraint::Fill(1),
+            Constraint::Fill(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [queues, active] = Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)]).areas(top);
+        let selected_queue = self.selected_queue().unwrap_or("-");
+        let highlight = Style::default().add_modifier(Modifier::REVERSED);
+
+        let rows = self.snapshot.queues.iter().map(|depth| {
+            let name = if depth.paused { format!("{} (paused)", depth.queue) } else { depth.queue.clone() };
+            Row::new(vec![name, depth.waiting.to_string(), depth.failed.to_string()])
+        });
+        let table = Table::new(rows, [Constraint::Fill(1), Constraint::Length(9), Constraint::Length(9)])
+            .header(Row::new(vec!["Queue", "Waiting", "Failed"]).bold())
+            .row_highlight_style(highlight)
+            .block(Block::bordered().title(" Queues "));
+        let mut state = TableState::default().with_selected(Some(self.selected_queue));
+        frame.render_stateful_widget(table, queues, &mut state);
+
+        let rows = self.snapshot.active.iter().map(|job| {
+            let progress = job.progress.map(progress_bar).unwrap_or_else(|| "-".to_string());
+            Row::new(vec![job.id.clone(), time_of(&job.since), progress])
+        });
+        let table = Table::new(rows, [Constraint::Fill(1), Constraint::Length(8), Constraint::Length(17)])
+            .header(Row::new(vec!["Job", "Since", "Progress"]).bold())
+            .row_highlight_style(highlight)
+            .block(self.pane_block(TopPane::Active, format!(" Active jobs of {} ", selected_queue)));
+        let mut state = TableState::default().with_selected((self.pane == TopPane::Active).then_some(self.selected_job));
+        frame.render_stateful_widget(table, active, &mut state);
+
+        let rows = self.snapshot.failures.iter().map(|job| {
+            Row::new(vec![
+                job.id.clone(),
+                job.attempts_made.unwrap_or(0).to_string(),
+                job.failed_reason.clone().unwrap_or_default().replace('\n', " "),
+            ])
+        });
+        let table = Table::new(rows, [Constraint::Percentage(25), Constraint::Length(8), Constraint::Fill(1)])
+            .header(Row::new(vec!["Job", "Attempts", "Reason"]).bold())
+            .row_highlight_style(highlight)
+            .block(self.pane_block(TopPane::Failures, format!(" Recent failures of {} ", selected_queue)));
+        let mut state = TableState::default().with_selected((self.pane == TopPane::Failures).then_some(self.selected_job));
+        frame.render_stateful_widget(table, failures, &mut state);
+
+        let lines: Vec<Line> = self
+            .snapshot
+            .log
+            .iter()
+            .map(|entry| {
+                Line::from(format!(
+                    "{} {:<5} {} {}",
+                    time_of(&entry.timestamp),
+                    format!("{:?}", entry.level).to_uppercase(),
+                    entry.job_id.as_deref().unwrap_or("-"),
+                    entry.event
+                ))
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(format!(" Log of {} ", selected_queue))), log);
+
+        let help = "q quit  ←/→ queue  tab pane  ↑/↓ job  r retry  d remove  p pause/resume";
+        let footer_text = if self.status.is_empty() { help.to_string() } else { format!("{}  |  {}", self.status, help) };
+        frame.render_widget(Paragraph::new(footer_text).dim(), footer);
+    }
+
+    fn pane_block(&self, pane: TopPane, title: String) -> Block<'static> {
+        let block = Block::bordered().title(title);
+        if self.pane == pane {
+            block.border_style(Style::default().bold())
+        } else {
+            block
+        }
+    }
+}
+
+/// Formats a progress value as a ten-cell bar.
+fn progress_bar(progress: u32) -> String {
+    let filled = (progress.min(100) / 10) as usize;
+    format!("[{}{}] {:>3}%", "#".repeat(filled), ".".repeat(10 - filled), progress)
+}
+
+/// Returns the time of day of an RFC 3339 timestamp, or the timestamp itself if it can not be parsed.
+fn time_of(timestamp: &str) -> String {
+    DateTime::parse_from_rfc3339(timestamp)
+        .map(|time| time.format("%H:%M:%S").to_string())
+        .unwrap_or_else(|_| timestamp.to_string())
+}
+
+/// Runs the `bullmq top` terminal UI until the user quits.
+///
+/// # Arguments
+///
+/// * `queue_service` - The queue service to monitor.
+/// * `queues` - The names of the queues to monitor, all the queues found in the backend if empty.
+/// * `refresh` - The interval between two refreshes.
+///
+/// # Returns
+///
+/// An `io::Result` indicating whether the terminal could be used.
+pub async fn run(queue_service: &mut dyn QueueServiceTrait, queues: Vec<String>, refresh: Duration) -> io::Result<()> {
+    let mut top = Top::new(queues);
+    let mut terminal = ratatui::try_init()?;
+    let result = async {
+        let mut next_refresh = Instant::now();
+        loop {
+            if Instant::now() >= next_refresh {
+                if let Err(e) = top.refresh(queue_service).await {
+                    top.status = format!("refresh failed: {}", e);
+                }
+                next_refresh = Instant::now() + refresh;
+            }
+            terminal.draw(|frame| top.render(frame))?;
+            if event::poll(Duration::ZERO)? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press && !top.handle_key(queue_service, key).await {
+                        return Ok(());
+                    }
+                }
+                continue;
+            }
+            tokio::time::sleep(Duration::from_millis(KEY_POLL_INTERVAL_MS)).await;
+        }
+    }
+    .await;
+    ratatui::try_restore()?;
+    result
+}
@@ -0,0 +1,668 @@
+use crate::job_model::JobData;
+#[cfg(feature = "otel")]
+use crate::job_model::{FailedJobFilter, JobState};
+#[cfg(feature = "otel")]
+use crate::log_model::{LogEntry, LogRange};
+#[cfg(feature = "otel")]
+use crate::metrics_model::{MetricKind, MetricPoint, MetricsRange};
+#[cfg(feature = "otel")]
+use crate::QueueServiceTrait;
+#[cfg(feature = "otel")]
+use async_trait::async_trait;
+#[cfg(feature = "otel")]
+use redis::RedisResult;
+use tracing::Span;
+#[cfg(feature = "otel")]
+use tracing::Instrument;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+#[cfg(feature = "otel")]
+use opentelemetry::propagation::TextMapPropagator;
+#[cfg(feature = "otel")]
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+#[cfg(feature = "otel")]
+use std::collections::HashMap;
+#[cfg(feature = "otel")]
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// Installs a global `tracing` subscriber writing to stderr.
+///
+/// The verbosity is read from the `RUST_LOG` environment variable and defaults to `info`.
+/// Does nothing if a global subscriber is already installed.
+pub fn init_tracing() {
+    let _ = tracing_subscriber::registry()
+        .with(env_filter())
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .try_init();
+}
+
+/// Installs a global `tracing` subscriber writing to stderr and exporting spans
+/// through the given OpenTelemetry tracer.
+///
+/// # Arguments
+///
+/// * `tracer` - The OpenTelemetry tracer spans are exported with, built from a `SdkTracerProvider`.
+#[cfg(feature = "otel")]
+pub fn init_tracing_with_otel(tracer: opentelemetry_sdk::trace::Tracer) {
+    let _ = tracing_subscriber::registry()
+        .with(env_filter())
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .with(tracing_opentelemetry::layer().with_tracer(tracer))
+        .try_init();
+}
+
+/// Keeps the OpenTelemetry exporter installed by `init_tracing_from_env` running,
+/// and flushes the pending spans when dropped.
+#[must_use = "the spans are no longer exported once the guard is dropped"]
+pub struct TracingGuard {
+    #[cfg(feature = "otel")]
+    provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+impl Drop for TracingGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otel")]
+        if let Some(provider) = self.provider.take() {
+            let _ = provider.shutdown();
+        }
+    }
+}
+
+/// Installs a global `tracing` subscriber writing to stderr, configured from the environment.
+///
+/// With the `otel` feature, spans are also exported over OTLP/HTTP when `OTEL_EXPORTER_OTLP_ENDPOINT`
+/// or `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT` is set. The exporter reads the other standard `OTEL_*`
+/// variables (headers, timeout, resource attributes), and `OTEL_SERVICE_NAME` overrides the service name.
+///
+/// # Arguments
+///
+/// * `service_name` - The service name spans are exported with when `OTEL_SERVICE_NAME` is not set.
+///
+/// # Returns
+///
+/// A guard to keep alive while the program runs.
+pub fn init_tracing_from_env(service_name: &str) -> TracingGuard {
+    #[cfg(feature = "otel")]
+    {
+        let configured = ["OTEL_EXPORTER_OTLP_ENDPOINT", "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT"]
+            .iter()
+            .any(|name| std::env::var_os(name).is_some_and(|value| !value.is_empty()));
+        if configured {
+            match otlp_tracer_provider(service_name) {
+                Ok(provider) => {
+                    use opentelemetry::trace::TracerProvider as _;
+                    init_tracing_with_otel(provider.tracer("bullmq_rust"));
+                    return TracingGuard { provider: Some(provider) };
+                }
+                Err(e) => {
+                    init_tracing();
+                    tracing::warn!("Failed to create the OTLP exporter, spans are not exported: {}", e);
+                    return TracingGuard { provider: None };
+                }
+            }
+        }
+    }
+    let _ = service_name;
+    init_tracing();
+    TracingGuard {
+        #[cfg(feature = "otel")]
+        provider: None,
+    }
+}
+
+#[cfg(feature = "otel")]
+fn otlp_tracer_provider(
+    service_name: &str,
+) -> Result<opentelemetry_sdk::trace::SdkTracerProvider, opentelemetry_otlp::ExporterBuildError> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .build()?;
+    let mut resource = opentelemetry_sdk::Resource::builder();
+    if std::env::var_os("OTEL_SERVICE_NAME").is_none() {
+        resource = resource.with_service_name(service_name.to_string());
+    }
+    Ok(opentelemetry_sdk::trace::SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(resource.build())
+        .build())
+}
+
+fn env_filter() -> EnvFilter {
+    EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"))
+}
+
+/// Creates the span a job is processed in.
+///
+/// With the `otel` feature, the span is attached to the trace context stored
+/// in the job by the producer, so producer and consumer share one trace.
+///
+/// # Arguments
+///
+/// * `queue_name` - The name of the queue the job was fetched from.
+/// * `job` - A reference to the job data.
+///
+/// # Returns
+///
+/// The span to run the job handler in.
+pub fn job_span(queue_name: &str, job: &JobData) -> Span {
+    let span = tracing::info_span!("process_job", queue = queue_name, job_id = %job.id);
+    #[cfg(feature = "otel")]
+    if let Some(trace_context) = &job.trace_context {
+        let _ = span.set_parent(TraceContextPropagator::new().extract(trace_context));
+    }
+    span
+}
+
+/// Injects the W3C trace context of the current span into the job.
+///
+/// An existing trace context is kept, so re-queued jobs stay in their original trace.
+///
+/// # Arguments
+///
+/// * `job` - The job to inject the trace context into.
+#[cfg(feature = "otel")]
+pub fn inject_trace_context(job: &mut JobData) {
+    if job.trace_context.is_some() {
+        return;
+    }
+    let mut carrier = HashMap::new();
+    TraceContextPropagator::new().inject_context(&Span::current().context(), &mut carrier);
+    if !carrier.is_empty() {
+        job.trace_context = Some(carrier);
+    }
+}
+
+/// Queue service injecting the trace context of the current span into the jobs added to another
+/// queue service.
+///
+/// Each addition runs in a `produce` span, child of the current span if any, so jobs added outside
+/// of any span still start a trace.
+///
+/// Wrapping the backends, rather than each backend injecting the context itself, gives the jobs of
+/// every backend the trace of their producer.
+#[cfg(feature = "otel")]
+#[derive(Clone)]
+pub struct TracedQueueService {
+    inner: Box<dyn QueueServiceTrait>,
+}
+
+#[cfg(feature = "otel")]
+impl TracedQueueService {
+    /// Creates a new `TracedQueueService`.
+    ///
+    /// # Arguments
+    ///
+    /// * `inner` - The queue service storing the jobs.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of `TracedQueueService`.
+    pub fn new(inner: Box<dyn QueueServiceTrait>) -> Self {
+        Self { inner }
+    }
+}
+
+#[cfg(feature = "otel")]
+#[async_trait]
+impl QueueServiceTrait for TracedQueueService {
+    /// Injects the trace context of the `produce` span into a job and adds the job to the specified queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to add the job to.
+    /// * `job` - The job data to add to the queue.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn add_job(&mut self, queue_name: &str, mut job: JobData) -> RedisResult<()> {
+        let span = tracing::info_span!("produce", queue = queue_name, job_id = %job.id);
+        async {
+            inject_trace_context(&mut job);
+            self.inner.add_job(queue_name, job).await
+        }
+        .instrument(span)
+        .await
+    }
+
+    /// Injects the trace context of the `produce` span into a job and adds the job to the specified queue,
+    /// available after the given delay.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to add the job to.
+    /// * `job` - The job data to add to the queue.
+    /// * `delay_secs` - The delay in seconds before the job can be fetched.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn add_job_after(&mut self, queue_name: &str, mut job: JobData, delay_secs: i64) -> RedisResult<()> {
+        let span = tracing::info_span!("produce", queue = queue_name, job_id = %job.id);
+        async {
+            inject_trace_context(&mut job);
+            self.inner.add_job_after(queue_name, job, delay_secs).await
+        }
+        .instrument(span)
+        .await
+    }
+
+    /// Retrieves the next job from the specified queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to retrieve the job from.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the serialized job, or `None` if the queue is empty.
+    async fn get_next_job(&mut self, queue_name: &str) -> RedisResult<Option<Vec<String>>> {
+        self.inner.get_next_job(queue_name).await
+    }
+
+    /// Blocks until a job is available in the specified queue or the timeout elapses.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to retrieve the job from.
+    /// * `timeout_secs` - The maximum time to wait, in seconds.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the serialized job, or `None` if the timeout elapsed.
+    async fn wait_for_next_job(&mut self, queue_name: &str, timeout_secs: f64) -> RedisResult<Option<Vec<String>>> {
+        self.inner.wait_for_next_job(queue_name, timeout_secs).await
+    }
+
+    /// Counts the number of jobs in the specified queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to count the jobs of.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the number of jobs.
+    async fn count_jobs(&mut self, queue_name: &str) -> RedisResult<u64> {
+        self.inner.count_jobs(queue_name).await
+    }
+
+    /// Counts the jobs of the specified queue in a given state.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to count the jobs of.
+    /// * `state` - The state of the jobs to count.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the number of jobs in the state.
+    async fn count_jobs_by_state(&mut self, queue_name: &str, state: JobState) -> RedisResult<u64> {
+        self.inner.count_jobs_by_state(queue_name, state).await
+    }
+
+    /// Updates the progress of a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    /// * `progress` - The progress value to update.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn update_job_progress(&mut self, queue_name: &str, job_id: &str, progress: u32) -> RedisResult<()> {
+        self.inner.update_job_progress(queue_name, job_id, progress).await
+    }
+
+    /// Retrieves the progress of a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the progress value of the job, or `None` if none was set.
+    async fn get_job_progress(&mut self, queue_name: &str, job_id: &str) -> RedisResult<Option<u32>> {
+        self.inner.get_job_progress(queue_name, job_id).await
+    }
+
+    /// Moves a job to the failed queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job` - The job data to move to the failed queue.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn move_to_failed(&mut self, queue_name: &str, job: JobData) -> RedisResult<()> {
+        self.inner.move_to_failed(queue_name, job).await
+    }
+
+    /// Logs the status of a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job` - The job data.
+    /// * `status` - The status to log.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn log_job_status(&mut self, queue_name: &str, job: &JobData, status: &str) -> RedisResult<()> {
+        self.inner.log_job_status(queue_name, job, status).await
+    }
+
+    /// Marks a job as completed and records its metrics.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job` - The completed job.
+    /// * `processing_ms` - The time spent processing the job, in milliseconds.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn mark_completed(&mut self, queue_name: &str, job: &JobData, processing_ms: u64) -> RedisResult<()> {
+        self.inner.mark_completed(queue_name, job, processing_ms).await
+    }
+
+    /// Retrieves the per-minute metrics of a queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue.
+    /// * `kind` - The kind of metric to retrieve.
+    /// * `range` - The range of minutes to retrieve.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the data points, oldest first.
+    async fn get_metrics(&mut self, queue_name: &str, kind: MetricKind, range: MetricsRange) -> RedisResult<Vec<MetricPoint>> {
+        self.inner.get_metrics(queue_name, kind, range).await
+    }
+
+    /// Reads a page of the queue log.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue.
+    /// * `job_id` - Only return the entries of this job when set.
+    /// * `range` - The page of entries to read, most recent first.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the log entries.
+    async fn read_logs(&mut self, queue_name: &str, job_id: Option<&str>, range: LogRange) -> RedisResult<Vec<LogEntry>> {
+        self.inner.read_logs(queue_name, job_id, range).await
+    }
+
+    /// Appends a line to the log of a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    /// * `line` - The line to append.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the number of lines in the log.
+    async fn job_log(&mut self, queue_name: &str, job_id: &str, line: &str) -> RedisResult<u64> {
+        self.inner.job_log(queue_name, job_id, line).await
+    }
+
+    /// Reads lines from the log of a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    /// * `start` - The index of the first line, negative values count from the end.
+    /// * `end` - The index of the last line, negative values count from the end.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the log lines.
+    async fn get_job_logs(&mut self, queue_name: &str, job_id: &str, start: isize, end: isize) -> RedisResult<Vec<String>> {
+        self.inner.get_job_logs(queue_name, job_id, start, end).await
+    }
+
+    /// Removes a job together with its progress and log lines.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the job was found.
+    async fn remove_job(&mut self, queue_name: &str, job_id: &str) -> RedisResult<bool> {
+        self.inner.remove_job(queue_name, job_id).await
+    }
+
+    /// Injects the trace context of the `produce` span into several jobs and adds them to the specified queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to add the jobs to.
+    /// * `jobs` - The jobs to add.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn add_jobs(&mut self, queue_name: &str, mut jobs: Vec<JobData>) -> RedisResult<()> {
+        let span = tracing::info_span!("produce", queue = queue_name, jobs = jobs.len());
+        async {
+            for job in &mut jobs {
+                inject_trace_context(job);
+            }
+            self.inner.add_jobs(queue_name, jobs).await
+        }
+        .instrument(span)
+        .await
+    }
+
+    /// Lists a page of the jobs of a queue in a given state.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to list the jobs of.
+    /// * `state` - The state of the jobs to list.
+    /// * `offset` - The number of jobs to skip.
+    /// * `limit` - The maximum number of jobs to return.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the jobs.
+    async fn list_jobs(&mut self, queue_name: &str, state: JobState, offset: usize, limit: usize) -> RedisResult<Vec<JobData>> {
+        self.inner.list_jobs(queue_name, state, offset, limit).await
+    }
+
+    /// Finds a waiting or failed job by its ID.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the state and data of the job, or `None` if it is not found.
+    async fn get_job(&mut self, queue_name: &str, job_id: &str) -> RedisResult<Option<(JobState, JobData)>> {
+        self.inner.get_job(queue_name, job_id).await
+    }
+
+    /// Moves a failed job back to the waiting jobs, ready to be fetched immediately.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the failed job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the job was found in the failed queue.
+    async fn retry_job(&mut self, queue_name: &str, job_id: &str) -> RedisResult<bool> {
+        self.inner.retry_job(queue_name, job_id).await
+    }
+
+    /// Removes a failed job from the failed jobs of a queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job failed in.
+    /// * `job_id` - The ID of the failed job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the job was found in the failed queue.
+    async fn remove_failed_job(&mut self, queue_name: &str, job_id: &str) -> RedisResult<bool> {
+        self.inner.remove_failed_job(queue_name, job_id).await
+    }
+
+    /// Lists the failed jobs of a queue selected by a filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to list the failed jobs of.
+    /// * `filter` - The filter selecting the jobs.
+    /// * `offset` - The number of selected jobs to skip.
+    /// * `limit` - The maximum number of jobs to return.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the selected jobs, most recently failed first.
+    async fn list_failed_jobs(
+        &mut self,
+        queue_name: &str,
+        filter: &FailedJobFilter,
+        offset: usize,
+        limit: usize,
+    ) -> RedisResult<Vec<JobData>> {
+        self.inner.list_failed_jobs(queue_name, filter, offset, limit).await
+    }
+
+    /// Moves the failed jobs selected by a filter back to the waiting jobs.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the jobs failed in.
+    /// * `filter` - The filter selecting the jobs.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the IDs of the retried jobs.
+    async fn retry_failed_jobs(&mut self, queue_name: &str, filter: &FailedJobFilter) -> RedisResult<Vec<String>> {
+        self.inner.retry_failed_jobs(queue_name, filter).await
+    }
+
+    /// Pauses the specified queue, workers stop fetching its jobs until it is resumed.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to pause.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn pause_queue(&mut self, queue_name: &str) -> RedisResult<()> {
+        self.inner.pause_queue(queue_name).await
+    }
+
+    /// Resumes a paused queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to resume.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn resume_queue(&mut self, queue_name: &str) -> RedisResult<()> {
+        self.inner.resume_queue(queue_name).await
+    }
+
+    /// Tells whether a queue is paused.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the queue is paused.
+    async fn is_paused(&mut self, queue_name: &str) -> RedisResult<bool> {
+        self.inner.is_paused(queue_name).await
+    }
+
+    /// Removes the jobs in a given state older than the grace period.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue to clean.
+    /// * `state` - The state of the jobs to remove.
+    /// * `grace_secs` - The minimum age, in seconds, of the removed jobs.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the number of removed jobs.
+    async fn clean_jobs(&mut self, queue_name: &str, state: JobState, grace_secs: u64) -> RedisResult<u64> {
+        self.inner.clean_jobs(queue_name, state, grace_secs).await
+    }
+
+    /// Lists the queues holding any data.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing the names of the queues, sorted.
+    async fn list_queues(&mut self) -> RedisResult<Vec<String>> {
+        self.inner.list_queues().await
+    }
+
+    /// Requests the cancellation of a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` indicating the success or failure of the operation.
+    async fn cancel_job(&mut self, queue_name: &str, job_id: &str) -> RedisResult<()> {
+        self.inner.cancel_job(queue_name, job_id).await
+    }
+
+    /// Consumes the cancellation request of a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job_id` - The ID of the job.
+    ///
+    /// # Returns
+    ///
+    /// A `RedisResult` containing `true` if the cancellation of the job was requested.
+    async fn take_job_cancellation(&mut self, queue_name: &str, job_id: &str) -> RedisResult<bool> {
+        self.inner.take_job_cancellation(queue_name, job_id).await
+    }
+
+    /// Returns a new handle on the same queues.
+    ///
+    /// # Returns
+    ///
+    /// The boxed copy of the queue service.
+    fn clone_box(&self) -> Box<dyn QueueServiceTrait> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,183 @@
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE, RETRY_AFTER};
+use reqwest::StatusCode;
+use sha2::Sha256;
+use std::time::Duration;
+use crate::job_model::JobData;
+
+/// Default time, in milliseconds, to wait for the endpoint to respond.
+pub const DEFAULT_WEBHOOK_TIMEOUT_MS: u64 = 10_000;
+
+/// Header holding the HMAC-SHA256 signature of the request body, as `sha256=<hex>`.
+pub const SIGNATURE_HEADER: &str = "x-bullmq-signature";
+
+/// Maximum number of bytes of the response body included in the reason of a failure.
+const MAX_REASON_BODY_BYTES: usize = 512;
+
+/// The result of forwarding a job to the webhook endpoint.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WebhookOutcome {
+    /// The endpoint answered with a 2xx status.
+    Delivered,
+    /// The endpoint answered 429 or 503, asking to retry later.
+    Backoff {
+        /// The delay requested by the `Retry-After` header, if any.
+        retry_after: Option<Duration>,
+        /// A description of the response.
+        reason: String,
+    },
+    /// The request failed or the endpoint answered with another status.
+    Failed(String),
+}
+
+/// Service forwarding jobs to an HTTP endpoint.
+///
+/// Each job is POSTed as JSON with its queue, ID and attempt in the
+/// `X-BullMQ-Queue`, `X-BullMQ-Job-Id` and `X-BullMQ-Attempt` headers. When a
+/// signing secret is set, the body is signed with HMAC-SHA256 in the
+/// `X-BullMQ-Signature` header so the endpoint can authenticate the worker.
+pub struct WebhookService {
+    client: reqwest::Client,
+    url: String,
+    headers: HeaderMap,
+    timeout: Duration,
+    secret: Option<Vec<u8>>,
+}
+
+impl WebhookService {
+    /// Creates a new `WebhookService`.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL the jobs are POSTed to.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of `WebhookService`.
+    pub fn new(url: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.to_string(),
+            headers: HeaderMap::new(),
+            timeout: Duration::from_millis(DEFAULT_WEBHOOK_TIMEOUT_MS),
+            secret: None,
+        }
+    }
+
+    /// Adds a header to every request.
+    ///
+    /// # Arguments
+    ///
+    /// * `header` - The header, as `Name: value`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `WebhookService` sending the header, or why the header is invalid.
+    pub fn with_header(mut self, header: &str) -> Result<Self, String> {
+        let (name, value) = header
+            .split_once(':')
+            .ok_or_else(|| format!("invalid header {:?}: expected `Name: value`", header))?;
+        let name = HeaderName::from_bytes(name.trim().as_bytes())
+            .map_err(|e| format!("invalid header name {:?}: {}", name.trim(), e))?;
+        let value = HeaderValue::from_str(value.trim())
+            .map_err(|e| format!("invalid value for header {}: {}", name, e))?;
+        self.headers.append(name, value);
+        Ok(self)
+    }
+
+    /// Sets the time to wait for the endpoint to respond.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets the secret the request bodies are signed with.
+    pub fn with_signing_secret(mut self, secret: &str) -> Self {
+        self.secret = Some(secret.as_bytes().to_vec());
+        self
+    }
+
+    /// Forwards a job to the endpoint.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_name` - The name of the queue the job belongs to.
+    /// * `job` - The job to forward.
+    ///
+    /// # Returns
+    ///
+    /// How the endpoint handled the job.
+    pub async fn forward(&self, queue_name: &str, job: &JobData) -> WebhookOutcome {
+        let body = match serde_json::to_vec(job) {
+            Ok(body) => body,
+            Err(e) => return WebhookOutcome::Failed(format!("cannot serialize job: {}", e)),
+        };
+        let mut request = self
+            .client
+            .post(&self.url)
+            .timeout(self.timeout)
+            .headers(self.headers.clone())
+            .header(CONTENT_TYPE, "application/json")
+            .header("x-bullmq-queue", queue_name)
+            .header("x-bullmq-job-id", &job.id)
+            .header("x-bullmq-attempt", job.attempt().to_string());
+        if let Some(secret) = &self.secret {
+            request = request.header(SIGNATURE_HEADER, sign(secret, &body));
+        }
+
+        let mut response = match request.body(body).send().await {
+            Ok(response) => response,
+            Err(e) if e.is_timeout() => {
+                return WebhookOutcome::Failed(format!("no response from {} within {:?}", self.url, self.timeout))
+            }
+            Err(e) => return WebhookOutcome::Failed(format!("request to {} failed: {}", self.url, e)),
+        };
+        let status = response.status();
+        if status.is_success() {
+            return WebhookOutcome::Delivered;
+        }
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok())
+            .map(Duration::from_secs);
+        let mut bytes = Vec::new();
+        while bytes.len() <= MAX_REASON_BODY_BYTES {
+            match response.chunk().await {
+                Ok(Some(chunk)) => bytes.extend_from_slice(&chunk),
+                _ => break,
+            }
+        }
+        let truncated = bytes.len() > MAX_REASON_BODY_BYTES;
+        bytes.truncate(MAX_REASON_BODY_BYTES);
+        let text = String::from_utf8_lossy(&bytes);
+        let reason = match (text.trim(), truncated) {
+            ("", _) => format!("endpoint answered {}", status),
+            (text, false) => format!("endpoint answered {}: {}", status, text),
+            (text, true) => format!("endpoint answered {}: {}…", status, text),
+        };
+        match status {
+            StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => WebhookOutcome::Backoff { retry_after, reason },
+            _ => WebhookOutcome::Failed(reason),
+        }
+    }
+}
+
+/// Signs a request body with HMAC-SHA256.
+///
+/// # Arguments
+///
+/// * `secret` - The signing secret.
+/// * `body` - The request body.
+///
+/// # Returns
+///
+/// The signature, as `sha256=<hex>`.
+pub fn sign(secret: &[u8], body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    let digest = mac.finalize().into_bytes();
+    let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("sha256={}", hex)
+}
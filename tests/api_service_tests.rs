@@ -0,0 +1,276 @@
+#![cfg(all(feature = "api", feature = "test-util"))]
+
+use axum::body::Body;
+use axum::http::{Method, Request, StatusCode};
+use axum::Router;
+use bullmq_rust::api_service::ApiService;
+use bullmq_rust::in_memory_queue_service::InMemoryQueueService;
+use bullmq_rust::job_model::JobState;
+use bullmq_rust::QueueServiceTrait;
+use http_body_util::BodyExt;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+mod common;
+use common::job;
+
+/// Sends a request to the router in-process and returns the status and JSON body of the response.
+async fn call(router: &Router, method: Method, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+    let request = Request::builder().method(method).uri(uri);
+    let request = match body {
+        Some(body) => request.header("content-type", "application/json").body(Body::from(body.to_string())),
+        None => request.header("x-requested-with", "XMLHttpRequest").body(Body::empty()),
+    }
+    .unwrap();
+    let response = router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    (status, serde_json::from_slice(&bytes).unwrap())
+}
+
+/// Test enqueueing single and bulk jobs, then listing, fetching and removing them.
+#[tokio::test]
+async fn test_enqueue_list_get_and_remove() {
+    let router = ApiService::new(Box::new(InMemoryQueueService::new())).router();
+
+    let (status, body) = call(&router, Method::POST, "/queues/q/jobs", Some(json!({ "id": "job_1", "message": {"n": 1} }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(body["id"], "job_1");
+    assert_eq!(body["message"], r#"{"n":1}"#);
+
+    let bulk = json!([{ "id": "job_2", "message": "two" }, { "message": "three", "delay": 60 }]);
+    let (status, body) = call(&router, Method::POST, "/queues/q/jobs", Some(bulk)).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(body["added"], 2);
+    assert_eq!(body["ids"][0], "job_2");
+
+    let (status, body) = call(&router, Method::GET, "/queues/q/jobs?limit=2", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["state"], "waiting");
+    assert_eq!(body["jobs"].as_array().unwrap().len(), 2);
+    let (_, body) = call(&router, Method::GET, "/queues/q/jobs?offset=2&limit=2", None).await;
+    assert_eq!(body["jobs"].as_array().unwrap().len(), 1);
+
+    let (status, body) = call(&router, Method::GET, "/queues/q/jobs/job_2", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["state"], "waiting");
+    assert_eq!(body["job"]["message"], "two");
+
+    let (status, body) = call(&router, Method::DELETE, "/queues/q/jobs/job_2", None).await;
+    assert_eq!((status, body), (StatusCode::OK, json!({ "removed": "job_2" })));
+    let (status, body) = call(&router, Method::GET, "/queues/q/jobs/job_2", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["error"], "no job job_2 in q");
+
+    let (_, body) = call(&router, Method::GET, "/queues/q", None).await;
+    assert_eq!(body, json!({ "queue": "q", "paused": false, "counts": { "waiting": 2, "failed": 0 } }));
+}
+
+/// Test fetching progress and logs, retrying failed jobs and pausing the queue.
+#[tokio::test]
+async fn test_progress_logs_retry_and_pause() {
+    let mut service = InMemoryQueueService::new();
+    service.add_job("q", job("job_1")).await.unwrap();
+    service.update_job_progress("q", "job_1", 40).await.unwrap();
+    for line in ["first", "second", "third"] {
+        service.job_log("q", "job_1", line).await.unwrap();
+    }
+    service.move_to_failed("q", job("job_2")).await.unwrap();
+    service.move_to_failed("q", job("job_3")).await.unwrap();
+    let router = ApiService::new(Box::new(service)).router();
+
+    let (status, body) = call(&router, Method::GET, "/queues/q/jobs/job_1/progress", None).await;
+    assert_eq!((status, body), (StatusCode::OK, json!({ "id": "job_1", "progress": 40 })));
+    let (status, _) = call(&router, Method::GET, "/queues/q/jobs/missing/progress", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (_, body) = call(&router, Method::GET, "/queues/q/jobs/job_1/logs?offset=1&limit=5", None).await;
+    assert_eq!(body["lines"], json!(["second", "third"]));
+
+    let (status, body) = call(&router, Method::POST, "/queues/q/jobs/job_2/retry", None).await;
+    assert_eq!((status, body), (StatusCode::OK, json!({ "retried": ["job_2"] })));
+    let (status, _) = call(&router, Method::POST, "/queues/q/jobs/job_2/retry", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (_, body) = call(&router, Method::POST, "/queues/q/retry", None).await;
+    assert_eq!(body, json!({ "retried": ["job_3"] }));
+    let (_, body) = call(&router, Method::GET, "/queues/q/jobs?state=failed", None).await;
+    assert_eq!(body["jobs"], json!([]));
+
+    let (_, body) = call(&router, Method::POST, "/queues/q/pause", None).await;
+    assert_eq!(body, json!({ "queue": "q", "paused": true }));
+    let (_, body) = call(&router, Method::GET, "/queues/q", None).await;
+    assert_eq!(body["paused"], true);
+    assert_eq!(body["counts"]["waiting"], 3);
+    let (_, body) = call(&router, Method::POST, "/queues/q/resume", None).await;
+    assert_eq!(body["paused"], false);
+}
+
+/// Test listing the queues and reading their metrics.
+#[tokio::test]
+async fn test_queues_and_metrics() {
+    let mut service = InMemoryQueueService::new();
+    service.add_job("emails", job("job_1")).await.unwrap();
+    service.mark_completed("emails", &job("job_2"), 20).await.unwrap();
+    service.move_to_failed("reports", job("job_3")).await.unwrap();
+    service.pause_queue("reports").await.unwrap();
+    let router = ApiService::new(Box::new(service)).router();
+
+    let (status, body) = call(&router, Method::GET, "/queues", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        body,
+        json!({ "queues": [
+            { "queue": "emails", "paused": false, "counts": { "waiting": 1, "failed": 0 } },
+            { "queue": "reports", "paused": true, "counts": { "waiting": 0, "failed": 1 } },
+        ] })
+    );
+
+    let (status, body) = call(&router, Method::GET, "/queues/emails/metrics?minutes=5", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["kind"], "completed");
+    let points = body["points"].as_array().unwrap();
+    assert!(points.len() >= 5);
+    assert_eq!(points.iter().map(|point| point["count"].as_u64().unwrap()).sum::<u64>(), 1);
+    let (_, body) = call(&router, Method::GET, "/queues/emails/metrics?kind=processing_time", None).await;
+    assert_eq!(body["kind"], "processing_time");
+
+    let (status, _) = call(&router, Method::GET, "/queues/emails/metrics?kind=latency", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = call(&router, Method::GET, "/queues/emails/metrics?minutes=0", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+/// Test that invalid input is answered with a JSON error and status 400.
+#[tokio::test]
+async fn test_invalid_requests() {
+    let router = ApiService::new(Box::new(InMemoryQueueService::new())).router();
+
+    let (status, body) = call(&router, Method::POST, "/queues/q/jobs", Some(json!({ "message": "m", "delay": -1 }))).await;
+    assert_eq!((status, body), (StatusCode::BAD_REQUEST, json!({ "error": "delay must not be negative" })));
+    let (status, body) = call(&router, Method::POST, "/queues/q/jobs", Some(json!([{ "message": "m" }, { "id": "" , "message": "m" }]))).await;
+    assert_eq!((status, body), (StatusCode::BAD_REQUEST, json!({ "error": "invalid job #2: id must not be empty" })));
+    let (status, body) = call(&router, Method::POST, "/queues/q/jobs", Some(json!({ "payload": "m" }))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(body["error"].is_string());
+
+    let (status, _) = call(&router, Method::GET, "/queues/q/jobs?state=done", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, body) = call(&router, Method::GET, "/queues/q/jobs?limit=0", None).await;
+    assert_eq!((status, body), (StatusCode::BAD_REQUEST, json!({ "error": "limit must be between 1 and 1000" })));
+    let (status, _) = call(&router, Method::GET, "/nowhere", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+/// Test that cancelling a job stores a cancellation request for the workers.
+#[tokio::test]
+async fn test_cancel_job() {
+    let mut service = InMemoryQueueService::new();
+    let router = ApiService::new(Box::new(service.clone())).router();
+
+    let (status, body) = call(&router, Method::POST, "/queues/q/jobs/job_1/cancel", None).await;
+    assert_eq!((status, body), (StatusCode::ACCEPTED, json!({ "cancelled": "job_1" })));
+    assert!(service.take_job_cancellation("q", "job_1").await.unwrap());
+}
+
+/// Test filtering failed jobs by reason and age, retrying them by filter and moving them to another queue.
+#[tokio::test]
+async fn test_failed_jobs_filter_retry_and_move() {
+    let mut service = InMemoryQueueService::new();
+    for (id, reason) in [("job_1", "connection refused"), ("job_2", "invalid payload"), ("job_3", "Connection reset")] {
+        let mut failed = job(id);
+        failed.record_failure(reason, Some("at handler".to_string()), 5);
+        service.move_to_failed("q", failed).await.unwrap();
+    }
+    // The target queue is served by another service, as when it is configured on another backend.
+    let mut review = InMemoryQueueService::new();
+    let router = ApiService::new(Box::new(service.clone()))
+        .with_queue_service("review", Box::new(review.clone()))
+        .router();
+
+    let (status, body) = call(&router, Method::GET, "/queues/q/jobs?state=failed&reason=connection&failed_within=60", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["jobs"].as_array().unwrap().len(), 2);
+    assert_eq!(body["jobs"][0]["failures"][0]["stack"], "at handler");
+    let (status, _) = call(&router, Method::GET, "/queues/q/jobs?reason=connection", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (_, body) = call(&router, Method::POST, "/queues/q/retry?reason=refused", None).await;
+    assert_eq!(body, json!({ "retried": ["job_1"] }));
+    let (status, body) = call(&router, Method::POST, "/queues/q/jobs/job_2/move?to=review", None).await;
+    assert_eq!((status, body), (StatusCode::OK, json!({ "moved": ["job_2"], "to": "review" })));
+    let (status, _) = call(&router, Method::POST, "/queues/q/jobs/job_2/move?to=review", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = call(&router, Method::POST, "/queues/q/jobs/job_3/move", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (_, body) = call(&router, Method::POST, "/queues/q/move?to=review&reason=reset", None).await;
+    assert_eq!(body, json!({ "moved": ["job_3"], "to": "review" }));
+    assert_eq!(service.count_jobs_by_state("review", JobState::Waiting).await.unwrap(), 0);
+    assert_eq!(review.count_jobs_by_state("review", JobState::Waiting).await.unwrap(), 2);
+}
+
+/// Test that concurrent requests, each served on its own handle on the queue service, all reach the same queues.
+#[tokio::test]
+async fn test_concurrent_requests() {
+    let service = InMemoryQueueService::new();
+    let router = ApiService::new(Box::new(service.clone())).router();
+
+    let requests = (0..20).map(|i| call(&router, Method::POST, "/queues/q/jobs", Some(json!({ "id": format!("job_{}", i), "message": "hello" }))));
+    let responses = futures::future::join_all(requests).await;
+    assert!(responses.iter().all(|(status, _)| *status == StatusCode::CREATED));
+    assert_eq!(service.jobs("q", JobState::Waiting).len(), 20);
+    let (_, body) = call(&router, Method::GET, "/queues/q", None).await;
+    assert_eq!(body["counts"]["waiting"], 20);
+}
+
+/// Test that a queue with a service of its own is read and written through it, and listed with the other queues.
+#[tokio::test]
+async fn test_queue_with_its_own_service() {
+    let mut default_service = InMemoryQueueService::new();
+    default_service.add_job("emails", job("email_1")).await.unwrap();
+    let events_service = InMemoryQueueService::new();
+    let router = ApiService::new(Box::new(default_service.clone()))
+        .with_queue_service("events", Box::new(events_service.clone()))
+        .router();
+
+    let (status, _) = call(&router, Method::POST, "/queues/events/jobs", Some(json!({ "id": "event_1", "message": "signup" }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(events_service.jobs("events", JobState::Waiting).len(), 1);
+    assert!(default_service.jobs("events", JobState::Waiting).is_empty());
+
+    let (_, body) = call(&router, Method::GET, "/queues/events/jobs/event_1", None).await;
+    assert_eq!(body["job"]["message"], "signup");
+    let (_, body) = call(&router, Method::GET, "/queues", None).await;
+    let queues: Vec<(&str, u64)> = body["queues"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|queue| (queue["queue"].as_str().unwrap(), queue["counts"]["waiting"].as_u64().unwrap()))
+        .collect();
+    assert_eq!(queues, [("emails", 1), ("events", 1)]);
+}
+
+/// Test that requests changing data without a JSON body or the `X-Requested-With` header are refused.
+#[tokio::test]
+async fn test_requests_without_script_header_are_refused() {
+    let backend = InMemoryQueueService::new();
+    let router = ApiService::new(Box::new(backend.clone())).router();
+
+    for (method, uri) in [(Method::POST, "/queues/q/pause"), (Method::DELETE, "/queues/q/jobs/job_1")] {
+        let request = Request::builder().method(method).uri(uri).body(Body::empty()).unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN, "{}", uri);
+    }
+    let form = Request::builder()
+        .method(Method::POST)
+        .uri("/queues/q/resume")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body(Body::from("a=b"))
+        .unwrap();
+    assert_eq!(router.clone().oneshot(form).await.unwrap().status(), StatusCode::FORBIDDEN);
+    assert!(!backend.clone().is_paused("q").await.unwrap());
+
+    let (status, _) = call(&router, Method::POST, "/queues/q/pause", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(backend.clone().is_paused("q").await.unwrap());
+    let (status, _) = call(&router, Method::GET, "/queues/q", None).await;
+    assert_eq!(status, StatusCode::OK);
+}
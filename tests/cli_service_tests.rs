@@ -0,0 +1,207 @@
+#![cfg(feature = "test-util")]
+
+use bullmq_rust::cli_service::{Cli, CliError, EXIT_NOT_FOUND, EXIT_USAGE};
+use bullmq_rust::in_memory_queue_service::InMemoryQueueService;
+use bullmq_rust::job_model::JobState;
+use bullmq_rust::QueueServiceTrait;
+use clap::Parser;
+use serde_json::Value;
+
+/// Runs a command line against the service and returns its output.
+async fn run(service: &mut InMemoryQueueService, args: &[&str]) -> Result<String, CliError> {
+    let cli = Cli::try_parse_from(std::iter::once("bullmq").chain(args.iter().copied())).unwrap();
+    let mut out = Vec::new();
+    cli.execute(service, &mut out).await?;
+    Ok(String::from_utf8(out).unwrap())
+}
+
+async fn run_json(service: &mut InMemoryQueueService, args: &[&str]) -> Value {
+    let output = run(service, &[&["--json"], args].concat()).await.unwrap();
+    serde_json::from_str(&output).unwrap()
+}
+
+/// Test adding, listing, getting and counting jobs.
+#[tokio::test]
+async fn test_add_list_get_and_count() {
+    let mut service = InMemoryQueueService::new();
+    let output = run(&mut service, &["add", "testQueue", "hello", "--id", "job_1", "--retries", "3"]).await.unwrap();
+    assert_eq!(output, "Added job job_1 to testQueue\n");
+    let added = run_json(&mut service, &["add", "testQueue", "world", "--delay", "60"]).await;
+    assert_eq!(added["delay"], 60);
+    assert!(added["id"].as_str().unwrap().starts_with("job_"));
+
+    let listed = run_json(&mut service, &["list", "testQueue", "--limit", "1"]).await;
+    assert_eq!(listed.as_array().unwrap().len(), 1);
+    assert_eq!(listed[0]["message"], "hello");
+
+    let found = run_json(&mut service, &["get", "testQueue", "job_1"]).await;
+    assert_eq!(found["state"], "waiting");
+    assert_eq!(found["job"]["retries"], 3);
+    let missing = run(&mut service, &["get", "testQueue", "missing"]).await.unwrap_err();
+    assert_eq!(missing.exit_code(), EXIT_NOT_FOUND);
+
+    let counts = run_json(&mut service, &["count", "testQueue"]).await;
+    assert_eq!((counts["waiting"].as_u64(), counts["failed"].as_u64()), (Some(2), Some(0)));
+    assert_eq!(run(&mut service, &["count", "testQueue", "--state", "failed"]).await.unwrap(), "failed\t0\n");
+}
+
+/// Test adding jobs in bulk from a JSON file, rejecting the whole file on an invalid job.
+#[tokio::test]
+async fn test_add_bulk() {
+    let mut service = InMemoryQueueService::new();
+    let path = std::env::temp_dir().join(format!("bullmq_cli_test_{}.json", std::process::id()));
+
+    std::fs::write(&path, r#"[{"id": "job_1", "message": "a"}, {"message": {"type": "b"}, "priority": 2}]"#).unwrap();
+    let added = run_json(&mut service, &["add-bulk", "testQueue", path.to_str().unwrap()]).await;
+    assert_eq!(added["added"], 2);
+    let jobs = service.jobs("testQueue", JobState::Waiting);
+    assert!(jobs.iter().any(|job| job.message == r#"{"type":"b"}"#));
+
+    std::fs::write(&path, r#"[{"message": "c"}, {"message": "d", "delay": -1}]"#).unwrap();
+    let invalid = run(&mut service, &["add-bulk", "testQueue", path.to_str().unwrap()]).await.unwrap_err();
+    assert_eq!(invalid.exit_code(), EXIT_USAGE);
+    assert!(invalid.to_string().contains("#2"));
+    assert_eq!(service.count_jobs("testQueue").await.unwrap(), 2);
+    std::fs::remove_file(&path).unwrap();
+}
+
+/// Test retrying, removing and cleaning failed jobs.
+#[tokio::test]
+async fn test_retry_remove_and_clean() {
+    let mut service = InMemoryQueueService::new();
+    for id in ["job_1", "job_2", "job_3"] {
+        run(&mut service, &["add", "testQueue", "message", "--id", id]).await.unwrap();
+        let job = service.jobs("testQueue", JobState::Waiting).into_iter().find(|job| job.id == id).unwrap();
+        service.remove_job("testQueue", id).await.unwrap();
+        service.move_to_failed("testQueue", job).await.unwrap();
+    }
+
+    assert_eq!(run(&mut service, &["retry", "testQueue", "job_1"]).await.unwrap(), "Retried 1 jobs in testQueue\n");
+    let missing = run(&mut service, &["retry", "testQueue", "job_1"]).await.unwrap_err();
+    assert_eq!(missing.exit_code(), EXIT_NOT_FOUND);
+    let removed = run_json(&mut service, &["remove", "testQueue", "job_2"]).await;
+    assert_eq!(removed["removed"], "job_2");
+    assert_eq!(run(&mut service, &["remove", "testQueue", "job_2"]).await.unwrap_err().exit_code(), EXIT_NOT_FOUND);
+
+    let cleaned = run_json(&mut service, &["clean", "testQueue", "--grace", "3600"]).await;
+    assert_eq!(cleaned["removed"], 0);
+    let retried = run_json(&mut service, &["retry", "testQueue", "--all"]).await;
+    assert_eq!(retried["retried"], serde_json::json!(["job_3"]));
+    let cleaned = run_json(&mut service, &["clean", "testQueue", "--state", "waiting"]).await;
+    assert_eq!(cleaned["removed"], 2);
+}
+
+/// Test pausing and resuming a queue, progress, logs and the worker.
+#[tokio::test]
+async fn test_pause_progress_logs_and_work() {
+    let mut service = InMemoryQueueService::new();
+    run(&mut service, &["add", "testQueue", "hello", "--id", "job_1"]).await.unwrap();
+
+    assert_eq!(run_json(&mut service, &["pause", "testQueue"]).await["paused"], true);
+    assert!(service.get_next_job("testQueue").await.unwrap().is_none());
+    run(&mut service, &["resume", "testQueue"]).await.unwrap();
+    assert!(!service.is_paused("testQueue").await.unwrap());
+
+    assert_eq!(run(&mut service, &["progress", "testQueue", "job_1"]).await.unwrap_err().exit_code(), EXIT_NOT_FOUND);
+    run(&mut service, &["progress", "testQueue", "job_1", "40"]).await.unwrap();
+    assert_eq!(run(&mut service, &["progress", "testQueue", "job_1"]).await.unwrap(), "40\n");
+
+    service.job_log("testQueue", "job_1", "line 1").await.unwrap();
+    service.job_log("testQueue", "job_1", "line 2").await.unwrap();
+    let lines = run_json(&mut service, &["logs", "testQueue", "--job", "job_1", "--offset", "1"]).await;
+    assert_eq!(lines, serde_json::json!(["line 2"]));
+
+    let worked = run_json(&mut service, &["work", "testQueue", "--max-jobs", "1", "--refresh", "50"]).await;
+    assert_eq!(worked["processed"], 1);
+    assert_eq!(service.count_jobs("testQueue").await.unwrap(), 0);
+}
+
+/// Test that a job added with a timeout and then cancelled is failed as cancelled by `work`.
+#[tokio::test]
+async fn test_timeout_and_cancel() {
+    let mut service = InMemoryQueueService::new();
+    let added = run_json(&mut service, &["add", "testQueue", "hello", "--id", "job_1", "--timeout", "5000"]).await;
+    assert_eq!(added["timeout"], 5000);
+    assert_eq!(run_json(&mut service, &["cancel", "testQueue", "job_1"]).await["cancelled"], "job_1");
+
+    let args = ["work", "testQueue", "--max-jobs", "1", "--refresh", "50", "--job-timeout", "1000"];
+    assert_eq!(run_json(&mut service, &args).await["processed"], 1);
+    let failed = service.jobs("testQueue", JobState::Failed);
+    assert_eq!(failed[0].failed_reason.as_deref(), Some("cancelled"));
+}
+
+/// Test listing failed jobs with their failure history, and retrying or moving them by filter.
+#[tokio::test]
+async fn test_failed_retry_and_move_by_filter() {
+    let mut service = InMemoryQueueService::new();
+    for (id, reason) in [("job_1", "connection refused"), ("job_2", "invalid payload"), ("job_3", "connection reset")] {
+        run(&mut service, &["add", "testQueue", "message", "--id", id]).await.unwrap();
+        let mut job = service.jobs("testQueue", JobState::Waiting).into_iter().find(|job| job.id == id).unwrap();
+        service.remove_job("testQueue", id).await.unwrap();
+        job.record_failure(reason, None, 5);
+        service.move_to_failed("testQueue", job).await.unwrap();
+    }
+
+    let listed = run_json(&mut service, &["failed", "testQueue", "--reason", "connection"]).await;
+    assert_eq!(listed.as_array().unwrap().len(), 2);
+    assert_eq!(listed[0]["failures"][0]["attempt"], 1);
+    let text = run(&mut service, &["failed", "testQueue", "--reason", "payload"]).await.unwrap();
+    assert!(text.starts_with("job_2\t1\t") && text.ends_with("\tinvalid payload\n"), "{}", text);
+    let details = run(&mut service, &["get", "testQueue", "job_2"]).await.unwrap();
+    assert!(details.contains("\nattempt 1:\t") && details.ends_with("5ms\tinvalid payload\n"), "{}", details);
+
+    let retried = run_json(&mut service, &["retry", "testQueue", "--reason", "refused", "--failed-within", "60"]).await;
+    assert_eq!(retried["retried"], serde_json::json!(["job_1"]));
+    let moved = run_json(&mut service, &["move", "testQueue", "job_2", "--to", "review"]).await;
+    assert_eq!(moved["moved"], serde_json::json!(["job_2"]));
+    let missing = run(&mut service, &["move", "testQueue", "job_2", "--to", "review"]).await.unwrap_err();
+    assert_eq!(missing.exit_code(), EXIT_NOT_FOUND);
+    assert_eq!(run(&mut service, &["move", "testQueue", "--all", "--to", "review"]).await.unwrap(), "Moved 1 jobs from testQueue to review\n");
+    assert_eq!(service.jobs("review", JobState::Waiting).len(), 2);
+    assert_eq!(service.jobs("testQueue", JobState::Failed).len(), 0);
+}
+
+/// Test that invalid arguments are rejected before running the command.
+#[test]
+fn test_argument_validation() {
+    for args in [
+        &["bullmq", "retry", "testQueue"][..],
+        &["bullmq", "retry", "testQueue", "job_1", "--all"],
+        &["bullmq", "retry", "testQueue", "job_1", "--reason", "timeout"],
+        &["bullmq", "move", "testQueue", "--all"],
+        &["bullmq", "move", "testQueue", "--to", "review"],
+        &["bullmq", "move", "testQueue", "job_1", "--to", ""],
+        &["bullmq", "list", "testQueue", "--state", "done"],
+        &["bullmq", "list", "testQueue", "--limit", "0"],
+        &["bullmq", "progress", "testQueue", "job_1", "101"],
+        &["bullmq", "add", "testQueue"],
+        &["bullmq", "add", "testQueue", "hello", "--timeout", "0"],
+    ] {
+        let e = Cli::try_parse_from(args).unwrap_err();
+        assert_eq!(e.exit_code(), EXIT_USAGE, "{:?}", args);
+    }
+}
+
+/// Test that `work` skips a malformed job instead of panicking, and processes the next one.
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_work_skips_malformed_job() {
+    use bullmq_rust::sqlite_queue_service::SqliteQueueService;
+
+    let path = std::env::temp_dir().join(format!("bullmq_cli_malformed_test_{}.sqlite3", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut service = SqliteQueueService::open(&path).unwrap();
+    rusqlite::Connection::open(&path)
+        .unwrap()
+        .execute("INSERT INTO jobs (queue, state, member, score, job_id) VALUES ('testQueue', 'waiting', 'not a job', 0, '')", [])
+        .unwrap();
+    let cli = Cli::try_parse_from(["bullmq", "add", "testQueue", "hello", "--id", "job_1"]).unwrap();
+    cli.execute(&mut service, &mut Vec::new()).await.unwrap();
+
+    let cli = Cli::try_parse_from(["bullmq", "--json", "work", "testQueue", "--max-jobs", "1", "--refresh", "50"]).unwrap();
+    let mut out = Vec::new();
+    cli.execute(&mut service, &mut out).await.unwrap();
+    assert_eq!(serde_json::from_slice::<Value>(&out).unwrap()["processed"], 1);
+    assert_eq!(service.count_jobs("testQueue").await.unwrap(), 0);
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,22 @@
+// Each test crate uses its own subset of the fixtures.
+#![allow(dead_code)]
+
+use bullmq_rust::job_model::JobData;
+use chrono::Utc;
+
+/// Creates a job timestamped now, with a message derived from its ID.
+///
+/// Other fields are set with the struct update syntax, e.g. `JobData { delay: Some(2), ..job("job_1") }`.
+pub fn job(id: &str) -> JobData {
+    JobData {
+        id: id.to_string(),
+        message: format!("payload of {}", id),
+        timestamp: Utc::now().to_rfc3339(),
+        ..Default::default()
+    }
+}
+
+/// Returns the ID of a fetched job, which must be present.
+pub fn job_id(next_job: Option<Vec<String>>) -> String {
+    serde_json::from_str::<JobData>(&next_job.unwrap()[0]).unwrap().id
+}
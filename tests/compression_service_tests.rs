@@ -0,0 +1,123 @@
+#![cfg(all(feature = "test-util", feature = "zstd", feature = "gzip"))]
+
+use bullmq_rust::compression_service::{compress_job, decompress_job, CompressedQueueService};
+use bullmq_rust::config_service::CompressionConfig;
+use bullmq_rust::in_memory_queue_service::InMemoryQueueService;
+use bullmq_rust::job_model::{Compression, JobData, JobState};
+use bullmq_rust::queue_trigger_service::QueueTriggerService;
+use bullmq_rust::QueueServiceTrait;
+mod common;
+use common::job;
+
+/// A JSON payload of about 20 KB, which compresses well.
+fn large_message() -> String {
+    let items: Vec<String> = (0..500).map(|i| format!(r#"{{"sku":"item-{}","quantity":1}}"#, i % 7)).collect();
+    format!(r#"{{"items":[{}]}}"#, items.join(","))
+}
+
+fn config(algorithm: Compression) -> CompressionConfig {
+    CompressionConfig { algorithm, threshold_bytes: 1024 }
+}
+
+/// Test that messages above the threshold are compressed and restored with both algorithms.
+#[test]
+fn test_compress_and_decompress_job() {
+    for algorithm in [Compression::Zstd, Compression::Gzip] {
+        let mut compressed = JobData { message: large_message(), ..job("job_1") };
+        assert!(compress_job(&mut compressed, &config(algorithm)).unwrap());
+        assert_eq!(compressed.compression, Some(algorithm));
+        assert!(compressed.message.len() < large_message().len() / 4, "{}", compressed.message.len());
+        assert!(!compress_job(&mut compressed, &config(algorithm)).unwrap(), "a compressed job is left unchanged");
+
+        let json = serde_json::to_string(&compressed).unwrap();
+        assert!(json.contains(&format!(r#""compression":"{}""#, algorithm.as_str())));
+        decompress_job(&mut compressed).unwrap();
+        assert_eq!((compressed.message, compressed.compression), (large_message(), None));
+    }
+
+    let mut small = JobData { message: "hello".to_string(), ..job("job_2") };
+    assert!(!compress_job(&mut small, &config(Compression::Zstd)).unwrap());
+    let alphabet = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let random: String = (0..2000)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            char::from(alphabet[(state % 64) as usize])
+        })
+        .collect();
+    let mut incompressible = JobData { message: random.clone(), ..job("job_3") };
+    assert!(!compress_job(&mut incompressible, &config(Compression::Gzip)).unwrap());
+    assert_eq!(incompressible.message, random);
+
+    let mut corrupted = JobData { compression: Some(Compression::Zstd), message: "bm90IHpzdGQ=".to_string(), ..job("job_4") };
+    assert!(decompress_job(&mut corrupted).is_err());
+}
+
+/// Test that the service stores large messages compressed, leaves small ones plain, and reads both back plain.
+#[tokio::test]
+async fn test_compressed_queue_service() {
+    let backend = InMemoryQueueService::new();
+    let mut service = CompressedQueueService::new(Box::new(backend.clone()), Some(config(Compression::Zstd)));
+    service.add_jobs("testQueue", vec![JobData { message: large_message(), ..job("large") }, JobData { message: "hello".to_string(), ..job("small") }]).await.unwrap();
+
+    let stored = backend.jobs("testQueue", JobState::Waiting);
+    let large = stored.iter().find(|job| job.id == "large").unwrap();
+    assert_eq!(large.compression, Some(Compression::Zstd));
+    assert!(large.message.len() < large_message().len());
+    let small = stored.iter().find(|job| job.id == "small").unwrap();
+    assert_eq!((small.compression, small.message.as_str()), (None, "hello"));
+
+    let (_, found) = service.get_job("testQueue", "large").await.unwrap().unwrap();
+    assert_eq!(found.message, large_message());
+    let listed = service.list_jobs("testQueue", JobState::Waiting, 0, 10).await.unwrap();
+    assert!(listed.iter().all(|job| job.compression.is_none()));
+
+    let mut reader = CompressedQueueService::new(Box::new(backend.clone()), None);
+    let mut fetched = Vec::new();
+    for _ in 0..2 {
+        let next_job = reader.get_next_job("testQueue").await.unwrap().unwrap();
+        fetched.push(serde_json::from_str::<JobData>(&next_job[0]).unwrap());
+    }
+    assert!(fetched.iter().any(|job| job.message == large_message() && job.compression.is_none()));
+
+    service.move_to_failed("testQueue", JobData { message: large_message(), ..job("failed") }).await.unwrap();
+    assert_eq!(backend.jobs("testQueue", JobState::Failed)[0].compression, Some(Compression::Zstd));
+    let failed = service.list_failed_jobs("testQueue", &Default::default(), 0, 10).await.unwrap();
+    assert_eq!(failed[0].message, large_message());
+
+    service.log_job_status("testQueue", &JobData { message: large_message(), ..job("logged") }, "completed").await.unwrap();
+    let stored = backend.clone().read_logs("testQueue", Some("logged"), Default::default()).await.unwrap();
+    assert!(stored[0].fields["message"].as_str().unwrap().len() < large_message().len() / 4, "the queue log keeps the compressed message");
+    assert_eq!(stored[0].fields["compression"], "zstd");
+    let entries = service.read_logs("testQueue", Some("logged"), Default::default()).await.unwrap();
+    assert_eq!(entries[0].fields["message"], large_message());
+    assert!(!entries[0].fields.contains_key("compression"));
+}
+
+/// Test that a worker processes compressed jobs plain, and fails jobs whose message cannot be decompressed.
+#[tokio::test]
+async fn test_worker_reads_compressed_jobs() {
+    let mut backend = InMemoryQueueService::new();
+    let corrupted = JobData { compression: Some(Compression::Gzip), message: "bm90IGd6aXA=".to_string(), ..job("corrupted") };
+    backend.add_job("testQueue", corrupted).await.unwrap();
+    let mut service = CompressedQueueService::new(Box::new(backend.clone()), Some(config(Compression::Gzip)));
+    service.add_job("testQueue", JobData { message: large_message(), ..job("large") }).await.unwrap();
+
+    let trigger = QueueTriggerService::new("testQueue".to_string())
+        .with_handler(|job, _token| async move {
+            if job.message.starts_with(r#"{"items":"#) {
+                Ok(())
+            } else {
+                Err("unexpected message".to_string())
+            }
+        });
+    assert_eq!(trigger.run(&mut service, 50, Some(2)).await, 2);
+
+    let failed = backend.jobs("testQueue", JobState::Failed);
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].id, "corrupted");
+    assert_eq!(failed[0].failed_reason.as_deref(), Some("cannot decompress the gzip message of the job"));
+    assert_eq!(failed[0].compression, Some(Compression::Gzip), "the stored message is kept");
+}
@@ -0,0 +1,43 @@
+use bullmq_rust::connection_service::{RetryMethod, RetryPolicy};
+use redis::{ErrorKind, RedisError};
+use std::time::Duration;
+
+fn error(kind: ErrorKind) -> RedisError {
+    RedisError::from((kind, "test error"))
+}
+
+/// Test the retry method chosen for each kind of error.
+#[test]
+fn test_retry_method_for_error() {
+    assert_eq!(RetryMethod::for_error(&error(ErrorKind::Moved)), Some(RetryMethod::MovedRedirect));
+    assert_eq!(RetryMethod::for_error(&error(ErrorKind::Ask)), Some(RetryMethod::AskRedirect));
+    assert_eq!(RetryMethod::for_error(&error(ErrorKind::TryAgain)), Some(RetryMethod::WaitAndRetry));
+    assert_eq!(RetryMethod::for_error(&error(ErrorKind::ClusterDown)), Some(RetryMethod::WaitAndRetry));
+    assert_eq!(RetryMethod::for_error(&error(ErrorKind::IoError)), Some(RetryMethod::Reconnect));
+    assert_eq!(RetryMethod::for_error(&error(ErrorKind::TypeError)), None);
+}
+
+/// Test the delays applied by the default retry policy.
+#[test]
+fn test_retry_policy_delay() {
+    let policy = RetryPolicy::default();
+
+    assert_eq!(policy.retry_delay(&error(ErrorKind::Moved), 0), Some(Duration::ZERO));
+    assert_eq!(policy.retry_delay(&error(ErrorKind::TryAgain), 0), Some(Duration::from_millis(100)));
+    assert_eq!(policy.retry_delay(&error(ErrorKind::TryAgain), 2), Some(Duration::from_millis(400)));
+    assert_eq!(policy.retry_delay(&error(ErrorKind::TryAgain), policy.max_retries), None);
+    assert_eq!(policy.retry_delay(&error(ErrorKind::ResponseError), 0), None);
+}
+
+/// Test that commands which may already have run are never replayed.
+#[test]
+fn test_retry_policy_never_replays_ambiguous_errors() {
+    let policy = RetryPolicy::default();
+    let timeout = RedisError::from(std::io::Error::new(std::io::ErrorKind::TimedOut, "test timeout"));
+
+    assert_eq!(policy.retry_delay(&error(ErrorKind::IoError), 0), None);
+    assert_eq!(policy.retry_delay(&timeout, 0), None);
+    assert!(!RetryMethod::Reconnect.is_replay_safe());
+    assert!(!RetryMethod::RetryImmediately.is_replay_safe());
+    assert!(RetryMethod::MovedRedirect.is_replay_safe());
+}
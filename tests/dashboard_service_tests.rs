@@ -0,0 +1,37 @@
+#![cfg(all(feature = "dashboard", feature = "test-util"))]
+
+use axum::body::Body;
+use axum::http::header::{CONTENT_TYPE, LOCATION};
+use axum::http::{Request, StatusCode};
+use bullmq_rust::api_service::ApiService;
+use bullmq_rust::in_memory_queue_service::InMemoryQueueService;
+use http_body_util::BodyExt;
+use tower::ServiceExt;
+
+/// Test that the bundled assets are served next to the API routes.
+#[tokio::test]
+async fn test_dashboard_assets() {
+    let router = ApiService::new(Box::new(InMemoryQueueService::new())).router();
+    let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+
+    let response = router.clone().oneshot(get("/")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(response.headers()[LOCATION], "/dashboard/");
+
+    for (uri, content_type, needle) in [
+        ("/dashboard/", "text/html; charset=utf-8", "/dashboard/app.js"),
+        ("/dashboard/app.js", "text/javascript; charset=utf-8", "/queues"),
+        ("/dashboard/style.css", "text/css; charset=utf-8", "body"),
+    ] {
+        let response = router.clone().oneshot(get(uri)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+        assert_eq!(response.headers()[CONTENT_TYPE], content_type);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert!(String::from_utf8_lossy(&body).contains(needle), "{}", uri);
+    }
+
+    let response = router.clone().oneshot(get("/queues")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = router.oneshot(get("/dashboard/missing.js")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
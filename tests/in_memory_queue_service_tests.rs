@@ -0,0 +1,245 @@
+#![cfg(feature = "test-util")]
+
+use bullmq_rust::in_memory_queue_service::InMemoryQueueService;
+use bullmq_rust::job_model::{FailedJobFilter, JobData, JobState};
+use bullmq_rust::log_model::LogRange;
+use bullmq_rust::metrics_model::{MetricKind, MetricsRange};
+use bullmq_rust::QueueServiceTrait;
+use chrono::Utc;
+use std::time::Duration;
+mod common;
+use common::{job, job_id};
+
+/// Test that jobs are fetched in the order they were added, and that a delayed job is not fetched before it is due.
+#[tokio::test]
+async fn test_ordering_and_delay() {
+    let mut service = InMemoryQueueService::new();
+    service.add_job("testQueue", JobData { delay: Some(60), ..job("delayed") }).await.unwrap();
+    service.add_job("testQueue", job("first")).await.unwrap();
+    service.add_job("testQueue", job("second")).await.unwrap();
+    assert_eq!(service.count_jobs("testQueue").await.unwrap(), 3);
+
+    assert_eq!(job_id(service.get_next_job("testQueue").await.unwrap()), "first");
+    assert_eq!(job_id(service.get_next_job("testQueue").await.unwrap()), "second");
+    assert!(service.get_next_job("testQueue").await.is_err(), "the delayed job is not due yet");
+    assert!(service.wait_for_next_job("testQueue", 0.05).await.unwrap().is_none());
+    assert_eq!(service.count_jobs("testQueue").await.unwrap(), 1);
+
+    let added_ms = Utc::now().timestamp_millis();
+    service.add_job("testQueue", JobData { delay: Some(1), ..job("soon") }).await.unwrap();
+    let next_job = service.wait_for_next_job("testQueue", 3.0).await.unwrap().unwrap();
+    let score = next_job[1].parse::<i64>().unwrap();
+    assert!(score >= added_ms + 1000 && score <= Utc::now().timestamp_millis(), "scored with its due time in milliseconds");
+    assert_eq!(job_id(Some(next_job)), "soon");
+}
+
+/// Test that a job added after a given delay is held until it is due, and keeps its own delay.
+#[tokio::test]
+async fn test_add_job_after() {
+    let mut service = InMemoryQueueService::new();
+    service.add_job_after("testQueue", job("job_1"), 60).await.unwrap();
+    assert!(service.get_next_job("testQueue").await.is_err(), "the job is not due yet");
+    let listed = service.list_jobs("testQueue", JobState::Waiting, 0, usize::MAX).await.unwrap();
+    assert_eq!(listed[0].delay, None);
+}
+
+/// Test that a failed job is aged by the time it failed, whatever its delay.
+#[tokio::test]
+async fn test_failed_job_ignores_delay() {
+    let mut service = InMemoryQueueService::new();
+    service.move_to_failed("testQueue", JobData { delay: Some(3600), ..job("job_1") }).await.unwrap();
+    assert_eq!(service.clean_jobs("testQueue", JobState::Failed, 0).await.unwrap(), 1);
+}
+
+/// Test that a waiting worker is woken up by a job added from a clone.
+#[tokio::test]
+async fn test_wait_for_next_job() {
+    let mut worker = InMemoryQueueService::new();
+    let mut producer = worker.clone();
+    assert!(worker.wait_for_next_job("testQueue", 0.05).await.unwrap().is_none());
+
+    let waiting = tokio::spawn(async move { worker.wait_for_next_job("testQueue", 0.0).await });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    producer.add_job("testQueue", job("job_1")).await.unwrap();
+    let next_job = tokio::time::timeout(Duration::from_secs(1), waiting).await.unwrap().unwrap().unwrap();
+    assert_eq!(job_id(next_job), "job_1");
+}
+
+/// Test the failed queue, progress and removal of a job.
+#[tokio::test]
+async fn test_failed_progress_and_remove() {
+    let mut service = InMemoryQueueService::new();
+    service.move_to_failed("testQueue", job("job_1")).await.unwrap();
+    assert_eq!(service.count_jobs_by_state("testQueue", JobState::Failed).await.unwrap(), 1);
+    assert_eq!(service.jobs("testQueue", JobState::Failed)[0].id, "job_1");
+
+    assert_eq!(service.get_job_progress("testQueue", "job_1").await.unwrap(), None);
+    service.update_job_progress("testQueue", "job_1", 40).await.unwrap();
+    assert_eq!(service.get_job_progress("testQueue", "job_1").await.unwrap(), Some(40));
+
+    assert!(service.remove_job("testQueue", "job_1").await.unwrap());
+    assert!(!service.remove_job("testQueue", "job_1").await.unwrap());
+    assert_eq!(service.count_jobs_by_state("testQueue", JobState::Failed).await.unwrap(), 0);
+    assert_eq!(service.get_job_progress("testQueue", "job_1").await.unwrap(), None);
+}
+
+/// Test the queue log and the capped job logs.
+#[tokio::test]
+async fn test_logs() {
+    let mut service = InMemoryQueueService::new().with_max_job_log_lines(2).with_max_log_entries(3);
+    for id in ["job_1", "job_2", "job_1", "job_2"] {
+        service.log_job_status("testQueue", &job(id), "Processing").await.unwrap();
+    }
+    let entries = service.read_logs("testQueue", None, LogRange::default()).await.unwrap();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].job_id.as_deref(), Some("job_2"));
+    let entries = service
+        .read_logs("testQueue", Some("job_1"), LogRange { offset: 0, limit: 10 })
+        .await
+        .unwrap();
+    assert_eq!(entries.len(), 1);
+
+    for line in ["first", "second", "third"] {
+        service.job_log("testQueue", "job_1", line).await.unwrap();
+    }
+    assert_eq!(service.get_job_logs("testQueue", "job_1", 0, -1).await.unwrap(), vec!["second", "third"]);
+    assert_eq!(service.get_job_logs("testQueue", "job_1", -1, -1).await.unwrap(), vec!["third"]);
+    assert!(service.get_job_logs("testQueue", "job_2", 0, -1).await.unwrap().is_empty());
+
+    service.add_job("testQueue", job("job_1")).await.unwrap();
+    assert!(service.remove_job("testQueue", "job_1").await.unwrap());
+    assert!(service.get_job_logs("testQueue", "job_1", 0, -1).await.unwrap().is_empty());
+}
+
+/// Test that completed and failed jobs are counted in the metrics.
+#[tokio::test]
+async fn test_metrics() {
+    let mut service = InMemoryQueueService::new();
+    service.mark_completed("testQueue", &job("job_1"), 120).await.unwrap();
+    service.move_to_failed("testQueue", job("job_2")).await.unwrap();
+
+    let range = MetricsRange::last_minutes(1);
+    let completed = service.get_metrics("testQueue", MetricKind::Completed, range).await.unwrap();
+    assert_eq!(completed.iter().map(|point| point.count).sum::<u64>(), 1);
+    let failed = service.get_metrics("testQueue", MetricKind::Failed, range).await.unwrap();
+    assert_eq!(failed.iter().map(|point| point.count).sum::<u64>(), 1);
+    let processing = service.get_metrics("testQueue", MetricKind::ProcessingTime, range).await.unwrap();
+    let in_bucket: u64 = processing
+        .iter()
+        .flat_map(|point| &point.buckets)
+        .filter(|bucket| bucket.le_ms == 250)
+        .map(|bucket| bucket.count)
+        .sum();
+    assert_eq!(in_bucket, 1);
+}
+
+/// Test listing, retrying, pausing and cleaning jobs.
+#[tokio::test]
+async fn test_list_retry_pause_and_clean() {
+    let mut service = InMemoryQueueService::new();
+    service
+        .add_jobs("testQueue", vec![job("job_1"), JobData { delay: Some(60), ..job("job_2") }, job("job_3")])
+        .await
+        .unwrap();
+    let ids = |jobs: Vec<JobData>| jobs.into_iter().map(|job| job.id).collect::<Vec<_>>();
+    assert_eq!(ids(service.list_jobs("testQueue", JobState::Waiting, 0, usize::MAX).await.unwrap()), ["job_1", "job_3", "job_2"]);
+    assert_eq!(ids(service.list_jobs("testQueue", JobState::Waiting, 1, 1).await.unwrap()), ["job_3"]);
+
+    service.remove_job("testQueue", "job_1").await.unwrap();
+    service.move_to_failed("testQueue", job("job_1")).await.unwrap();
+    let (state, found) = service.get_job("testQueue", "job_1").await.unwrap().unwrap();
+    assert_eq!((state, found.id.as_str()), (JobState::Failed, "job_1"));
+    assert!(service.get_job("testQueue", "missing").await.unwrap().is_none());
+    assert!(service.retry_job("testQueue", "job_1").await.unwrap());
+    assert!(!service.retry_job("testQueue", "job_1").await.unwrap());
+    assert_eq!(service.count_jobs_by_state("testQueue", JobState::Failed).await.unwrap(), 0);
+
+    service.pause_queue("testQueue").await.unwrap();
+    assert!(service.is_paused("testQueue").await.unwrap());
+    assert!(service.get_next_job("testQueue").await.unwrap().is_none());
+    assert!(service.wait_for_next_job("testQueue", 0.05).await.unwrap().is_none());
+    service.resume_queue("testQueue").await.unwrap();
+    assert!(!service.is_paused("testQueue").await.unwrap());
+    assert_eq!(job_id(service.get_next_job("testQueue").await.unwrap()), "job_3");
+
+    service.update_job_progress("testQueue", "job_1", 50).await.unwrap();
+    assert_eq!(service.clean_jobs("testQueue", JobState::Waiting, 3600).await.unwrap(), 0);
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert_eq!(service.clean_jobs("testQueue", JobState::Waiting, 0).await.unwrap(), 1, "the delayed job is not due yet");
+    assert_eq!(ids(service.list_jobs("testQueue", JobState::Waiting, 0, usize::MAX).await.unwrap()), ["job_2"]);
+    assert_eq!(service.get_job_progress("testQueue", "job_1").await.unwrap(), None);
+}
+
+/// Test that the queues holding any data are listed, sorted.
+#[tokio::test]
+async fn test_list_queues() {
+    let mut service = InMemoryQueueService::new();
+    assert!(service.list_queues().await.unwrap().is_empty());
+    service.add_job("emails", job("job_1")).await.unwrap();
+    service.move_to_failed("reports", job("job_2")).await.unwrap();
+    service.pause_queue("audit").await.unwrap();
+    service.count_jobs("unknown").await.unwrap();
+    assert_eq!(service.list_queues().await.unwrap(), ["audit", "emails", "reports"]);
+
+    service.resume_queue("audit").await.unwrap();
+    assert_eq!(service.list_queues().await.unwrap(), ["emails", "reports"]);
+}
+
+/// Test that a cancellation request is consumed once, and dropped with the job.
+#[tokio::test]
+async fn test_cancel_job() {
+    let mut service = InMemoryQueueService::new();
+    assert!(!service.take_job_cancellation("testQueue", "job_1").await.unwrap());
+    service.cancel_job("testQueue", "job_1").await.unwrap();
+    assert_eq!(service.list_queues().await.unwrap(), ["testQueue"]);
+    assert!(service.take_job_cancellation("testQueue", "job_1").await.unwrap());
+    assert!(!service.take_job_cancellation("testQueue", "job_1").await.unwrap());
+
+    service.cancel_job("testQueue", "job_2").await.unwrap();
+    assert!(!service.take_job_cancellation("otherQueue", "job_2").await.unwrap());
+    service.remove_job("testQueue", "job_2").await.unwrap();
+    assert!(!service.take_job_cancellation("testQueue", "job_2").await.unwrap());
+}
+
+/// Test listing failed jobs by reason and age, then retrying and moving them in bulk or one at a time.
+#[tokio::test]
+async fn test_failed_jobs_retry_and_move() {
+    let mut service = InMemoryQueueService::new();
+    let failed = |id: &str, reason: &str| {
+        let mut job = job(id);
+        job.record_failure(reason, None, 10);
+        job
+    };
+    service.move_to_failed("testQueue", failed("job_1", "Timed out after 100ms")).await.unwrap();
+    service.move_to_failed("testQueue", failed("job_2", "connection refused")).await.unwrap();
+    service.move_to_failed("testQueue", failed("job_3", "connection reset")).await.unwrap();
+    service.move_to_failed("testQueue", job("job_4")).await.unwrap();
+    let ids = |jobs: Vec<JobData>| jobs.into_iter().map(|job| job.id).collect::<Vec<_>>();
+
+    let connection = FailedJobFilter { reason: Some("CONNECTION".to_string()), ..Default::default() };
+    let mut listed = ids(service.list_failed_jobs("testQueue", &connection, 0, usize::MAX).await.unwrap());
+    listed.sort();
+    assert_eq!(listed, ["job_2", "job_3"]);
+    let recent = FailedJobFilter { failed_within_secs: Some(60), ..Default::default() };
+    assert_eq!(service.list_failed_jobs("testQueue", &recent, 0, usize::MAX).await.unwrap().len(), 3);
+    assert_eq!(service.list_failed_jobs("testQueue", &FailedJobFilter::default(), 1, 2).await.unwrap().len(), 2);
+
+    let mut retried = service.retry_failed_jobs("testQueue", &connection).await.unwrap();
+    retried.sort();
+    assert_eq!(retried, ["job_2", "job_3"]);
+    assert_eq!(service.count_jobs_by_state("testQueue", JobState::Waiting).await.unwrap(), 2);
+
+    // The target queue is served by another service, as when it is configured on another backend.
+    let mut review = InMemoryQueueService::new();
+    assert!(service.move_failed_job("testQueue", "job_1", &mut review, "review").await.unwrap());
+    assert!(!service.move_failed_job("testQueue", "job_1", &mut review, "review").await.unwrap());
+    let (state, moved) = review.get_job("review", "job_1").await.unwrap().unwrap();
+    assert_eq!(state, JobState::Waiting);
+    assert_eq!(moved.attempts_made, Some(1));
+    assert_eq!(moved.failures[0].reason, "Timed out after 100ms");
+    let all = FailedJobFilter::default();
+    assert_eq!(service.move_failed_jobs("testQueue", &all, &mut review, "review").await.unwrap(), ["job_4"]);
+    assert_eq!(service.count_jobs_by_state("testQueue", JobState::Failed).await.unwrap(), 0);
+    assert_eq!(service.count_jobs_by_state("review", JobState::Waiting).await.unwrap(), 0);
+    assert_eq!(review.count_jobs_by_state("review", JobState::Waiting).await.unwrap(), 2);
+}
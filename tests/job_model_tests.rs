@@ -0,0 +1,90 @@
+use bullmq_rust::job_model::{parse_job_lines, FailedJobFilter, JobData, JobFailure, JobLineError, JobSpec, MAX_FAILURE_HISTORY};
+use serde_json::json;
+
+/// Test the three kinds of JSON Lines jobs: full jobs, job specs and bare payloads.
+#[test]
+fn test_parse_job_lines() {
+    let input = r#"{"id": "job_1", "message": "full", "timestamp": "2024-01-01T00:00:00+00:00", "priority": 5, "delay": null, "retries": 2, "expires_in": null, "progress": null}
+
+{"id": "job_2", "message": {"type": "spec"}, "priority": 3, "delay": 10, "retries": 1}
+"bare"
+{"type": "payload"}
+"#;
+    let jobs = parse_job_lines(input).unwrap();
+    assert_eq!(jobs.len(), 4);
+    assert_eq!((jobs[0].id.as_str(), jobs[0].timestamp.as_str(), jobs[0].priority), ("job_1", "2024-01-01T00:00:00+00:00", Some(5)));
+    assert_eq!((jobs[1].id.as_str(), jobs[1].message.as_str()), ("job_2", r#"{"type":"spec"}"#));
+    assert_eq!((jobs[1].priority, jobs[1].delay, jobs[1].retries), (Some(3), Some(10), Some(1)));
+    assert_eq!(jobs[2].message, "bare");
+    assert_eq!(jobs[3].message, r#"{"type":"payload"}"#);
+    assert_ne!(jobs[2].id, jobs[3].id);
+}
+
+/// Test that an object with a `message` and fields a job spec does not have is a bare payload.
+#[test]
+fn test_parse_job_lines_payload_with_message() {
+    let jobs = parse_job_lines(r#"{"message": "hi", "user": 1}"#).unwrap();
+    assert_eq!(jobs.len(), 1);
+    let payload: serde_json::Value = serde_json::from_str(&jobs[0].message).unwrap();
+    assert_eq!(payload, json!({"message": "hi", "user": 1}));
+    assert_eq!(jobs[0].priority, None);
+}
+
+/// Test that every malformed line is reported with its line number.
+#[test]
+fn test_parse_job_lines_errors() {
+    let input = "\"ok\"\n{\"message\": \"x\", \"delay\": -5}\n\n{not json\n{\"message\": \"x\", \"priority\": \"high\"}\n";
+    let errors = parse_job_lines(input).unwrap_err();
+    assert_eq!(errors.iter().map(|e| e.line).collect::<Vec<_>>(), [2, 4, 5]);
+    assert_eq!(errors[0], JobLineError { line: 2, reason: "delay must not be negative".to_string() });
+    assert!(errors[2].to_string().starts_with("line 5: invalid job"));
+}
+
+/// Test that a job spec generates an ID unless it sets one.
+#[test]
+fn test_job_spec_into_job() {
+    let spec = JobSpec { message: json!("hello"), ..Default::default() };
+    assert!(spec.into_job().unwrap().id.starts_with("job_"));
+    let spec = JobSpec { id: Some(" ".to_string()), message: json!("hello"), ..Default::default() };
+    assert!(spec.into_job().is_err());
+    let spec = JobSpec { message: json!("hello"), timeout: Some(30_000), ..Default::default() };
+    assert_eq!(spec.into_job().unwrap().timeout, Some(30_000));
+    let spec = JobSpec { message: json!("hello"), timeout: Some(0), ..Default::default() };
+    assert!(spec.into_job().is_err());
+}
+
+/// Test that failures are counted and kept in a bounded history, omitted from the JSON while there are none.
+#[test]
+fn test_record_failure() {
+    let mut job = JobData { id: "job_1".to_string(), ..Default::default() };
+    assert!(!serde_json::to_string(&job).unwrap().contains("failures"));
+    job.record_failure("boom", Some("at handler.rs:12".to_string()), 40);
+    assert_eq!((job.attempts_made, job.failed_reason.as_deref()), (Some(1), Some("boom")));
+    assert_eq!(job.failures[0].stack.as_deref(), Some("at handler.rs:12"));
+    assert_eq!((job.failures[0].attempt, job.failures[0].duration_ms), (1, 40));
+    assert!(job.failed_at().is_some());
+
+    for attempt in 2..=MAX_FAILURE_HISTORY as u32 + 5 {
+        job.record_failure(&format!("failure {}", attempt), None, 0);
+    }
+    assert_eq!(job.failures.len(), MAX_FAILURE_HISTORY);
+    assert_eq!(job.failures[0].attempt, 6);
+    let json = serde_json::to_string(&job).unwrap();
+    assert_eq!(serde_json::from_str::<JobData>(&json).unwrap(), job);
+}
+
+/// Test that the failed job filter matches the failure reason ignoring case, and the time of the last failure.
+#[test]
+fn test_failed_job_filter() {
+    let mut job = JobData { failed_reason: Some("Connection refused".to_string()), ..Default::default() };
+    assert!(FailedJobFilter::default().matches(&job));
+    assert!(FailedJobFilter { reason: Some("connection".to_string()), ..Default::default() }.matches(&job));
+    assert!(!FailedJobFilter { reason: Some("timeout".to_string()), ..Default::default() }.matches(&job));
+
+    let recent = FailedJobFilter { failed_within_secs: Some(60), ..Default::default() };
+    assert!(!recent.matches(&job), "a job without failure history has no failure time");
+    job.failures.push(JobFailure { failed_at: "2024-01-01T00:00:00+00:00".to_string(), ..Default::default() });
+    assert!(!recent.matches(&job));
+    job.record_failure("Connection refused", None, 0);
+    assert!(recent.matches(&job));
+}
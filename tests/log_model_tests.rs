@@ -0,0 +1,38 @@
+use bullmq_rust::log_model::{LogEntry, LogLevel, LogRange};
+
+/// Test that structured entries round-trip through their JSON representation.
+#[test]
+fn test_log_entry_round_trip() {
+    let entry = LogEntry::new(LogLevel::Error, "failed")
+        .with_job_id("job_1")
+        .with_field("reason", "timeout")
+        .with_field("attempt", 2);
+
+    let raw = serde_json::to_string(&entry).unwrap();
+    assert!(raw.contains("\"level\":\"error\""));
+    assert_eq!(LogEntry::parse(&raw), entry);
+}
+
+/// Test that empty optional data is not serialized.
+#[test]
+fn test_log_entry_skips_empty_fields() {
+    let raw = serde_json::to_string(&LogEntry::new(LogLevel::Info, "completed")).unwrap();
+    assert!(!raw.contains("job_id"));
+    assert!(!raw.contains("fields"));
+}
+
+/// Test that plain entries written by older versions are still readable.
+#[test]
+fn test_log_entry_parse_legacy() {
+    let entry = LogEntry::parse("2025-01-01T00:00:00+00:00 - completed: Hello");
+    assert_eq!(entry.timestamp, "2025-01-01T00:00:00+00:00");
+    assert_eq!(entry.level, LogLevel::Info);
+    assert_eq!(entry.event, "completed: Hello");
+    assert!(entry.job_id.is_none());
+}
+
+/// Test the default log page.
+#[test]
+fn test_log_range_default() {
+    assert_eq!(LogRange::default(), LogRange { offset: 0, limit: 100 });
+}
@@ -0,0 +1,54 @@
+use bullmq_rust::metrics_model::{histogram_bucket, minute_bucket, MetricKind, MetricsRange};
+
+/// Test that timestamps are aligned on the start of their minute.
+#[test]
+fn test_minute_bucket() {
+    assert_eq!(minute_bucket(120), 120);
+    assert_eq!(minute_bucket(179), 120);
+    assert_eq!(minute_bucket(-1), -60);
+}
+
+/// Test the minutes covered by a metrics range.
+#[test]
+fn test_metrics_range_minutes() {
+    let range = MetricsRange { start: 125, end: 305 };
+    assert_eq!(range.minutes(), vec![120, 180, 240, 300]);
+
+    let range = MetricsRange { start: 300, end: 120 };
+    assert!(range.minutes().is_empty());
+
+    let range = MetricsRange::last_minutes(60);
+    assert_eq!(range.minutes().len(), 61);
+}
+
+/// Test that a range is clamped to the retention period and never reaches into the future.
+#[test]
+fn test_metrics_range_clamp_to_retention() {
+    let now = chrono::Utc::now().timestamp();
+    let range = MetricsRange { start: 0, end: i64::MAX }.clamp_to_retention(3600);
+    assert!(range.start >= now - 3600 && range.end <= now + 1, "{:?}", range);
+    assert!(range.minutes().len() <= 62);
+
+    let future = MetricsRange { start: now + 3600, end: now + 7200 }.clamp_to_retention(3600);
+    assert!(future.minutes().is_empty());
+}
+
+/// Test that durations land in the expected histogram buckets.
+#[test]
+fn test_histogram_bucket() {
+    assert_eq!(histogram_bucket(0), 10);
+    assert_eq!(histogram_bucket(10), 10);
+    assert_eq!(histogram_bucket(11), 50);
+    assert_eq!(histogram_bucket(60_000), 60_000);
+    assert_eq!(histogram_bucket(60_001), u64::MAX);
+}
+
+/// Test which metric kinds are stored as histograms.
+#[test]
+fn test_metric_kind() {
+    assert!(!MetricKind::Completed.is_histogram());
+    assert!(!MetricKind::Failed.is_histogram());
+    assert!(MetricKind::WaitTime.is_histogram());
+    assert!(MetricKind::ProcessingTime.is_histogram());
+    assert_eq!(MetricKind::ProcessingTime.as_str(), "processing_time");
+}
@@ -40,6 +40,18 @@ mock! {
         /// A `RedisResult` containing an optional job JSON string.
         pub async fn get_next_job(&mut self, queue_name: &str) -> RedisResult<Option<Vec<String>>>;
 
+        /// Waits for the next job from the specified queue.
+        ///
+        /// # Arguments
+        ///
+        /// * `queue_name` - The name of the queue to retrieve the job from.
+        /// * `timeout_secs` - The maximum time in seconds to block, `0` blocks forever.
+        ///
+        /// # Returns
+        ///
+        /// A `RedisResult` containing the job JSON string and its score, or `None` on timeout.
+        pub async fn wait_for_next_job(&mut self, queue_name: &str, timeout_secs: f64) -> RedisResult<Option<Vec<String>>>;
+
         /// Counts the number of jobs in the specified queue.
         ///
         /// # Arguments
@@ -73,8 +85,8 @@ mock! {
         ///
         /// # Returns
         ///
-        /// A `RedisResult` containing the progress value of the job.
-        pub async fn get_job_progress(&mut self, queue_name: &str, job_id: &str) -> RedisResult<u32>;
+        /// A `RedisResult` containing the progress value of the job, or `None` if none was set.
+        pub async fn get_job_progress(&mut self, queue_name: &str, job_id: &str) -> RedisResult<Option<u32>>;
 
         /// Moves a job to the failed queue.
         ///
@@ -100,5 +112,44 @@ mock! {
         ///
         /// A `RedisResult` indicating the success or failure of the operation.
         pub async fn log_job_status(&mut self, queue_name: &str, job: &JobData, status: &str) -> RedisResult<()>;
+    
+        /// Appends a line to the log of a job.
+        ///
+        /// # Arguments
+        ///
+        /// * `queue_name` - The name of the queue the job belongs to.
+        /// * `job_id` - The ID of the job.
+        /// * `line` - The line to append.
+        ///
+        /// # Returns
+        ///
+        /// A `RedisResult` containing the number of lines in the job log.
+        pub async fn job_log(&mut self, queue_name: &str, job_id: &str, line: &str) -> RedisResult<u64>;
+
+        /// Reads lines from the log of a job, oldest first.
+        ///
+        /// # Arguments
+        ///
+        /// * `queue_name` - The name of the queue the job belongs to.
+        /// * `job_id` - The ID of the job.
+        /// * `start` - The index of the first line.
+        /// * `end` - The index of the last line, inclusive.
+        ///
+        /// # Returns
+        ///
+        /// A `RedisResult` containing the requested lines.
+        pub async fn get_job_logs(&mut self, queue_name: &str, job_id: &str, start: isize, end: isize) -> RedisResult<Vec<String>>;
+
+        /// Removes a job together with its progress and log lines.
+        ///
+        /// # Arguments
+        ///
+        /// * `queue_name` - The name of the queue the job belongs to.
+        /// * `job_id` - The ID of the job.
+        ///
+        /// # Returns
+        ///
+        /// A `RedisResult` containing `true` if the job was found in a queue.
+        pub async fn remove_job(&mut self, queue_name: &str, job_id: &str) -> RedisResult<bool>;
     }
 }
@@ -0,0 +1,68 @@
+#![cfg(feature = "prometheus")]
+
+use bullmq_rust::prometheus_service::PrometheusService;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+async fn scrape(addr: std::net::SocketAddr, path: &str) -> String {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n", path);
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+/// Test scraping the metrics endpoint.
+#[tokio::test]
+async fn test_scrape_metrics_endpoint() {
+    let prometheus = Arc::new(PrometheusService::new().unwrap());
+    prometheus.set_queue_depth("testQueue", "waiting", 3);
+    prometheus.set_queue_depth("testQueue", "failed", 1);
+    prometheus.set_concurrency("testQueue", 2);
+    prometheus.job_started("testQueue");
+    prometheus.observe_completed("testQueue", 0.2);
+    prometheus.observe_failed("testQueue");
+    prometheus.observe_retry("testQueue");
+
+    let (addr, server) = prometheus.clone().serve("127.0.0.1:0".parse().unwrap()).await.unwrap();
+
+    let response = scrape(addr, "/metrics").await;
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+    assert!(response.contains("bullmq_queue_jobs{queue=\"testQueue\",state=\"waiting\"} 3"));
+    assert!(response.contains("bullmq_queue_jobs{queue=\"testQueue\",state=\"failed\"} 1"));
+    assert!(response.contains("bullmq_jobs_completed_total{queue=\"testQueue\"} 1"));
+    assert!(response.contains("bullmq_jobs_failed_total{queue=\"testQueue\"} 1"));
+    assert!(response.contains("bullmq_job_retries_total{queue=\"testQueue\"} 1"));
+    assert!(response.contains("bullmq_job_duration_seconds_count{queue=\"testQueue\"} 1"));
+    assert!(response.contains("bullmq_worker_utilization{queue=\"testQueue\"} 0.5"));
+
+    let response = scrape(addr, "/metrics?name[]=bullmq_queue_jobs").await;
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+
+    let response = scrape(addr, "/unknown").await;
+    assert!(response.starts_with("HTTP/1.1 404 Not Found"));
+
+    server.abort();
+}
+
+/// Test that a request whose headers arrive in several packets is answered.
+#[tokio::test]
+async fn test_scrape_request_in_several_writes() {
+    let prometheus = Arc::new(PrometheusService::new().unwrap());
+    prometheus.set_queue_depth("testQueue", "waiting", 3);
+    let (addr, server) = prometheus.serve("127.0.0.1:0".parse().unwrap()).await.unwrap();
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(b"GET /metrics HTTP/1.1\r\n").await.unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    let padding = format!("Host: localhost\r\nX-Padding: {}\r\nConnection: close\r\n\r\n", "a".repeat(2048));
+    stream.write_all(padding.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+    assert!(response.contains("bullmq_queue_jobs{queue=\"testQueue\",state=\"waiting\"} 3"));
+
+    server.abort();
+}
@@ -0,0 +1,59 @@
+use bullmq_rust::metrics_model::MetricKind;
+use bullmq_rust::queue_keys::{escape_segment, unescape_segment, QueueKeys};
+
+/// Test that every key of a queue shares the same hash tag.
+#[test]
+fn test_queue_keys_share_hash_tag() {
+    let keys = QueueKeys::new("emails");
+    let all = [
+        keys.wait(),
+        keys.failed(),
+        keys.stream(),
+        keys.delayed(),
+        keys.paused(),
+        keys.log(),
+        keys.job("job_1"),
+        keys.job_logs("job_1"),
+        keys.metric(MetricKind::Completed, 120),
+    ];
+    for key in &all {
+        assert!(key.starts_with("bull:{emails}:"), "{}", key);
+    }
+    assert_eq!(keys.failed(), "bull:{emails}:failed");
+    assert_eq!(keys.metric(MetricKind::WaitTime, 120), "bull:{emails}:metrics:wait_time:120");
+}
+
+/// Test that the prefix namespaces every key.
+#[test]
+fn test_queue_keys_prefix() {
+    assert_eq!(QueueKeys::new("emails").wait(), "bull:{emails}:wait");
+    let keys = QueueKeys::with_prefix("tenant_a", "emails");
+    assert_eq!(keys.wait(), "tenant_a:{emails}:wait");
+    assert_eq!(keys.job("job_1"), "tenant_a:{emails}:job:job_1");
+    assert_ne!(keys.wait(), QueueKeys::with_prefix("tenant_b", "emails").wait());
+}
+
+/// Test that job IDs and queue names can not collide with other keys.
+#[test]
+fn test_queue_keys_collision_safe() {
+    let keys = QueueKeys::new("emails");
+    assert_ne!(keys.job("log"), keys.log());
+    assert_ne!(keys.job("failed"), keys.failed());
+    assert_ne!(keys.job("1:logs"), keys.job_logs("1"));
+    assert_eq!(keys.job("1:logs"), "bull:{emails}:job:1%3Alogs");
+    assert_eq!(QueueKeys::new("a}:{b").wait(), "bull:{a%7D%3A%7Bb}:wait");
+    assert_eq!(escape_segment("100%"), "100%25");
+}
+
+/// Test that the queue name of a key is recovered, escaped characters included.
+#[test]
+fn test_queue_name_of_key() {
+    for queue_name in ["emails", "a}:{b", "100%", "%3A"] {
+        let keys = QueueKeys::with_prefix("app:bull", queue_name);
+        assert_eq!(unescape_segment(&escape_segment(queue_name)), queue_name);
+        assert_eq!(QueueKeys::queue_name("app:bull", &keys.job_logs("1")).as_deref(), Some(queue_name));
+    }
+    assert_eq!(QueueKeys::queue_name("bull", "other:{emails}:wait"), None);
+    assert_eq!(QueueKeys::queue_name("bull", "bull:emails"), None);
+    assert_eq!(QueueKeys::scan_pattern("app*"), "app\\*:{*}:*");
+}
@@ -1,7 +1,9 @@
 use bullmq_rust::job_model::JobData;
 use mockall::predicate::*;
 use chrono::Utc;
+mod common;
 mod mocks;
+use common::{job, job_id};
 use mocks::mocks::MockQueueService;
 
 /// Test adding and getting a job from the queue.
@@ -18,6 +20,7 @@ async fn test_add_and_get_job() {
         retries: Some(3),
         expires_in: None,
         progress: Some(0),
+        ..Default::default()
     };
 
     // Successful case
@@ -73,6 +76,37 @@ async fn test_add_and_get_job() {
     assert!(mock_queue_service.get_next_job("testQueue").await.is_err());
 }
 
+/// Test that a blocked fetch returns the job added meanwhile, and `None` once the timeout elapses on an empty queue.
+#[tokio::test]
+#[ignore = "requires a Redis server at REDIS_URL"]
+async fn test_wait_for_next_job() {
+    use bullmq_rust::config_service::ConfigService;
+    use bullmq_rust::queue_service::QueueService;
+    use bullmq_rust::QueueServiceTrait;
+    use std::time::{Duration, Instant};
+
+    let config = ConfigService::load().unwrap();
+    let prefix = format!("bullmq_wait_test_{}", std::process::id());
+    let mut worker = QueueService::new(config.connect().await.unwrap())
+        .with_key_prefix(&prefix)
+        .with_blocking_connection(config.connect().await.unwrap());
+    let mut producer = QueueService::new(config.connect().await.unwrap()).with_key_prefix(&prefix);
+
+    let started = Instant::now();
+    assert!(worker.wait_for_next_job("waitQueue", 0.2).await.unwrap().is_none());
+    assert!(started.elapsed() >= Duration::from_millis(200));
+
+    let add = async {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        producer.add_job("waitQueue", job("late")).await.unwrap();
+    };
+    let started = Instant::now();
+    let (next_job, _) = tokio::join!(worker.wait_for_next_job("waitQueue", 5.0), add);
+    assert_eq!(job_id(next_job.unwrap()), "late");
+    assert!(started.elapsed() < Duration::from_secs(5), "the fetch returned when the job was added");
+    assert_eq!(worker.count_jobs("waitQueue").await.unwrap(), 0);
+}
+
 /// Test counting jobs in the queue.
 #[tokio::test]
 async fn test_count_jobs() {
@@ -120,7 +154,7 @@ async fn test_update_and_get_job_progress() {
         .expect_get_job_progress()
         .with(eq(queue_name), eq(job_id))
         .times(1)
-        .returning(|_, _| Ok(50));
+        .returning(|_, _| Ok(Some(50)));
 
     mock_queue_service
         .update_job_progress(queue_name, job_id, 50)
@@ -130,7 +164,7 @@ async fn test_update_and_get_job_progress() {
         .get_job_progress(queue_name, job_id)
         .await
         .unwrap();
-    assert_eq!(progress, 50);
+    assert_eq!(progress, Some(50));
 
     // Failing case
     mock_queue_service
@@ -177,6 +211,7 @@ async fn test_move_to_failed() {
         retries: Some(3),
         expires_in: None,
         progress: Some(0),
+        ..Default::default()
     };
 
     // Successful case
@@ -222,6 +257,7 @@ async fn test_log_job_status() {
         retries: Some(3),
         expires_in: None,
         progress: Some(0),
+        ..Default::default()
     };
 
     // Successful case
@@ -254,6 +290,31 @@ async fn test_log_job_status() {
         .is_err());
 }
 
+/// Test that the job log keeps its newest lines up to the cap, and is deleted with the job.
+#[tokio::test]
+#[ignore = "requires a Redis server at REDIS_URL"]
+async fn test_job_logs() {
+    use bullmq_rust::config_service::ConfigService;
+    use bullmq_rust::queue_service::QueueService;
+    use bullmq_rust::QueueServiceTrait;
+
+    let config = ConfigService::load().unwrap();
+    let prefix = format!("bullmq_job_log_test_{}", std::process::id());
+    let mut service = QueueService::new(config.connect().await.unwrap())
+        .with_key_prefix(&prefix)
+        .with_max_job_log_lines(2);
+    service.add_job("logQueue", job("logged")).await.unwrap();
+
+    for line in ["first", "second", "third"] {
+        service.job_log("logQueue", "logged", line).await.unwrap();
+    }
+    assert_eq!(service.get_job_logs("logQueue", "logged", 0, -1).await.unwrap(), vec!["second", "third"]);
+    assert_eq!(service.get_job_logs("logQueue", "logged", -1, -1).await.unwrap(), vec!["third"]);
+
+    assert!(service.remove_job("logQueue", "logged").await.unwrap());
+    assert!(service.get_job_logs("logQueue", "logged", 0, -1).await.unwrap().is_empty());
+}
+
 /// Test adding a job to the queue.
 #[tokio::test]
 async fn test_add_job() {
@@ -268,6 +329,7 @@ async fn test_add_job() {
         retries: Some(3),
         expires_in: None,
         progress: Some(0),
+        ..Default::default()
     };
 
     // Define the expectation for the add_job method
@@ -295,6 +357,7 @@ async fn test_get_next_job() {
         retries: Some(3),
         expires_in: None,
         progress: Some(0),
+        ..Default::default()
     };
 
     // Define the expectation for the get_next_job method
@@ -309,3 +372,56 @@ async fn test_get_next_job() {
     assert!(result.is_ok());
     assert_eq!(result.unwrap().unwrap()[0], "{".to_owned() + &attempt_string + "}");
 }
+
+/// Test that jobs are fetched in the order they were added, and that a delayed job is not fetched before it is due.
+#[tokio::test]
+#[ignore = "requires a Redis server at REDIS_URL"]
+async fn test_delayed_jobs_wait_until_due() {
+    use bullmq_rust::config_service::ConfigService;
+    use bullmq_rust::job_model::JobState;
+    use bullmq_rust::queue_service::QueueService;
+    use bullmq_rust::QueueServiceTrait;
+
+    let config = ConfigService::load().unwrap();
+    let prefix = format!("bullmq_delay_test_{}", std::process::id());
+    let mut service = QueueService::new(config.connect().await.unwrap())
+        .with_key_prefix(&prefix)
+        .with_blocking_connection(config.connect().await.unwrap());
+    service.add_job("delayQueue", JobData { delay: Some(2), ..job("delayed") }).await.unwrap();
+    service.add_jobs("delayQueue", vec![job("first"), job("second")]).await.unwrap();
+    assert_eq!(service.count_jobs("delayQueue").await.unwrap(), 3);
+    let listed: Vec<String> = service.list_jobs("delayQueue", JobState::Waiting, 0, 10).await.unwrap().into_iter().map(|job| job.id).collect();
+    assert_eq!(listed, ["first", "second", "delayed"]);
+
+    assert_eq!(job_id(service.get_next_job("delayQueue").await.unwrap()), "first");
+    assert_eq!(job_id(service.wait_for_next_job("delayQueue", 1.0).await.unwrap()), "second");
+    assert!(service.get_next_job("delayQueue").await.is_err(), "the delayed job is not due yet");
+    assert_eq!(job_id(service.wait_for_next_job("delayQueue", 5.0).await.unwrap()), "delayed");
+    assert_eq!(service.count_jobs("delayQueue").await.unwrap(), 0);
+}
+
+/// Test that a block longer than the response timeout neither fails nor loses the job added meanwhile.
+#[tokio::test]
+#[ignore = "requires a Redis server at REDIS_URL"]
+async fn test_block_outlasts_response_timeout() {
+    use bullmq_rust::config_service::ConfigService;
+    use std::time::Duration;
+
+    let config = ConfigService {
+        response_timeout_ms: Some(100),
+        key_prefix: format!("bullmq_timeout_test_{}", std::process::id()),
+        ..ConfigService::load().unwrap()
+    };
+    let mut service = config.queue_service().await.unwrap();
+    let mut producer = config.queue_service().await.unwrap();
+    assert_eq!(service.wait_for_next_job("timeoutQueue", 0.5).await.unwrap(), None);
+
+    let job = job("late");
+    let add = async {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        producer.add_job("timeoutQueue", job).await.unwrap();
+    };
+    let (next_job, _) = tokio::join!(service.wait_for_next_job("timeoutQueue", 2.0), add);
+    let next_job: JobData = serde_json::from_str(&next_job.unwrap().unwrap()[0]).unwrap();
+    assert_eq!(next_job.id, "late");
+}
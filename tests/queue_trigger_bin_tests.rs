@@ -0,0 +1,95 @@
+use std::process::{Command, Output, Stdio};
+use std::time::{Duration, Instant};
+
+/// Runs the `queue_trigger` binary with the given arguments; they must be rejected before it connects.
+fn queue_trigger(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_queue_trigger")).args(args).output().unwrap()
+}
+
+/// Test that unknown flags, invalid values and conflicting modes are usage errors.
+#[test]
+fn test_queue_trigger_rejects_invalid_arguments() {
+    for args in [
+        &[][..],
+        &["-q", "testQueue", "--bogus"],
+        &["-q", "testQueue", "--refresh", "soon"],
+        &["-q", "testQueue", "--job-timeout", "0"],
+        &["-q", "testQueue", "--processes", "4"],
+        &["-q", "testQueue", "--exec", "cat", "--sandbox", "cat"],
+    ] {
+        let output = queue_trigger(args);
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        assert!(String::from_utf8_lossy(&output.stderr).starts_with("error: "), "{:?}", args);
+    }
+    assert!(queue_trigger(&["--help"]).status.success());
+}
+
+/// Test that misspelled or invalid webhook options are usage errors instead of being ignored.
+#[test]
+fn test_queue_trigger_rejects_invalid_webhook_arguments() {
+    for args in [
+        &["-q", "testQueue", "--forward-url", "http://localhost/jobs", "--hmac-secert", "secret"][..],
+        &["-q", "testQueue", "--forward-url", "http://localhost/jobs", "--timeout", "ten"],
+        &["-q", "testQueue", "--forward-url", "http://localhost/jobs", "--header", "no colon"],
+        &["-q", "testQueue", "--forward-url", "http://localhost/jobs", "--exec", "cat"],
+        &["-q", "testQueue", "--hmac-secret", "secret"],
+    ] {
+        let output = queue_trigger(args);
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        assert!(String::from_utf8_lossy(&output.stderr).starts_with("error: "), "{:?}", args);
+    }
+}
+
+/// Test that an unknown output format, or a value given to `--quiet`, is a usage error.
+#[test]
+fn test_queue_trigger_rejects_invalid_output_arguments() {
+    for args in [
+        &["-q", "testQueue", "--output", "xml"][..],
+        &["-q", "testQueue", "--output"],
+        &["-q", "testQueue", "--quiet=yes"],
+        &["-q", "testQueue", "--quite"],
+    ] {
+        let output = queue_trigger(args);
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        assert!(String::from_utf8_lossy(&output.stderr).starts_with("error: "), "{:?}", args);
+    }
+}
+
+/// Test that the worker exits with an error when its queue service cannot be created, instead of waiting forever.
+#[test]
+fn test_queue_trigger_exits_when_backend_is_unavailable() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_queue_trigger"))
+        .args(["-q", "testQueue"])
+        .env("BULLMQ_BACKEND", "sqlite")
+        .env("BULLMQ_SQLITE_PATH", "/nonexistent/bullmq/queues.sqlite3")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break status;
+        }
+        if Instant::now() > deadline {
+            child.kill().unwrap();
+            panic!("queue_trigger kept running without a queue service");
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
+    assert!(!status.success());
+}
+
+/// Test that an invalid configuration is reported as an error rather than a panic.
+#[test]
+fn test_queue_trigger_reports_invalid_configuration() {
+    let output = Command::new(env!("CARGO_BIN_EXE_queue_trigger"))
+        .args(["-q", "testQueue"])
+        .env("REDIS_DB", "two")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains("REDIS_DB"), "{}", stderr);
+    assert!(!stderr.contains("panicked"), "{}", stderr);
+}
@@ -0,0 +1,184 @@
+#![cfg(all(feature = "test-util", unix))]
+
+use bullmq_rust::in_memory_queue_service::InMemoryQueueService;
+use bullmq_rust::job_model::{JobData, JobState};
+use bullmq_rust::log_model::LogRange;
+use bullmq_rust::queue_trigger_service::QueueTriggerService;
+use bullmq_rust::QueueServiceTrait;
+mod common;
+use common::job;
+
+/// Test that the exec command gets the payload on stdin and the job environment, and completes the job on exit 0.
+#[tokio::test]
+async fn test_exec_completes_job() {
+    let mut service = InMemoryQueueService::new();
+    service.add_job("testQueue", job("job_1")).await.unwrap();
+
+    let trigger = QueueTriggerService::new("testQueue".to_string())
+        .with_exec(r#"read payload; echo "$QUEUE $JOB_ID $ATTEMPT $payload" >&2"#);
+    assert_eq!(trigger.run(&mut service, 50, Some(1)).await, 1);
+
+    assert_eq!(service.count_jobs("testQueue").await.unwrap(), 0);
+    assert_eq!(service.count_jobs_by_state("testQueue", JobState::Failed).await.unwrap(), 0);
+    let lines = service.get_job_logs("testQueue", "job_1", 0, -1).await.unwrap();
+    assert_eq!(lines, ["testQueue job_1 1 payload of job_1"]);
+    let entries = service.read_logs("testQueue", Some("job_1"), LogRange::default()).await.unwrap();
+    let events: Vec<&str> = entries.iter().map(|entry| entry.event.as_str()).collect();
+    assert_eq!(events, ["completed", "active"]);
+}
+
+/// Test that a non-zero exit retries the job while it has retries left, then fails it with stderr as reason
+/// and every attempt in its failure history. The delay of the job only applies when it is first added.
+#[tokio::test]
+async fn test_exec_retries_then_fails_job() {
+    let mut service = InMemoryQueueService::new();
+    service.add_job("testQueue", JobData { retries: Some(1), delay: Some(1), ..job("job_1") }).await.unwrap();
+
+    let trigger = QueueTriggerService::new("testQueue".to_string())
+        .with_exec(r#"cat > /dev/null; echo "boom on attempt $ATTEMPT" >&2; exit 3"#);
+    assert_eq!(trigger.run(&mut service, 50, Some(1)).await, 1);
+    let retried = service.jobs("testQueue", JobState::Waiting);
+    assert_eq!(retried.len(), 1);
+    assert_eq!(retried[0].attempts_made, Some(1));
+    assert_eq!(retried[0].attempt(), 2);
+    assert_eq!(retried[0].delay, None);
+
+    assert_eq!(trigger.run(&mut service, 50, Some(1)).await, 1);
+    assert_eq!(service.count_jobs("testQueue").await.unwrap(), 0);
+    let failed = service.jobs("testQueue", JobState::Failed);
+    assert_eq!(failed[0].attempts_made, Some(2));
+    let reason = failed[0].failed_reason.as_deref().unwrap();
+    assert!(reason.contains("exit status: 3") && reason.ends_with("boom on attempt 2"), "{}", reason);
+    let attempts: Vec<u32> = failed[0].failures.iter().map(|failure| failure.attempt).collect();
+    assert_eq!(attempts, [1, 2]);
+    assert!(failed[0].failures[0].reason.ends_with("boom on attempt 1"));
+
+    let lines = service.get_job_logs("testQueue", "job_1", 0, -1).await.unwrap();
+    assert_eq!(lines[0], "boom on attempt 1");
+    assert!(lines[1].starts_with("attempt 1 failed: "));
+    assert_eq!(lines[2], "boom on attempt 2");
+}
+
+/// Test that the output formats parse case-insensitively and reject unknown names.
+#[test]
+fn test_output_format_from_str() {
+    use bullmq_rust::queue_trigger_service::OutputFormat;
+
+    assert_eq!("text".parse::<OutputFormat>(), Ok(OutputFormat::Text));
+    assert_eq!("JSON".parse::<OutputFormat>(), Ok(OutputFormat::Json));
+    assert_eq!(" jsonl ".parse::<OutputFormat>(), Ok(OutputFormat::Jsonl));
+    assert_eq!(OutputFormat::default(), OutputFormat::Text);
+    assert_eq!(OutputFormat::Jsonl.to_string(), "jsonl");
+    assert!("yaml".parse::<OutputFormat>().is_err());
+}
+
+/// Test that a quiet JSON Lines worker still processes jobs, with the exec output kept off stdout.
+#[tokio::test]
+async fn test_quiet_jsonl_worker_processes_jobs() {
+    use bullmq_rust::queue_trigger_service::OutputFormat;
+
+    let mut service = InMemoryQueueService::new();
+    service.add_job("testQueue", job("job_1")).await.unwrap();
+    service.add_job("testQueue", job("job_2")).await.unwrap();
+
+    let trigger = QueueTriggerService::new("testQueue".to_string())
+        .with_output(OutputFormat::Jsonl)
+        .with_quiet(true)
+        .with_exec("cat");
+    assert_eq!(trigger.run(&mut service, 50, Some(2)).await, 2);
+    assert_eq!(service.count_jobs("testQueue").await.unwrap(), 0);
+}
+
+/// Test that an exec command outliving the job timeout is killed and the attempt failed.
+#[tokio::test]
+async fn test_exec_times_out() {
+    let mut service = InMemoryQueueService::new();
+    service.add_job("testQueue", JobData { timeout: Some(200), ..job("job_1") }).await.unwrap();
+
+    let trigger = QueueTriggerService::new("testQueue".to_string()).with_exec("sleep 5");
+    let started = std::time::Instant::now();
+    assert_eq!(trigger.run(&mut service, 50, Some(1)).await, 1);
+    assert!(started.elapsed() < std::time::Duration::from_secs(3), "{:?}", started.elapsed());
+
+    let failed = service.jobs("testQueue", JobState::Failed);
+    assert_eq!(failed[0].failed_reason.as_deref(), Some("timed out after 200ms"));
+}
+
+/// Test that the worker's job timeout cancels the handler's token, and that the timed out attempt is retried.
+#[tokio::test]
+async fn test_handler_observes_worker_timeout() {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    let mut service = InMemoryQueueService::new();
+    service.add_job("testQueue", JobData { retries: Some(1), ..job("job_1") }).await.unwrap();
+
+    let stopped = Arc::new(AtomicU32::new(0));
+    let handler_stopped = stopped.clone();
+    let trigger = QueueTriggerService::new("testQueue".to_string())
+        .with_job_timeout(std::time::Duration::from_millis(100))
+        .with_handler(move |_job, token| {
+            let stopped = handler_stopped.clone();
+            async move {
+                token.cancelled().await;
+                stopped.fetch_add(1, Ordering::SeqCst);
+                Err("stopped".to_string())
+            }
+        });
+    assert_eq!(trigger.run(&mut service, 50, Some(2)).await, 2);
+
+    assert_eq!(stopped.load(Ordering::SeqCst), 2);
+    let failed = service.jobs("testQueue", JobState::Failed);
+    assert_eq!(failed[0].attempts_made, Some(2));
+    assert_eq!(failed[0].failed_reason.as_deref(), Some("timed out after 100ms"));
+}
+
+/// Test that cancelling a running job reaches its handler through the backend, and fails it without retrying.
+#[tokio::test]
+async fn test_cancel_running_job() {
+    let mut service = InMemoryQueueService::new();
+    service.add_job("testQueue", JobData { retries: Some(3), ..job("job_1") }).await.unwrap();
+
+    let mut producer = service.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        producer.cancel_job("testQueue", "job_1").await.unwrap();
+    });
+    let trigger = QueueTriggerService::new("testQueue".to_string()).with_handler(|_job, token| async move {
+        token.cancelled().await;
+        Ok(())
+    });
+    assert_eq!(trigger.run(&mut service, 50, Some(1)).await, 1);
+
+    assert_eq!(service.count_jobs("testQueue").await.unwrap(), 0);
+    let failed = service.jobs("testQueue", JobState::Failed);
+    assert_eq!(failed[0].attempts_made, Some(1));
+    assert_eq!(failed[0].failed_reason.as_deref(), Some("cancelled"));
+}
+
+/// Test that a job cancelled while waiting is failed without running, and that handler errors and panics fail jobs.
+#[tokio::test]
+async fn test_cancel_waiting_job_and_handler_failures() {
+    let mut service = InMemoryQueueService::new();
+    service.add_job("testQueue", job("job_1")).await.unwrap();
+    service.cancel_job("testQueue", "job_1").await.unwrap();
+
+    let trigger = QueueTriggerService::new("testQueue".to_string()).with_handler(|job, _token| async move {
+        match job.id.as_str() {
+            "job_1" => Ok(()),
+            "job_2" => Err("bad payload".to_string()),
+            _ => panic!("handler bug"),
+        }
+    });
+    assert_eq!(trigger.run(&mut service, 50, Some(1)).await, 1);
+    let failed = service.jobs("testQueue", JobState::Failed);
+    assert_eq!(failed[0].failed_reason.as_deref(), Some("cancelled"));
+
+    service.add_job("testQueue", job("job_2")).await.unwrap();
+    assert_eq!(trigger.run(&mut service, 50, Some(1)).await, 1);
+    service.add_job("testQueue", job("job_3")).await.unwrap();
+    assert_eq!(trigger.run(&mut service, 50, Some(1)).await, 1);
+    let reasons: Vec<Option<String>> = service.jobs("testQueue", JobState::Failed).into_iter().map(|job| job.failed_reason).collect();
+    assert!(reasons.contains(&Some("bad payload".to_string())), "{:?}", reasons);
+    assert!(reasons.contains(&Some("handler panicked: handler bug".to_string())), "{:?}", reasons);
+}
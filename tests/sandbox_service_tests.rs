@@ -0,0 +1,181 @@
+#![cfg(all(feature = "test-util", unix))]
+
+use bullmq_rust::in_memory_queue_service::InMemoryQueueService;
+use bullmq_rust::job_model::{JobData, JobState};
+use bullmq_rust::queue_trigger_service::QueueTriggerService;
+use bullmq_rust::sandbox_service::{serve_with, SandboxMessage, SandboxOptions, SandboxRequest};
+use bullmq_rust::QueueServiceTrait;
+use std::collections::HashSet;
+use std::io::Cursor;
+use std::time::Instant;
+mod common;
+use common::job;
+
+/// A sandbox process answering every job with a progress message, a plain line stored as a log line, and a completion.
+const COMPLETING_CHILD: &str = r#"while read -r line; do echo '{"type":"progress","progress":50}'; echo "pid $$"; echo '{"type":"completed"}'; done"#;
+
+/// Test that the jobs are completed by the sandbox process, with its progress and plain output stored.
+#[tokio::test]
+async fn test_sandbox_completes_jobs() {
+    let mut service = InMemoryQueueService::new();
+    service.add_job("testQueue", job("job_1")).await.unwrap();
+    service.add_job("testQueue", job("job_2")).await.unwrap();
+
+    let trigger = QueueTriggerService::new("testQueue".to_string()).with_sandbox(SandboxOptions::new(COMPLETING_CHILD));
+    assert_eq!(trigger.run(&mut service, 50, Some(2)).await, 2);
+
+    assert_eq!(service.count_jobs("testQueue").await.unwrap(), 0);
+    assert_eq!(service.count_jobs_by_state("testQueue", JobState::Failed).await.unwrap(), 0);
+    assert_eq!(service.get_job_progress("testQueue", "job_1").await.unwrap(), Some(50));
+    let first = service.get_job_logs("testQueue", "job_1", 0, -1).await.unwrap();
+    let second = service.get_job_logs("testQueue", "job_2", 0, -1).await.unwrap();
+    assert!(first[0].starts_with("pid "), "{:?}", first);
+    assert_eq!(first, second, "a single process handles both jobs");
+}
+
+/// Test that a process exiting before answering fails the attempt, and that the job is retried in a new process.
+#[tokio::test]
+async fn test_sandbox_crash_retries_then_fails_job() {
+    let mut service = InMemoryQueueService::new();
+    service.add_job("testQueue", JobData { retries: Some(1), ..job("job_1") }).await.unwrap();
+
+    let trigger = QueueTriggerService::new("testQueue".to_string())
+        .with_sandbox(SandboxOptions::new("read -r line; exit 7"));
+    assert_eq!(trigger.run(&mut service, 50, Some(1)).await, 1);
+    let retried = service.jobs("testQueue", JobState::Waiting);
+    assert_eq!(retried.len(), 1);
+    assert_eq!(retried[0].attempts_made, Some(1));
+
+    assert_eq!(trigger.run(&mut service, 50, Some(1)).await, 1);
+    let failed = service.jobs("testQueue", JobState::Failed);
+    assert_eq!(failed.len(), 1);
+    let reason = failed[0].failed_reason.as_deref().unwrap();
+    assert!(reason.contains("exit status: 7"), "{}", reason);
+}
+
+/// Test that a failed message fails the job with the reported reason and stack.
+#[tokio::test]
+async fn test_sandbox_failed_message_fails_job() {
+    let mut service = InMemoryQueueService::new();
+    service.add_job("testQueue", job("job_1")).await.unwrap();
+
+    let trigger = QueueTriggerService::new("testQueue".to_string())
+        .with_sandbox(SandboxOptions::new(r#"while read -r line; do echo '{"type":"failed","reason":"bad payload","stack":"at parse (job.js:3)"}'; done"#));
+    assert_eq!(trigger.run(&mut service, 50, Some(1)).await, 1);
+
+    let failed = service.jobs("testQueue", JobState::Failed);
+    assert_eq!(failed[0].failed_reason.as_deref(), Some("bad payload"));
+    assert_eq!(failed[0].failures[0].stack.as_deref(), Some("at parse (job.js:3)"));
+}
+
+/// Test that processes are replaced once they processed the maximum number of jobs.
+#[tokio::test]
+async fn test_sandbox_recycles_processes() {
+    let mut service = InMemoryQueueService::new();
+    for id in ["job_1", "job_2", "job_3"] {
+        service.add_job("testQueue", job(id)).await.unwrap();
+    }
+
+    let options = SandboxOptions::new(COMPLETING_CHILD).with_max_jobs_per_process(1);
+    let trigger = QueueTriggerService::new("testQueue".to_string()).with_sandbox(options);
+    assert_eq!(trigger.run(&mut service, 50, Some(3)).await, 3);
+
+    let mut pids = HashSet::new();
+    for id in ["job_1", "job_2", "job_3"] {
+        pids.insert(service.get_job_logs("testQueue", id, 0, -1).await.unwrap().remove(0));
+    }
+    assert_eq!(pids.len(), 3, "{:?}", pids);
+}
+
+/// Test that a pool of processes handles jobs concurrently.
+#[tokio::test]
+async fn test_sandbox_pool_processes_jobs_concurrently() {
+    let mut service = InMemoryQueueService::new();
+    for id in ["job_1", "job_2", "job_3", "job_4"] {
+        service.add_job("testQueue", job(id)).await.unwrap();
+    }
+
+    let options = SandboxOptions::new(r#"while read -r line; do sleep 0.5; echo '{"type":"completed"}'; done"#).with_processes(4);
+    let trigger = QueueTriggerService::new("testQueue".to_string()).with_sandbox(options);
+    let started = Instant::now();
+    assert_eq!(trigger.run(&mut service, 50, Some(4)).await, 4);
+    assert!(started.elapsed().as_millis() < 1500, "{:?}", started.elapsed());
+    assert_eq!(service.count_jobs("testQueue").await.unwrap(), 0);
+}
+
+/// Test that the child side of the protocol reports progress, logs, completions, errors and panics.
+#[test]
+fn test_serve_with_reports_outcomes() {
+    let mut input = String::new();
+    for id in ["ok", "error", "panic"] {
+        let request = SandboxRequest { queue: "testQueue".to_string(), attempt: 1, job: job(id) };
+        input.push_str(&serde_json::to_string(&request).unwrap());
+        input.push('\n');
+    }
+    let mut output = Vec::new();
+    let processed = serve_with(Cursor::new(input), &mut output, |request, context| match request.job.id.as_str() {
+        "ok" => {
+            context.progress(100).unwrap();
+            context.log(&request.job.message).unwrap();
+            Ok(())
+        }
+        "error" => Err("bad payload".to_string()),
+        _ => panic!("handler bug"),
+    })
+    .unwrap();
+    assert_eq!(processed, 3);
+
+    let messages: Vec<SandboxMessage> = String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            SandboxMessage::Progress { progress: 100 },
+            SandboxMessage::Log { line: "payload of ok".to_string() },
+            SandboxMessage::Completed,
+            SandboxMessage::Failed { reason: "bad payload".to_string(), stack: None },
+            SandboxMessage::Failed { reason: "handler panicked: handler bug".to_string(), stack: None },
+        ]
+    );
+}
+
+/// Test that a sandbox process outliving the job timeout is killed and replaced.
+#[tokio::test]
+async fn test_sandbox_timeout_kills_process() {
+    let mut service = InMemoryQueueService::new();
+    service.add_job("testQueue", JobData { timeout: Some(200), ..job("job_1") }).await.unwrap();
+    service.add_job("testQueue", job("job_2")).await.unwrap();
+
+    let child = r#"while read -r line; do case "$line" in *job_1*) sleep 5;; esac; echo '{"type":"completed"}'; done"#;
+    let trigger = QueueTriggerService::new("testQueue".to_string()).with_sandbox(SandboxOptions::new(child));
+    let started = Instant::now();
+    assert_eq!(trigger.run(&mut service, 50, Some(2)).await, 2);
+    assert!(started.elapsed().as_secs() < 3, "{:?}", started.elapsed());
+
+    let failed = service.jobs("testQueue", JobState::Failed);
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].id, "job_1");
+    assert_eq!(failed[0].failed_reason.as_deref(), Some("timed out after 200ms"));
+}
+
+/// Test that the sandboxed worker reports the depth of its queue to Prometheus.
+#[cfg(feature = "prometheus")]
+#[tokio::test]
+async fn test_sandbox_reports_queue_depth() {
+    let mut service = InMemoryQueueService::new();
+    for id in ["job_1", "job_2", "job_3"] {
+        service.add_job("testQueue", job(id)).await.unwrap();
+    }
+    let prometheus = std::sync::Arc::new(bullmq_rust::prometheus_service::PrometheusService::new().unwrap());
+    let trigger = QueueTriggerService::new("testQueue".to_string())
+        .with_sandbox(SandboxOptions::new(COMPLETING_CHILD))
+        .with_prometheus(prometheus.clone());
+    assert_eq!(trigger.run(&mut service, 50, Some(1)).await, 1);
+
+    let metrics = prometheus.render();
+    assert!(metrics.contains("bullmq_queue_jobs{queue=\"testQueue\",state=\"waiting\"} 3"), "{}", metrics);
+    assert!(metrics.contains("bullmq_queue_jobs{queue=\"testQueue\",state=\"failed\"} 0"), "{}", metrics);
+}
@@ -0,0 +1,126 @@
+#![cfg(feature = "sentinel")]
+
+use bullmq_rust::config_service::ConfigService;
+use bullmq_rust::connection_service::SentinelConnection;
+use bullmq_rust::queue_service::QueueService;
+use bullmq_rust::QueueServiceTrait;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+mod common;
+use common::job;
+
+/// Redis processes killed when the test ends.
+struct Processes(Vec<Child>);
+
+impl Drop for Processes {
+    fn drop(&mut self) {
+        for child in &mut self.0 {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+fn spawn(program: &str, args: &[String]) -> Child {
+    Command::new(program)
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap_or_else(|e| panic!("cannot start {}: {}", program, e))
+}
+
+async fn wait_for_port(port: u16) {
+    for _ in 0..50 {
+        if tokio::net::TcpStream::connect(("127.0.0.1", port)).await.is_ok() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("port {} did not open", port);
+}
+
+/// Test resolving the master through the sentinels and following a failover.
+#[tokio::test]
+#[ignore = "requires the redis-server and redis-sentinel binaries"]
+async fn test_sentinel_failover() {
+    let master_port = free_port();
+    let replica_port = free_port();
+    let sentinel_port = free_port();
+    let dir: PathBuf = std::env::temp_dir().join(format!("bullmq_sentinel_{}", sentinel_port));
+    std::fs::create_dir_all(&dir).unwrap();
+    let sentinel_conf = dir.join("sentinel.conf");
+    std::fs::write(
+        &sentinel_conf,
+        format!(
+            "port {}\ndir {}\nsentinel monitor mymaster 127.0.0.1 {} 1\nsentinel down-after-milliseconds mymaster 1000\nsentinel failover-timeout mymaster 5000\n",
+            sentinel_port,
+            dir.display(),
+            master_port
+        ),
+    )
+    .unwrap();
+
+    let mut processes = Processes(Vec::new());
+    processes.0.push(spawn("redis-server", &["--port".into(), master_port.to_string(), "--save".into(), "".into()]));
+    wait_for_port(master_port).await;
+    processes.0.push(spawn(
+        "redis-server",
+        &[
+            "--port".into(),
+            replica_port.to_string(),
+            "--save".into(),
+            "".into(),
+            "--replicaof".into(),
+            "127.0.0.1".into(),
+            master_port.to_string(),
+        ],
+    ));
+    wait_for_port(replica_port).await;
+    processes.0.push(spawn("redis-sentinel", &[sentinel_conf.display().to_string()]));
+    wait_for_port(sentinel_port).await;
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    let sentinel_url = format!("redis://127.0.0.1:{}", sentinel_port);
+    let config = ConfigService {
+        sentinels: vec![sentinel_url.clone()],
+        sentinel_master: Some("mymaster".to_string()),
+        ..Default::default()
+    };
+    config.validate().unwrap();
+    let mut queue_service = QueueService::new(config.connect().await.unwrap());
+
+    queue_service.add_job("sentinelQueue", job("before_failover")).await.unwrap();
+
+    let mut sentinel = redis::Client::open(sentinel_url).unwrap().get_multiplexed_async_connection().await.unwrap();
+    let _: () = redis::cmd("SENTINEL").arg("FAILOVER").arg("mymaster").query_async(&mut sentinel).await.unwrap();
+    for _ in 0..100 {
+        let master: Vec<String> = redis::cmd("SENTINEL")
+            .arg("GET-MASTER-ADDR-BY-NAME")
+            .arg("mymaster")
+            .query_async(&mut sentinel)
+            .await
+            .unwrap();
+        if master[1] == replica_port.to_string() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    // A command cut off by the failover is returned rather than replayed, the next one reaches the new master.
+    let after_failover = job("after_failover");
+    if let Err(e) = queue_service.add_job("sentinelQueue", after_failover.clone()).await {
+        assert!(SentinelConnection::is_failover_error(&e), "{}", e);
+        queue_service.add_job("sentinelQueue", after_failover).await.unwrap();
+    }
+    assert_eq!(queue_service.count_jobs("sentinelQueue").await.unwrap(), 2);
+
+    drop(processes);
+    let _ = std::fs::remove_dir_all(dir);
+}
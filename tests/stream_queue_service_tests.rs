@@ -0,0 +1,72 @@
+use bullmq_rust::config_service::ConfigService;
+use bullmq_rust::job_model::{JobData, JobState};
+use bullmq_rust::stream_queue_service::StreamQueueService;
+use bullmq_rust::QueueServiceTrait;
+mod common;
+use common::{job, job_id};
+
+async fn service(consumer: &str, prefix: &str) -> StreamQueueService {
+    let config = ConfigService::load().unwrap();
+    StreamQueueService::new(config.connect().await.unwrap())
+        .with_key_prefix(prefix)
+        .with_consumer(consumer)
+        .with_claim_idle_ms(200)
+}
+
+/// Test fetching in insertion order, acknowledging from another handle, and replaying a job left pending by a crashed consumer.
+#[tokio::test]
+#[ignore = "requires a Redis server at REDIS_URL"]
+async fn test_stream_consumer_group() {
+    let prefix = format!("bullmq_stream_test_{}", std::process::id());
+    let mut producer = service("producer", &prefix).await;
+    let mut crashed = service("crashed", &prefix).await;
+    let mut worker = service("worker", &prefix).await;
+
+    producer.add_job("streamQueue", job("job_1")).await.unwrap();
+    producer.add_job("streamQueue", job("job_2")).await.unwrap();
+    producer.add_job("streamQueue", JobData { delay: Some(3600), ..job("job_3") }).await.unwrap();
+    assert_eq!(producer.count_jobs("streamQueue").await.unwrap(), 3);
+
+    assert_eq!(job_id(crashed.get_next_job("streamQueue").await.unwrap()), "job_1");
+    let next_job = worker.get_next_job("streamQueue").await.unwrap();
+    let job_2: JobData = serde_json::from_str(&next_job.as_ref().unwrap()[0]).unwrap();
+    assert_eq!(job_2.id, "job_2");
+    // Any handle can acknowledge a job, not only the one that fetched it.
+    producer.mark_completed("streamQueue", &job_2, 5).await.unwrap();
+    assert_eq!(producer.count_jobs("streamQueue").await.unwrap(), 1);
+
+    // job_1 was never acknowledged, it is replayed once idle for the claim time.
+    let replayed = worker.wait_for_next_job("streamQueue", 2.0).await.unwrap();
+    let job_1: JobData = serde_json::from_str(&replayed.unwrap()[0]).unwrap();
+    assert_eq!(job_1.id, "job_1");
+    worker.move_to_failed("streamQueue", job_1).await.unwrap();
+    assert_eq!(worker.count_jobs_by_state("streamQueue", JobState::Failed).await.unwrap(), 1);
+
+    assert!(worker.wait_for_next_job("streamQueue", 0.3).await.unwrap().is_none());
+    assert!(worker.remove_job("streamQueue", "job_3").await.unwrap());
+    assert!(worker.remove_job("streamQueue", "job_1").await.unwrap());
+    assert_eq!(producer.count_jobs("streamQueue").await.unwrap(), 0);
+}
+
+/// Test that cleaning without a grace period removes the jobs just added but keeps the ones being processed
+/// and the ones not due yet, and that listing pages through the stream then the delayed jobs.
+#[tokio::test]
+#[ignore = "requires a Redis server at REDIS_URL"]
+async fn test_stream_clean_and_list() {
+    let prefix = format!("bullmq_stream_clean_test_{}", std::process::id());
+    let mut service = service("worker", &prefix).await;
+
+    for id in ["job_1", "job_2", "job_3"] {
+        service.add_job("cleanQueue", job(id)).await.unwrap();
+    }
+    service.add_job("cleanQueue", JobData { delay: Some(3600), ..job("job_4") }).await.unwrap();
+    let listed: Vec<String> = service.list_jobs("cleanQueue", JobState::Waiting, 2, 2).await.unwrap().into_iter().map(|job| job.id).collect();
+    assert_eq!(listed, ["job_3", "job_4"]);
+
+    assert_eq!(job_id(service.get_next_job("cleanQueue").await.unwrap()), "job_1");
+    assert_eq!(service.clean_jobs("cleanQueue", JobState::Waiting, 0).await.unwrap(), 2);
+    let listed: Vec<String> = service.list_jobs("cleanQueue", JobState::Waiting, 0, 10).await.unwrap().into_iter().map(|job| job.id).collect();
+    assert_eq!(listed, ["job_1", "job_4"]);
+    assert!(service.remove_job("cleanQueue", "job_1").await.unwrap());
+    assert!(service.remove_job("cleanQueue", "job_4").await.unwrap());
+}
@@ -0,0 +1,109 @@
+#![cfg(all(feature = "tui", feature = "test-util"))]
+
+use bullmq_rust::in_memory_queue_service::InMemoryQueueService;
+use bullmq_rust::job_model::{JobData, JobState};
+use bullmq_rust::top_service::{ActiveJob, QueueDepth, Top, TopSnapshot};
+use bullmq_rust::QueueServiceTrait;
+use ratatui::backend::TestBackend;
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::Terminal;
+mod common;
+use common::job;
+
+/// Fills a queue with a waiting job, an active job with progress, a completed job and a failure.
+async fn service() -> InMemoryQueueService {
+    let mut service = InMemoryQueueService::new();
+    service.add_job("emails", job("job_1")).await.unwrap();
+    for (id, status) in [("job_2", "active"), ("job_3", "active"), ("job_3", "completed")] {
+        service.log_job_status("emails", &job(id), status).await.unwrap();
+    }
+    service.update_job_progress("emails", "job_2", 40).await.unwrap();
+    let failed = JobData { attempts_made: Some(2), failed_reason: Some("boom".to_string()), ..job("job_4") };
+    service.move_to_failed("emails", failed).await.unwrap();
+    service.add_job("reports", job("job_5")).await.unwrap();
+    service
+}
+
+/// Test that the snapshot holds the depths, the jobs whose last status is active, the failures and the log.
+#[tokio::test]
+async fn test_snapshot() {
+    let mut service = service().await;
+    let queues = vec!["emails".to_string(), "reports".to_string()];
+    let snapshot = TopSnapshot::read(&mut service, &queues, Some("emails")).await.unwrap();
+
+    assert_eq!(
+        snapshot.queues,
+        [
+            QueueDepth { queue: "emails".to_string(), waiting: 1, failed: 1, paused: false },
+            QueueDepth { queue: "reports".to_string(), waiting: 1, failed: 0, paused: false },
+        ]
+    );
+    assert_eq!(snapshot.active.len(), 1);
+    let ActiveJob { id, progress, .. } = &snapshot.active[0];
+    assert_eq!((id.as_str(), *progress), ("job_2", Some(40)));
+    assert_eq!(snapshot.failures.len(), 1);
+    assert_eq!(snapshot.failures[0].failed_reason.as_deref(), Some("boom"));
+    let events: Vec<&str> = snapshot.log.iter().map(|entry| entry.event.as_str()).collect();
+    assert_eq!(events, ["completed", "active", "active"]);
+
+    let snapshot = TopSnapshot::read(&mut service, &queues, None).await.unwrap();
+    assert!(snapshot.active.is_empty() && snapshot.log.is_empty());
+}
+
+/// Test that the keyboard shortcuts select jobs and retry, remove or pause them.
+#[tokio::test]
+async fn test_keyboard_shortcuts() {
+    let mut service = service().await;
+    let mut top = Top::new(Vec::new());
+    top.refresh(&mut service).await.unwrap();
+    assert_eq!(top.snapshot().queues.len(), 2);
+    assert_eq!(top.selected_queue(), Some("emails"));
+    assert_eq!(top.selected_job(), Some("job_4"));
+
+    assert!(top.handle_key(&mut service, KeyEvent::from(KeyCode::Char('r'))).await);
+    assert_eq!(top.status(), "retried job_4");
+    assert_eq!(service.count_jobs_by_state("emails", JobState::Failed).await.unwrap(), 0);
+    assert_eq!(top.selected_job(), None);
+
+    assert!(top.handle_key(&mut service, KeyEvent::from(KeyCode::Tab)).await);
+    assert_eq!(top.selected_job(), Some("job_2"));
+    assert!(top.handle_key(&mut service, KeyEvent::from(KeyCode::Char('r'))).await);
+    assert_eq!(top.status(), "job_2 is not a failed job");
+
+    assert!(top.handle_key(&mut service, KeyEvent::from(KeyCode::Right)).await);
+    assert_eq!(top.selected_queue(), Some("reports"));
+    assert!(top.handle_key(&mut service, KeyEvent::from(KeyCode::Char('p'))).await);
+    assert!(service.is_paused("reports").await.unwrap());
+    assert!(top.snapshot().queues[1].paused);
+
+    assert!(top.handle_key(&mut service, KeyEvent::from(KeyCode::Left)).await);
+    service.move_to_failed("emails", job("job_6")).await.unwrap();
+    assert!(top.handle_key(&mut service, KeyEvent::from(KeyCode::Tab)).await);
+    assert_eq!(top.selected_job(), Some("job_6"));
+    assert!(top.handle_key(&mut service, KeyEvent::from(KeyCode::Delete)).await);
+    assert_eq!(top.status(), "removed job_6");
+    assert_eq!(service.count_jobs_by_state("emails", JobState::Failed).await.unwrap(), 0);
+
+    assert!(!top.handle_key(&mut service, KeyEvent::from(KeyCode::Char('q'))).await);
+}
+
+/// Test that the UI draws the queues, the active jobs with their progress and the failure reasons.
+#[tokio::test]
+async fn test_render() {
+    let mut service = service().await;
+    let mut top = Top::new(vec!["emails".to_string()]);
+    top.refresh(&mut service).await.unwrap();
+
+    let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+    terminal.draw(|frame| top.render(frame)).unwrap();
+    let screen: String = terminal
+        .backend()
+        .buffer()
+        .content()
+        .chunks(100)
+        .map(|line| line.iter().map(|cell| cell.symbol()).collect::<String>() + "\n")
+        .collect();
+    for needle in ["emails", "job_2", "[####......]  40%", "job_4", "boom", "completed", "q quit"] {
+        assert!(screen.contains(needle), "{} not found in\n{}", needle, screen);
+    }
+}
@@ -0,0 +1,96 @@
+#![cfg(feature = "otel")]
+
+use bullmq_rust::in_memory_queue_service::InMemoryQueueService;
+use bullmq_rust::job_model::{JobData, JobState};
+use bullmq_rust::tracing_service::{inject_trace_context, job_span, TracedQueueService};
+use bullmq_rust::QueueServiceTrait;
+use opentelemetry::trace::{TraceContextExt, TracerProvider};
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use tracing::Instrument;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::layer::SubscriberExt;
+mod common;
+use common::job;
+
+/// Test that the producer trace context is injected into the job and picked up by the consumer span.
+#[test]
+fn test_trace_context_propagation() {
+    let provider = SdkTracerProvider::builder().build();
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("bullmq_rust")));
+
+    tracing::subscriber::with_default(subscriber, || {
+        let mut job = JobData {
+            id: "traced".to_string(),
+            message: "Traced Job".to_string(),
+            ..Default::default()
+        };
+
+        let producer_span = tracing::info_span!("producer");
+        let producer_trace_id = producer_span.context().span().span_context().trace_id();
+        producer_span.in_scope(|| inject_trace_context(&mut job));
+
+        let trace_context = job.trace_context.clone().unwrap();
+        assert!(trace_context["traceparent"].contains(&producer_trace_id.to_string()));
+
+        let consumer_span = job_span("testQueue", &job);
+        assert_eq!(consumer_span.context().span().span_context().trace_id(), producer_trace_id);
+    });
+}
+
+/// Test that an existing trace context is kept when the job is re-queued.
+#[test]
+fn test_existing_trace_context_is_kept() {
+    let mut job = JobData {
+        id: "traced".to_string(),
+        trace_context: Some([("traceparent".to_string(), "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01".to_string())].into()),
+        ..Default::default()
+    };
+    let expected = job.trace_context.clone();
+
+    inject_trace_context(&mut job);
+    assert_eq!(job.trace_context, expected);
+}
+
+/// Test that the jobs added through the traced service carry the trace of their producer, whatever the backend.
+#[tokio::test]
+async fn test_traced_queue_service() {
+    let provider = SdkTracerProvider::builder().build();
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("bullmq_rust")));
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let backend = InMemoryQueueService::new();
+    let mut service = TracedQueueService::new(Box::new(backend.clone()));
+    let producer_span = tracing::info_span!("producer");
+    let producer_trace_id = producer_span.context().span().span_context().trace_id().to_string();
+    async {
+        service.add_job("testQueue", job("single")).await.unwrap();
+        service.add_jobs("testQueue", vec![job("bulk_1"), job("bulk_2")]).await.unwrap();
+    }
+    .instrument(producer_span)
+    .await;
+
+    let stored = backend.jobs("testQueue", JobState::Waiting);
+    assert_eq!(stored.len(), 3);
+    for job in stored {
+        assert!(job.trace_context.unwrap()["traceparent"].contains(&producer_trace_id), "{}", job.id);
+    }
+}
+
+/// Test that a job added outside of any span still gets a trace context, from the `produce` span.
+#[tokio::test]
+async fn test_traced_queue_service_without_parent_span() {
+    let provider = SdkTracerProvider::builder().build();
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("bullmq_rust")));
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let backend = InMemoryQueueService::new();
+    let mut service = TracedQueueService::new(Box::new(backend.clone()));
+    service.add_job("testQueue", job("orphan")).await.unwrap();
+
+    let stored = backend.jobs("testQueue", JobState::Waiting);
+    let trace_context = stored[0].trace_context.clone().unwrap();
+    assert!(!trace_context["traceparent"].is_empty());
+}
@@ -0,0 +1,160 @@
+#![cfg(all(feature = "webhook", feature = "test-util"))]
+
+use bullmq_rust::in_memory_queue_service::InMemoryQueueService;
+use bullmq_rust::job_model::{JobData, JobState};
+use bullmq_rust::queue_trigger_service::QueueTriggerService;
+use bullmq_rust::webhook_service::{sign, WebhookOutcome, WebhookService};
+use bullmq_rust::QueueServiceTrait;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+mod common;
+use common::job;
+
+/// A request received by the stand-in server.
+struct Request {
+    head: String,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.head.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.eq_ignore_ascii_case(name).then(|| value.trim())
+        })
+    }
+}
+
+/// Starts a stand-in HTTP server answering each request with the next of the given raw responses,
+/// or never answering once they are exhausted.
+async fn serve(responses: Vec<&'static str>) -> (String, mpsc::UnboundedReceiver<Request>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/jobs", listener.local_addr().unwrap());
+    let (sender, receiver) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let mut responses = responses.into_iter();
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buffer = Vec::new();
+            let mut chunk = [0u8; 4096];
+            let head_end = loop {
+                let read = stream.read(&mut chunk).await.unwrap();
+                buffer.extend_from_slice(&chunk[..read]);
+                if let Some(position) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+                    break position + 4;
+                }
+            };
+            let head = String::from_utf8_lossy(&buffer[..head_end]).to_string();
+            let mut request = Request { head, body: Vec::new() };
+            let length: usize = request.header("content-length").map_or(0, |length| length.parse().unwrap());
+            while buffer.len() < head_end + length {
+                let read = stream.read(&mut chunk).await.unwrap();
+                buffer.extend_from_slice(&chunk[..read]);
+            }
+            request.body = buffer[head_end..head_end + length].to_vec();
+            sender.send(request).unwrap();
+            match responses.next() {
+                Some(response) => {
+                    stream.write_all(response.as_bytes()).await.unwrap();
+                    stream.shutdown().await.unwrap();
+                }
+                None => {
+                    tokio::spawn(async move {
+                        tokio::time::sleep(Duration::from_secs(60)).await;
+                        drop(stream);
+                    });
+                }
+            }
+        }
+    });
+    (url, receiver)
+}
+
+const OK: &str = "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+
+/// Test that a job is POSTed as signed JSON with the configured and job headers.
+#[tokio::test]
+async fn test_forward_signed_job() {
+    let (url, mut requests) = serve(vec![OK]).await;
+    let webhook = WebhookService::new(&url)
+        .with_header("Authorization: Bearer token")
+        .unwrap()
+        .with_signing_secret("secret");
+    let outcome = webhook.forward("testQueue", &job("job_1")).await;
+    assert_eq!(outcome, WebhookOutcome::Delivered);
+
+    let request = requests.recv().await.unwrap();
+    assert!(request.head.starts_with("POST /jobs HTTP/1.1"));
+    assert_eq!(request.header("authorization"), Some("Bearer token"));
+    assert_eq!(request.header("content-type"), Some("application/json"));
+    assert_eq!(request.header("x-bullmq-queue"), Some("testQueue"));
+    assert_eq!(request.header("x-bullmq-job-id"), Some("job_1"));
+    assert_eq!(request.header("x-bullmq-attempt"), Some("1"));
+    let signature = sign(b"secret", &request.body);
+    assert_eq!(request.header("x-bullmq-signature"), Some(signature.as_str()));
+    assert_eq!(serde_json::from_slice::<JobData>(&request.body).unwrap().id, "job_1");
+
+    assert!(WebhookService::new(&url).with_header("no colon").is_err());
+}
+
+/// Test how the response statuses, a long response body and a missing response are reported.
+#[tokio::test]
+async fn test_forward_outcomes() {
+    let (url, _requests) = serve(vec![
+        "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 7\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        "HTTP/1.1 400 Bad Request\r\nContent-Length: 11\r\nConnection: close\r\n\r\nbad payload",
+        Box::leak(format!("HTTP/1.1 500 Internal Server Error\r\nContent-Length: 2000\r\nConnection: close\r\n\r\n{}", "x".repeat(2000)).into_boxed_str()),
+    ])
+    .await;
+    let webhook = WebhookService::new(&url).with_timeout(Duration::from_millis(200));
+    let job = job("job_1");
+
+    match webhook.forward("testQueue", &job).await {
+        WebhookOutcome::Backoff { retry_after, .. } => assert_eq!(retry_after, Some(Duration::from_secs(7))),
+        outcome => panic!("unexpected outcome {:?}", outcome),
+    }
+    assert!(matches!(webhook.forward("testQueue", &job).await, WebhookOutcome::Backoff { retry_after: None, .. }));
+    match webhook.forward("testQueue", &job).await {
+        WebhookOutcome::Failed(reason) => assert_eq!(reason, "endpoint answered 400 Bad Request: bad payload"),
+        outcome => panic!("unexpected outcome {:?}", outcome),
+    }
+    match webhook.forward("testQueue", &job).await {
+        WebhookOutcome::Failed(reason) => {
+            assert_eq!(reason, format!("endpoint answered 500 Internal Server Error: {}…", "x".repeat(512)))
+        }
+        outcome => panic!("unexpected outcome {:?}", outcome),
+    }
+    match webhook.forward("testQueue", &job).await {
+        WebhookOutcome::Failed(reason) => assert!(reason.starts_with("no response from"), "{}", reason),
+        outcome => panic!("unexpected outcome {:?}", outcome),
+    }
+}
+
+/// Test that the worker postpones a job on back-off without consuming an attempt, and fails it on other statuses.
+#[tokio::test]
+async fn test_trigger_forwarding() {
+    let (url, mut requests) = serve(vec![
+        "HTTP/1.1 503 Service Unavailable\r\nRetry-After: 0\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        OK,
+        "HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+    ])
+    .await;
+    let mut service = InMemoryQueueService::new();
+    let trigger = QueueTriggerService::new("testQueue".to_string()).with_webhook(WebhookService::new(&url));
+
+    service.add_job("testQueue", job("job_1")).await.unwrap();
+    assert_eq!(trigger.run(&mut service, 50, Some(2)).await, 2);
+    assert_eq!(requests.recv().await.unwrap().header("x-bullmq-attempt"), Some("1"));
+    assert_eq!(requests.recv().await.unwrap().header("x-bullmq-attempt"), Some("1"));
+    assert_eq!(service.count_jobs("testQueue").await.unwrap(), 0);
+    let lines = service.get_job_logs("testQueue", "job_1", 0, -1).await.unwrap();
+    assert_eq!(lines, ["postponed by 0s: endpoint answered 503 Service Unavailable"]);
+
+    service.add_job("testQueue", job("job_2")).await.unwrap();
+    assert_eq!(trigger.run(&mut service, 50, Some(1)).await, 1);
+    let failed = service.jobs("testQueue", JobState::Failed);
+    assert_eq!(failed[0].failed_reason.as_deref(), Some("endpoint answered 500 Internal Server Error"));
+}